@@ -0,0 +1,31 @@
+//! Generates large specs for the benchmarks
+
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+
+/// Number of entries in the large spec, in the range of the biggest specs seen in practice
+pub const LARGE: usize = 10_000;
+
+/// Spec with `entries` declarations, split evenly between Secrets Manager secrets,
+/// Parameter Store parameters and plain defaults. Secret IDs use the `$env` placeholder.
+pub fn spec(entries: usize) -> String {
+    let mut spec = String::new();
+
+    for i in 0..entries {
+        match i % 3 {
+            0 => writeln!(spec, "# @aws-sm $env/service-{}/credentials", i),
+            1 => writeln!(spec, "# @aws-ps /$env/app/param-{} @optional", i),
+            _ => writeln!(spec, "# Plain value {}", i),
+        }
+        .unwrap();
+
+        writeln!(spec, "KEY_{}=\"default value {}\"\n", i, i).unwrap();
+    }
+
+    spec
+}
+
+pub fn placeholders() -> IndexMap<String, String> {
+    IndexMap::from([("env".to_string(), "production".to_string())])
+}
@@ -0,0 +1,134 @@
+//! Benchmarks for parsing, placeholder substitution, formatting and resolution on large
+//! specs. Run with `cargo bench`, criterion reports changes against the previous run.
+
+mod fixture;
+
+use std::hint::black_box;
+
+use anyhow::Result;
+use awsm_env::{
+    output::{EnvOutput, JsonOutput, Output, ShellOutput},
+    parser::EnvParser,
+    provider::{Provider, SecretMetadata, SecretResult, SecretVersion},
+    resolve::{replace_placeholders, resolve_with_providers},
+    variable::Variables,
+};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+
+/// Resolves every secret to its ID without any network calls
+struct MockProvider;
+
+impl Provider for MockProvider {
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+        Ok(ids
+            .into_iter()
+            .map(|id| SecretResult::Found {
+                value: id,
+                version: Some("v1".to_string()),
+                metadata: SecretMetadata::default(),
+            })
+            .collect())
+    }
+
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>> {
+        self.provide_secrets(ids.into_iter().map(|(id, _)| id).collect())
+            .await
+    }
+}
+
+fn large_variables() -> Variables {
+    EnvParser::parse_variables(&fixture::spec(fixture::LARGE)).unwrap()
+}
+
+fn parse(c: &mut Criterion) {
+    let spec = fixture::spec(fixture::LARGE);
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(fixture::LARGE as u64));
+    group.bench_function("large", |b| {
+        b.iter(|| EnvParser::parse_variables(black_box(&spec)).unwrap())
+    });
+    group.finish();
+}
+
+fn placeholders(c: &mut Criterion) {
+    let variables = large_variables();
+    let placeholders = fixture::placeholders();
+    let ids = variables
+        .iter()
+        .filter_map(|var| var.provider_config.as_ref())
+        .map(|config| config.id())
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("replace_placeholders");
+    group.throughput(Throughput::Elements(ids.len() as u64));
+    group.bench_function("large", |b| {
+        b.iter(|| {
+            for id in &ids {
+                black_box(replace_placeholders(black_box(id), &placeholders).unwrap());
+            }
+        })
+    });
+    group.bench_function("without_placeholders", |b| {
+        let ids = ids.iter().map(|id| id.replace('$', "")).collect::<Vec<_>>();
+
+        b.iter(|| {
+            for id in &ids {
+                black_box(replace_placeholders(black_box(id), &placeholders).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn formatters(c: &mut Criterion) {
+    let outputs: [(&str, &dyn Output); 3] = [
+        ("env", &EnvOutput),
+        ("shell", &ShellOutput),
+        ("json", &JsonOutput),
+    ];
+
+    let mut group = c.benchmark_group("format");
+    group.throughput(Throughput::Elements(fixture::LARGE as u64));
+    for (name, output) in outputs {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                large_variables,
+                |variables| output.format(variables).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn resolve(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("resolve");
+    group.throughput(Throughput::Elements(fixture::LARGE as u64));
+    group.bench_function("mocked_providers", |b| {
+        b.iter_batched(
+            large_variables,
+            |mut variables| {
+                runtime
+                    .block_on(resolve_with_providers(
+                        &mut variables,
+                        fixture::placeholders(),
+                        &MockProvider,
+                        &MockProvider,
+                    ))
+                    .unwrap();
+                variables
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, placeholders, formatters, resolve);
+criterion_main!(benches);
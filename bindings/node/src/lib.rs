@@ -0,0 +1,41 @@
+//! Node.js bindings exposing the spec parser and secret resolution, so services can
+//! resolve their environment in-process instead of spawning the CLI
+
+use std::collections::HashMap;
+
+use awsm_env::{output::spec_entries, parser::EnvParser, resolve::resolve as resolve_secrets};
+use indexmap::IndexMap;
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::Value;
+
+fn to_napi_error(err: anyhow::Error) -> Error {
+    Error::from_reason(format!("{:#}", err))
+}
+
+/// Parses a spec, returning its entries as
+/// `{key, required, default, provider, id, tags, ttl, description, deprecated}` objects
+#[napi(
+    ts_return_type = "Array<{ key: string, required: boolean, default: string | null, provider: string | null, id: string | null, tags: Record<string, string> }>"
+)]
+pub fn parse(spec: String) -> Result<Value> {
+    let variables = EnvParser::parse_variables(&spec).map_err(to_napi_error)?;
+    Ok(spec_entries(&variables))
+}
+
+/// Parses a spec and fetches its secrets, resolving to an object of the form
+/// `{KEY: "value"}` in spec order
+#[napi(ts_return_type = "Promise<Record<string, string>>")]
+pub async fn resolve(spec: String, placeholders: Option<HashMap<String, String>>) -> Result<Value> {
+    let mut variables = EnvParser::parse_variables(&spec).map_err(to_napi_error)?;
+    let placeholders = placeholders.unwrap_or_default().into_iter().collect();
+
+    resolve_secrets(&mut variables, placeholders)
+        .await
+        .map_err(to_napi_error)?;
+
+    variables.drop_empty();
+    let values: IndexMap<String, String> = variables.into();
+
+    serde_json::to_value(values).map_err(|err| Error::from_reason(err.to_string()))
+}
@@ -0,0 +1,19 @@
+use std::{env, process::Command};
+
+fn main() {
+    let git_sha = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=AWSM_ENV_GIT_SHA={}", git_sha.trim());
+    println!(
+        "cargo:rustc-env=AWSM_ENV_TARGET={}",
+        env::var("TARGET").expect("cargo sets TARGET for build scripts")
+    );
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,35 @@
+/*
+ * C interface to awsm-env, built with `cargo build --release --features ffi`.
+ *
+ * Strings are UTF-8 and NUL-terminated. Strings returned by these functions
+ * must be released with awsm_env_free. On error, functions return NULL and
+ * awsm_env_last_error describes the failure.
+ */
+
+#ifndef AWSM_ENV_H
+#define AWSM_ENV_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Parses a spec, returning its entries as a JSON array of
+ * {key, required, default, provider, id, tags, ttl, description, deprecated} objects. */
+char *awsm_env_parse(const char *spec);
+
+/* Parses a spec and fetches its secrets, returning a JSON object of the form
+ * {"KEY": "value"}. `placeholders` is NULL or a JSON object of placeholder values. */
+char *awsm_env_resolve(const char *spec, const char *placeholders);
+
+/* Error message of the last failed call on the calling thread, or NULL.
+ * Owned by the library, valid until the next call on the same thread. */
+const char *awsm_env_last_error(void);
+
+/* Frees a string returned by this library. */
+void awsm_env_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AWSM_ENV_H */
@@ -0,0 +1,177 @@
+//! Account lists for `--accounts`, rendering the same spec once per AWS account
+//!
+//! ```yaml
+//! accounts:
+//!   - alias: payments-prod
+//!     id: "123456789012"
+//!     role_arn: arn:aws:iam::123456789012:role/secrets-reader
+//!     region: eu-west-1
+//!     placeholders:
+//!       environment: production
+//! ```
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::{Deserialize, Deserializer};
+
+use crate::provider::AwsAccount;
+
+/// Placeholder set to the account's alias
+pub const ALIAS_PLACEHOLDER: &str = "account_alias";
+
+/// Placeholder set to the account's ID, when it's given
+pub const ID_PLACEHOLDER: &str = "account_id";
+
+/// An account the spec is rendered for
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Account {
+    /// Name of the account, also used in output paths
+    pub alias: String,
+    #[serde(default, deserialize_with = "deserialize_id")]
+    pub id: Option<String>,
+    /// Profile to load credentials and region from
+    pub profile: Option<String>,
+    /// Role to assume, with the profile's credentials or those of the default chain
+    pub role_arn: Option<String>,
+    pub region: Option<String>,
+    /// Placeholders used only for this account
+    #[serde(default)]
+    pub placeholders: IndexMap<String, String>,
+}
+
+impl Account {
+    pub fn aws_account(&self) -> AwsAccount {
+        AwsAccount {
+            profile: self.profile.clone(),
+            role_arn: self.role_arn.clone(),
+            region: self.region.clone(),
+        }
+    }
+
+    /// `$account_alias` and `$account_id`, set without being listed under `placeholders`
+    pub fn implicit_placeholders(&self) -> IndexMap<String, String> {
+        [
+            (ALIAS_PLACEHOLDER, Some(&self.alias)),
+            (ID_PLACEHOLDER, self.id.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value?.clone())))
+        .collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountsFile {
+    accounts: Vec<Account>,
+}
+
+/// Account IDs are often written unquoted, which YAML reads as a number
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Text(String),
+        Number(u64),
+    }
+
+    Ok(Option::<Id>::deserialize(deserializer)?.map(|id| match id {
+        Id::Text(id) => id,
+        // IDs are always 12 digits, leading zeros are lost when read as a number
+        Id::Number(id) => format!("{:012}", id),
+    }))
+}
+
+/// Parses an accounts file, YAML or JSON, checking that aliases are unique and usable
+/// in file names
+pub fn parse_accounts(input: &str) -> Result<Vec<Account>> {
+    let file: AccountsFile = serde_yaml::from_str(input)?;
+
+    if file.accounts.is_empty() {
+        return Err(anyhow!("No accounts are listed"));
+    }
+
+    if let Some(alias) = file
+        .accounts
+        .iter()
+        .map(|account| account.alias.as_str())
+        .find(|alias| !is_valid_alias(alias))
+    {
+        return Err(anyhow!(
+            "Invalid account alias {:?}, use letters, digits, `-`, `_` and `.`",
+            alias
+        ));
+    }
+
+    let duplicates = file
+        .accounts
+        .iter()
+        .map(|account| account.alias.as_str())
+        .duplicates()
+        .collect::<Vec<_>>();
+    if !duplicates.is_empty() {
+        return Err(anyhow!(
+            "Account aliases are listed more than once: {}",
+            duplicates.join(", ")
+        ));
+    }
+
+    Ok(file.accounts)
+}
+
+pub fn load_accounts(path: &Path) -> Result<Vec<Account>> {
+    let input = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read accounts file {}", path.display()))?;
+
+    parse_accounts(&input)
+        .with_context(|| format!("Failed to parse accounts file {}", path.display()))
+}
+
+fn is_valid_alias(alias: &str) -> bool {
+    !alias.is_empty()
+        && alias != "."
+        && alias != ".."
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accounts() {
+        let accounts = parse_accounts(
+            "accounts:\n  - alias: prod\n    id: 012345678901\n    role_arn: arn:aws:iam::012345678901:role/reader\n    placeholders:\n      environment: production\n  - alias: dev\n    profile: dev\n    region: eu-west-1\n",
+        )
+        .unwrap();
+
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(
+            accounts[0].implicit_placeholders(),
+            IndexMap::from([
+                ("account_alias".to_string(), "prod".to_string()),
+                ("account_id".to_string(), "012345678901".to_string()),
+            ])
+        );
+        assert_eq!(accounts[0].placeholders["environment"], "production");
+        assert_eq!(
+            accounts[1].aws_account(),
+            AwsAccount {
+                profile: Some("dev".to_string()),
+                role_arn: None,
+                region: Some("eu-west-1".to_string()),
+            }
+        );
+
+        assert!(parse_accounts("accounts: []\n").is_err());
+        assert!(parse_accounts("accounts:\n  - alias: ../prod\n").is_err());
+        assert!(parse_accounts("accounts:\n  - alias: a\n  - alias: a\n").is_err());
+        assert!(parse_accounts("accounts:\n  - alias: a\n    role: x\n").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Structured records of secret accesses, e.g. as evidence for compliance audits
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A single secret access. Never includes the value.
+/// See [`Schema::Audit`](crate::schema::Schema::Audit).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: String,
+    /// ARN of the AWS principal making the request, `None` if it couldn't be determined
+    pub identity: Option<String>,
+    pub key: String,
+    pub provider: String,
+    /// Secret ID after placeholder substitution
+    pub id: String,
+    pub version: Option<String>,
+    pub outcome: AuditOutcome,
+    /// Why the access failed, only set for [`AuditOutcome::Error`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(key: &str, provider: &str, id: &str, outcome: AuditOutcome) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            identity: None,
+            key: key.to_string(),
+            provider: provider.to_string(),
+            id: id.to_string(),
+            version: None,
+            outcome,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Resolved,
+    /// The provider didn't return the secret, e.g. an optional entry that doesn't exist
+    NotFound,
+    /// The caller isn't allowed to read the secret
+    AccessDenied,
+    Error,
+}
+
+/// Receives a record for every secret access during resolution. Resolution fails if
+/// a record can't be stored.
+pub trait AuditSink {
+    fn record(&self, record: &AuditRecord) -> Result<()>;
+}
+
+impl<F: Fn(&AuditRecord) -> Result<()>> AuditSink for F {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        self(record)
+    }
+}
+
+/// Appends records to a file as JSON lines
+pub struct AuditLog(File);
+
+impl AuditLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+
+        Ok(Self(file))
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        // A single write per record keeps lines intact when several processes append
+        (&self.0)
+            .write_all(&line)
+            .context("Failed to write to audit log")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn test_audit_log_appends_json_lines() {
+        let path = env::temp_dir().join(format!("awsm-env-audit-{}.jsonl", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut record = AuditRecord::new("KEY1", "aws-sm", "prod/key1", AuditOutcome::Resolved);
+        record.timestamp = "2025-01-01T00:00:00Z".to_string();
+        record.version = Some("v1".to_string());
+
+        AuditLog::open(&path).unwrap().record(&record).unwrap();
+        AuditLog::open(&path).unwrap().record(&record).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let line = r#"{"timestamp":"2025-01-01T00:00:00Z","identity":null,"key":"KEY1","provider":"aws-sm","id":"prod/key1","version":"v1","outcome":"resolved"}"#;
+        assert_eq!(contents, format!("{}\n{}\n", line, line));
+
+        let _ = fs::remove_file(&path);
+    }
+}
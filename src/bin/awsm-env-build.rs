@@ -0,0 +1,65 @@
+//! Cargo runner starting `cargo run` and `cargo test` binaries with the values resolved
+//! from a spec in their environment
+
+use std::{
+    io,
+    process::{Command, ExitCode},
+};
+
+use anyhow::{Context, Result};
+use awsm_env::{
+    cli::{AwsArgs, ResolveArgs, Settings},
+    config::load_trusted_keys,
+    term,
+};
+use clap::Parser;
+use indexmap::IndexMap;
+
+/// Runs a program with the values resolved from a spec in its environment. Set it as the
+/// runner in `.cargo/config.toml`, followed by `--`
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    #[command(flatten)]
+    resolve: ResolveArgs,
+
+    #[command(flatten)]
+    aws: AwsArgs,
+
+    /// Program to run and its arguments, as passed by cargo
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(&Args::parse()).await {
+        Ok(code) => code,
+        Err(err) => {
+            term::write_error(&mut io::stderr(), &err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: &Args) -> Result<ExitCode> {
+    let settings = Settings {
+        aws: args.aws.options()?,
+        trusted_keys: load_trusted_keys()?,
+        ..Default::default()
+    };
+
+    let mut variables = args.resolve.load(&settings).await?;
+    variables.retain(|var| !var.no_export);
+    variables.drop_empty();
+    let env: IndexMap<String, String> = variables.into();
+
+    let status = Command::new(&args.command[0])
+        .args(&args.command[1..])
+        .envs(env)
+        .status()
+        .with_context(|| format!("Failed to run {}", args.command[0]))?;
+
+    // Killed by a signal when there's no code
+    Ok(ExitCode::from(status.code().unwrap_or(1) as u8))
+}
@@ -0,0 +1,139 @@
+//! Lambda external extension rendering the spec bundled with a function to `/tmp/.env`
+//! before the function starts, and again on invocations once the values are older than
+//! the refresh interval
+
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use awsm_env::{
+    cli::{self, Args as CliArgs},
+    lambda::{Event, ExtensionClient},
+    output::FormatterRegistry,
+    shutdown, term,
+};
+use clap::Parser;
+use itertools::Itertools;
+use tokio_util::sync::CancellationToken;
+
+/// Renders the spec bundled with a Lambda function. Install it in `/opt/extensions` from
+/// a layer, it's configured through the function's environment variables
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Spec to render
+    #[arg(long, env = "AWSM_ENV_SPEC", default_value = "/var/task/.env.example")]
+    spec: PathBuf,
+
+    /// File to write, the only writable location in Lambda is `/tmp`
+    #[arg(long, short, env = "AWSM_ENV_OUTPUT", default_value = "/tmp/.env")]
+    output: PathBuf,
+
+    /// Output format
+    #[arg(
+        long,
+        short,
+        env = "AWSM_ENV_FORMAT",
+        default_value = "env",
+        value_parser = parse_format
+    )]
+    format: String,
+
+    /// Comma separated placeholders, e.g. `environment=prod,region=eu-west-1`
+    #[arg(
+        long = "placeholder",
+        short,
+        env = "AWSM_ENV_PLACEHOLDERS",
+        value_delimiter = ',',
+        value_parser = parse_placeholder
+    )]
+    placeholders: Vec<(String, String)>,
+
+    /// How old the rendered values can get before an invocation renders them again.
+    /// Refreshed values are seen by the invocations after it
+    #[arg(
+        long,
+        env = "AWSM_ENV_REFRESH_INTERVAL",
+        default_value = "5m",
+        value_parser = humantime::parse_duration
+    )]
+    refresh_interval: Duration,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(&Args::parse()).await {
+        Ok(code) => code,
+        Err(err) => {
+            term::write_error(&mut io::stderr(), &err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: &Args) -> Result<ExitCode> {
+    // Lambda identifies extensions by their file name in `/opt/extensions`
+    let exe = env::args().next().unwrap_or_default();
+    let name = Path::new(&exe)
+        .file_name()
+        .ok_or_else(|| anyhow!("Can't determine the extension name"))?
+        .to_string_lossy();
+    let client = ExtensionClient::register(&name).await?;
+    let shutdown = shutdown::on_signal();
+
+    // Failing during init fails the cold start, rather than starting the function
+    // without its environment
+    let code = render(args, &shutdown).await?;
+    if code != ExitCode::SUCCESS {
+        return Ok(code);
+    }
+    let mut rendered_at = Instant::now();
+
+    loop {
+        match client.next_event().await? {
+            Event::Invoke if rendered_at.elapsed() >= args.refresh_interval => {
+                // Keeps serving the previous file when a refresh fails
+                if render(args, &shutdown).await? == ExitCode::SUCCESS {
+                    rendered_at = Instant::now();
+                }
+            }
+            Event::Invoke => {}
+            Event::Shutdown => return Ok(ExitCode::SUCCESS),
+        }
+    }
+}
+
+async fn render(args: &Args, shutdown: &CancellationToken) -> Result<ExitCode> {
+    let mut cli_args = CliArgs::defaults()?;
+    cli_args.resolve.spec = args.spec.clone();
+    cli_args.resolve.placeholders = Some(args.placeholders.clone());
+    cli_args.formats = vec![args.format.clone()];
+    cli_args.output = Some(args.output.clone());
+    cli_args.force = true;
+
+    Ok(cli::run_until(cli_args, &mut io::stdout(), &mut io::stderr(), shutdown).await)
+}
+
+fn parse_format(s: &str) -> Result<String, String> {
+    let registry = FormatterRegistry::new();
+
+    match registry.contains(s) {
+        true => Ok(s.to_string()),
+        false => Err(format!(
+            "expected one of {} but found `{}`",
+            registry.names().join(", "),
+            s
+        )),
+    }
+}
+
+fn parse_placeholder(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE but found `{}`", s))
+}
@@ -0,0 +1,231 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+use crate::{
+    provider::{
+        AwsOptions, AwsParameterStoreProvider, AwsSecretsManagerProvider, CreateOptions,
+        PluginProvider, Provider,
+    },
+    resolve::{Resolver, replace_placeholders},
+    variable::{Provenance, ProviderConfig, Variables},
+};
+
+/// A secret referenced by the spec that doesn't exist remotely
+#[derive(Debug, PartialEq, Eq)]
+pub struct MissingSecret {
+    pub key: String,
+    pub provider_config: ProviderConfig,
+    /// Secret ID after placeholder substitution
+    pub id: String,
+    /// Default value from the spec used to populate the secret
+    pub value: Option<String>,
+    /// Tags from the spec after placeholder substitution
+    pub tags: IndexMap<String, String>,
+}
+
+/// Resolves `variables` and returns the secrets that could not be found
+pub async fn find_missing(
+    mut variables: Variables,
+    placeholders: IndexMap<String, String>,
+    aws: &AwsOptions,
+) -> Result<Vec<MissingSecret>> {
+    Resolver::new(aws)
+        .await?
+        .resolve(&mut variables, placeholders.clone())
+        .await?;
+
+    let mut missing: Vec<MissingSecret> = Vec::new();
+    // Keys using fields of the same JSON secret are created as a single object
+    let mut objects: HashMap<usize, Map<String, Value>> = HashMap::new();
+
+    for var in variables.into_iter().filter(|var| var.value.is_none()) {
+        let Some(config) = var.provider_config else {
+            continue;
+        };
+        let Some(field) = var.json_key else {
+            missing.push(missing_secret(
+                var.key,
+                config,
+                var.default,
+                var.tags,
+                &placeholders,
+            )?);
+            continue;
+        };
+
+        let secret = missing_secret(var.key, config, None, var.tags, &placeholders)?;
+        let existing = (0..missing.len()).find(|idx| {
+            objects.contains_key(idx)
+                && missing[*idx].provider_config == secret.provider_config
+                && missing[*idx].id == secret.id
+        });
+
+        let idx = match existing {
+            Some(idx) => {
+                missing[idx].key = format!("{}, {}", missing[idx].key, secret.key);
+                missing[idx].tags.extend(secret.tags);
+                idx
+            }
+            None => {
+                missing.push(secret);
+                missing.len() - 1
+            }
+        };
+
+        let object = objects.entry(idx).or_default();
+        if let Some(default) = var.default {
+            object.insert(field, Value::String(default));
+        }
+    }
+
+    for (idx, object) in objects {
+        if !object.is_empty() {
+            missing[idx].value = Some(Value::Object(object).to_string());
+        }
+    }
+
+    Ok(missing)
+}
+
+fn missing_secret(
+    key: String,
+    provider_config: ProviderConfig,
+    value: Option<String>,
+    tags: IndexMap<String, String>,
+    placeholders: &IndexMap<String, String>,
+) -> Result<MissingSecret> {
+    let id = replace_placeholders(provider_config.id(), placeholders)?.into_owned();
+    let tags = tags
+        .into_iter()
+        .map(|(key, value)| {
+            let value = replace_placeholders(&value, placeholders)?.into_owned();
+            Ok((key, value))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(MissingSecret {
+        key,
+        provider_config,
+        id,
+        value,
+        tags,
+    })
+}
+
+/// Creates each of the `missing` secrets with its default value
+pub async fn create_missing(
+    missing: &[MissingSecret],
+    options: &CreateOptions,
+    aws: &AwsOptions,
+) -> Result<()> {
+    let aws_sm = AwsSecretsManagerProvider::new(aws).await?;
+    let aws_ps = AwsParameterStoreProvider::new(aws).await?;
+
+    for secret in missing {
+        let value = secret.value.as_deref().unwrap_or_default();
+
+        // Tags from the spec take precedence over ones passed on the command line
+        let mut options = options.clone();
+        options.tags.extend(secret.tags.clone());
+
+        match &secret.provider_config {
+            ProviderConfig::AwsSecretsManager(_) => {
+                aws_sm.create_secret(&secret.id, value, &options).await?
+            }
+            ProviderConfig::AwsParameterStore(_) => {
+                aws_ps.create_secret(&secret.id, value, &options).await?
+            }
+            ProviderConfig::Plugin { name, .. } => {
+                PluginProvider::find(name)?
+                    .create_secret(&secret.id, value, &options)
+                    .await?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates values for the unresolved secrets marked `@generate`, creates them with
+/// their provider and uses them. Returns the keys that were generated.
+pub async fn generate_missing(
+    variables: &mut Variables,
+    placeholders: &IndexMap<String, String>,
+    options: &CreateOptions,
+    aws: &AwsOptions,
+) -> Result<Vec<String>> {
+    let mut missing = Vec::new();
+
+    for var in variables.iter().filter(|var| var.value.is_none()) {
+        let (Some(generator), Some(config)) = (var.generate, &var.provider_config) else {
+            continue;
+        };
+
+        missing.push(missing_secret(
+            var.key.clone(),
+            config.clone(),
+            Some(generator.generate()?),
+            var.tags.clone(),
+            placeholders,
+        )?);
+    }
+
+    if missing.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    create_missing(&missing, options, aws)
+        .await
+        .context("Failed to store generated secrets")?;
+
+    let fetched_at = SystemTime::now();
+    for secret in &missing {
+        let var = variables
+            .iter_mut()
+            .find(|var| var.key == secret.key)
+            .expect("should be a spec entry");
+
+        var.value = secret.value.clone();
+        var.provenance = Some(Provenance {
+            provider: secret.provider_config.name().to_string(),
+            id: secret.id.clone(),
+            version: None,
+            fetched_at,
+            expires_at: var.ttl.map(|ttl| fetched_at + ttl),
+            changed_at: Some(fetched_at),
+        });
+    }
+
+    Ok(missing.into_iter().map(|secret| secret.key).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_secret_substitutes_placeholders() {
+        let placeholders: IndexMap<_, _> = [("env".to_string(), "prod".to_string())].into();
+        let tags: IndexMap<_, _> = [
+            ("team".to_string(), "payments".to_string()),
+            ("env".to_string(), "$env".to_string()),
+        ]
+        .into();
+
+        let result = missing_secret(
+            "KEY1".to_string(),
+            ProviderConfig::AwsSecretsManager("$env/key1".to_string()),
+            Some("value1".to_string()),
+            tags,
+            &placeholders,
+        )
+        .unwrap();
+
+        assert_eq!(result.id, "prod/key1");
+        assert_eq!(result.tags["team"], "payments");
+        assert_eq!(result.tags["env"], "prod");
+    }
+}
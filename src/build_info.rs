@@ -0,0 +1,98 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Describes the running build, reported by `awsm-env version`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Commit the binary was built from, if it was built from a git checkout
+    pub git_sha: Option<&'static str>,
+    pub target: &'static str,
+    /// Enabled cargo features
+    pub features: Vec<&'static str>,
+    /// Directives supported by this build
+    pub providers: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let features = [
+            ("age", cfg!(feature = "age")),
+            ("aws", cfg!(feature = "aws")),
+            ("aws-ps", cfg!(feature = "aws-ps")),
+            ("aws-sm", cfg!(feature = "aws-sm")),
+            ("ffi", cfg!(feature = "ffi")),
+            ("rustls", cfg!(feature = "rustls")),
+            ("wasm", cfg!(feature = "wasm")),
+            ("wasm-plugins", cfg!(feature = "wasm-plugins")),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+
+        let providers = [
+            ("aws-sm", cfg!(feature = "aws-sm")),
+            ("aws-ps", cfg!(feature = "aws-ps")),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: Some(env!("AWSM_ENV_GIT_SHA")).filter(|sha| !sha.is_empty()),
+            target: env!("AWSM_ENV_TARGET"),
+            features,
+            providers,
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "awsm-env {}", self.version)?;
+
+        if let Some(sha) = self.git_sha {
+            write!(f, " ({})", sha)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "target: {}", self.target)?;
+        writeln!(f, "features: {}", self.features.join(", "))?;
+        write!(f, "providers: {}", self.providers.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_display() {
+        let info = BuildInfo {
+            version: "1.2.3",
+            git_sha: Some("abc123"),
+            target: "x86_64-unknown-linux-gnu",
+            features: vec!["aws", "ffi"],
+            providers: vec!["aws-sm", "aws-ps"],
+        };
+
+        assert_eq!(
+            info.to_string(),
+            "awsm-env 1.2.3 (abc123)\ntarget: x86_64-unknown-linux-gnu\nfeatures: aws, ffi\nproviders: aws-sm, aws-ps"
+        );
+    }
+
+    #[test]
+    fn test_build_info_json() {
+        let info = BuildInfo {
+            git_sha: None,
+            ..BuildInfo::current()
+        };
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["git_sha"].is_null());
+    }
+}
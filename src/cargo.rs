@@ -0,0 +1,76 @@
+//! Helpers for Rust projects consuming resolved values through cargo, from a build script
+//! or the `awsm-env-build` runner
+
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+
+use crate::{parser::EnvParser, resolve::resolve, variable::Variables};
+
+/// `cargo:` directives rerunning the build script when the spec changes and setting each
+/// value as a compile-time environment variable, read with `env!`. Keys marked
+/// `@no-export` and keys without a value are left out.
+pub fn build_script_directives(spec: &Path, variables: &Variables) -> Result<String> {
+    let mut directives = format!("cargo:rerun-if-changed={}\n", spec.display());
+
+    for var in variables.iter().filter(|var| !var.no_export) {
+        let Some(value) = var.value.as_deref().or(var.default.as_deref()) else {
+            continue;
+        };
+
+        // Directives are line based, so there's no way to pass a newline through
+        if value.contains(['\n', '\r']) {
+            return Err(anyhow!(
+                "Value of {} contains a newline, which can't be set from a build script",
+                var.key
+            ));
+        }
+
+        directives.push_str(&format!("cargo:rustc-env={}={}\n", var.key, value));
+    }
+
+    Ok(directives)
+}
+
+/// Resolves the spec at `spec` and prints the directives of [`build_script_directives`],
+/// for calling from `build.rs`. The values become part of the compiled binary.
+pub fn emit_build_env(
+    spec: impl AsRef<Path>,
+    placeholders: IndexMap<String, String>,
+) -> Result<()> {
+    let spec = spec.as_ref();
+    let input = std::fs::read_to_string(spec).context("Failed to read specification file")?;
+    let mut variables = EnvParser::parse_variables(&input).context("Failed to parse file")?;
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start runtime")?
+        .block_on(resolve(&mut variables, placeholders))
+        .context("Failed to fetch secrets")?;
+
+    print!("{}", build_script_directives(spec, &variables)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_script_directives() {
+        let variables = EnvParser::parse_variables(
+            "API_URL=https://api.example.com\n# @no-export\nLOCAL=1\nEMPTY=\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            build_script_directives(Path::new(".env.example"), &variables).unwrap(),
+            "cargo:rerun-if-changed=.env.example\ncargo:rustc-env=API_URL=https://api.example.com\n"
+        );
+
+        let variables = EnvParser::parse_variables("CERT=\"a\nb\"\n").unwrap();
+        assert!(build_script_directives(Path::new(".env.example"), &variables).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Settings read from `.awsm-env.toml`, which can extend a base config fetched from an
+//! `https://` or `s3://` URL so that many repositories inherit an organization's policy
+//!
+//! ```toml
+//! extends = "s3://platform-configs/awsm-env.toml"
+//! format = "json"
+//! strict = true
+//! name-rules = "posix"
+//! allowed-prefixes = ["payments/", "/payments/"]
+//! require-signed = true
+//!
+//! [macros]
+//! db = "@aws-sm payments/$env/db/%s"
+//! ```
+//!
+//! Keys that spec signatures are checked against are only read from the user's and the
+//! system's `awsm-env/config.toml`, so that a repository can't vouch for its own specs
+//!
+//! ```toml
+//! trusted-keys = ["RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"]
+//! ```
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::{
+    output::FormatterRegistry,
+    remote::{Fetcher, is_url},
+    resolve::replace_placeholders,
+    signing::PublicKey,
+    validate::NameRules,
+    variable::Variables,
+};
+
+/// Config file used when `--config` isn't given, read from the current directory
+pub const DEFAULT_CONFIG: &str = ".awsm-env.toml";
+
+/// How long a base config fetched from a URL is used before fetching it again
+pub const BASE_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Settings that apply to every command
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// URL or path of a base config, providing the settings this one leaves unset
+    pub extends: Option<String>,
+    /// Output format used when `--format` isn't given
+    pub format: Option<String>,
+    /// Fail instead of warning about problems in a spec, as with `--strict`
+    pub strict: Option<bool>,
+    /// Naming rules keys are checked against, as with `--name-rules`
+    pub name_rules: Option<NameRules>,
+    /// Secret IDs must start with one of these, after placeholder substitution
+    pub allowed_prefixes: Option<Vec<String>>,
+    /// Refuse specs without a valid signature, as with `--require-signed`
+    pub require_signed: Option<bool>,
+    /// Macros available to every spec, by name without the `@`
+    pub macros: IndexMap<String, String>,
+}
+
+impl Config {
+    pub fn parse(input: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(input)?;
+        if table.contains_key("trusted-keys") {
+            return Err(anyhow!(
+                "trusted-keys can't be set in a project config, set it in {} or use --trusted-key",
+                user_config_path().display()
+            ));
+        }
+
+        let config: Config = toml::Value::Table(table).try_into()?;
+
+        if let Some(format) = &config.format
+            && !FormatterRegistry::new().contains(format)
+        {
+            return Err(anyhow!("Unknown format {}", format));
+        }
+
+        Ok(config)
+    }
+
+    /// This config with the settings it leaves unset taken from `base`. Macros are
+    /// combined, with those of this config replacing the base's.
+    pub fn extend(self, base: Config) -> Config {
+        let mut macros = base.macros;
+        macros.extend(self.macros);
+
+        Config {
+            extends: self.extends,
+            format: self.format.or(base.format),
+            strict: self.strict.or(base.strict),
+            name_rules: self.name_rules.or(base.name_rules),
+            allowed_prefixes: self.allowed_prefixes.or(base.allowed_prefixes),
+            require_signed: self.require_signed.or(base.require_signed),
+            macros,
+        }
+    }
+
+    /// Macros as definitions accepted by
+    /// [`EnvParser::expand_macros_with`](crate::parser::EnvParser::expand_macros_with)
+    pub fn macro_definitions(&self) -> Vec<String> {
+        self.macros
+            .iter()
+            .map(|(name, directive)| format!("@{} = {}", name, directive))
+            .collect()
+    }
+
+    /// Secret IDs in `variables`, including those of mirrors and `@aws-ps-path` paths,
+    /// that don't start with an allowed prefix. IDs with missing placeholders are skipped,
+    /// resolving them fails anyway.
+    pub fn disallowed_ids(
+        &self,
+        variables: &Variables,
+        placeholders: &IndexMap<String, String>,
+    ) -> Vec<String> {
+        let Some(prefixes) = &self.allowed_prefixes else {
+            return Vec::new();
+        };
+
+        variables
+            .iter()
+            .flat_map(|var| {
+                let configs = var.provider_config.iter().chain(&var.mirror);
+                configs
+                    .map(|config| config.id())
+                    .chain(var.ps_path.as_deref())
+            })
+            .filter_map(|id| replace_placeholders(id, placeholders).ok())
+            .filter(|id| {
+                !prefixes
+                    .iter()
+                    .any(|prefix| id.starts_with(prefix.as_str()))
+            })
+            .map(|id| id.into_owned())
+            .collect()
+    }
+}
+
+/// Loads the config at `path`, or [`DEFAULT_CONFIG`] when it exists, along with the
+/// base config it extends. Base configs at URLs are fetched with `fetcher` and cached for
+/// [`BASE_CACHE_TTL`].
+pub async fn load_config(path: Option<&Path>, fetcher: &Fetcher) -> Result<Config> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG).exists() => Path::new(DEFAULT_CONFIG),
+        None => return Ok(Config::default()),
+    };
+
+    let config = read_config(path)?;
+    let Some(base) = &config.extends else {
+        return Ok(config);
+    };
+
+    let (location, input) = match is_url(base) {
+        true => (
+            base.clone(),
+            fetcher.fetch_cached(base, BASE_CACHE_TTL).await?,
+        ),
+        false => {
+            let base = path.parent().unwrap_or(Path::new("")).join(base);
+            let input = fs::read(&base)
+                .with_context(|| format!("Failed to read config {}", base.display()))?;
+            (base.display().to_string(), input)
+        }
+    };
+
+    let base = String::from_utf8(input)
+        .map_err(Into::into)
+        .and_then(|input| Config::parse(&input))
+        .with_context(|| format!("Failed to parse config {}", location))?;
+    if base.extends.is_some() {
+        return Err(anyhow!(
+            "Config {} extends another config, only one level is supported",
+            location
+        ));
+    }
+
+    Ok(config.extend(base))
+}
+
+fn read_config(path: &Path) -> Result<Config> {
+    let input = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config {}", path.display()))?;
+
+    Config::parse(&input).with_context(|| format!("Failed to parse config {}", path.display()))
+}
+
+/// Settings read from the user's and the system's config rather than a repository's
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct TrustConfig {
+    /// Minisign public keys spec signatures are checked against, as with `--trusted-key`
+    trusted_keys: Vec<String>,
+}
+
+/// Loads the trusted keys of the user's and the system's config, those that exist
+pub fn load_trusted_keys() -> Result<Vec<PublicKey>> {
+    read_trusted_keys(&[user_config_path(), system_config_path()])
+}
+
+fn read_trusted_keys(paths: &[PathBuf]) -> Result<Vec<PublicKey>> {
+    let mut keys = Vec::new();
+    for path in paths.iter().filter(|path| path.exists()) {
+        let config: TrustConfig = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))
+            .and_then(|input| Ok(toml::from_str(&input)?))
+            .with_context(|| format!("Failed to parse config {}", path.display()))?;
+
+        for key in &config.trusted_keys {
+            keys.push(
+                PublicKey::parse(key).with_context(|| {
+                    format!("Invalid trusted key {} in {}", key, path.display())
+                })?,
+            );
+        }
+    }
+
+    Ok(keys)
+}
+
+/// `awsm-env/config.toml` in `$XDG_CONFIG_HOME`, `%APPDATA%` or `~/.config`
+pub fn user_config_path() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default()
+        .join("awsm-env")
+        .join("config.toml")
+}
+
+/// `awsm-env/config.toml` in `/etc`, or `%ProgramData%` on Windows
+pub fn system_config_path() -> PathBuf {
+    let dir = match cfg!(windows) {
+        true => env::var_os("ProgramData")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData")),
+        false => PathBuf::from("/etc"),
+    };
+
+    dir.join("awsm-env").join("config.toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use crate::parser::EnvParser;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_extends_base_config() {
+        let dir = env::temp_dir().join(format!("awsm_env_test_config_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("base.toml"),
+            "format = \"json\"\nstrict = true\nallowed-prefixes = [\"app/\"]\n\n[macros]\ndb = \"@aws-sm app/db/%s\"\nps = \"@aws-ps /app/%s\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(".awsm-env.toml"),
+            "extends = \"base.toml\"\nstrict = false\n\n[macros]\nps = \"@aws-ps /other/%s\"\n",
+        )
+        .unwrap();
+
+        let config = load_config(Some(&dir.join(".awsm-env.toml")), &Fetcher::default())
+            .await
+            .unwrap();
+        assert_eq!(config.format.as_deref(), Some("json"));
+        assert_eq!(config.strict, Some(false));
+        assert_eq!(
+            config.macro_definitions(),
+            vec!["@db = @aws-sm app/db/%s", "@ps = @aws-ps /other/%s"]
+        );
+
+        let variables =
+            EnvParser::parse_variables("# @aws-sm app/$env/a\nA=\n# @aws-sm other/b\nB=\n")
+                .unwrap();
+        let placeholders = IndexMap::from([("env".to_string(), "prod".to_string())]);
+        assert_eq!(
+            config.disallowed_ids(&variables, &placeholders),
+            vec!["other/b"]
+        );
+
+        fs::write(dir.join("base.toml"), "extends = \"other.toml\"\n").unwrap();
+        assert!(
+            load_config(Some(&dir.join(".awsm-env.toml")), &Fetcher::default())
+                .await
+                .is_err()
+        );
+        assert!(Config::parse("format = \"xml\"\n").is_err());
+        assert!(Config::parse("strcit = true\n").is_err());
+        let err = Config::parse("trusted-keys = [\"RWQ\"]\n").unwrap_err();
+        assert!(err.to_string().contains("can't be set in a project config"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reads_trusted_keys() {
+        let dir = env::temp_dir().join(format!("awsm_env_test_trust_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("user.toml"),
+            "trusted-keys = [\"RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\"]\n",
+        )
+        .unwrap();
+
+        let keys = read_trusted_keys(&[dir.join("user.toml"), dir.join("missing.toml")]).unwrap();
+        assert_eq!(
+            keys.iter().map(PublicKey::id).collect::<Vec<_>>(),
+            vec!["E7620F1842B4E81F"]
+        );
+
+        fs::write(dir.join("system.toml"), "trusted-keys = [\"RWQ\"]\n").unwrap();
+        assert!(read_trusted_keys(&[dir.join("system.toml")]).is_err());
+        fs::write(dir.join("system.toml"), "format = \"json\"\n").unwrap();
+        assert!(read_trusted_keys(&[dir.join("system.toml")]).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,138 @@
+//! Output for `.envrc` files, cached next to the spec so direnv doesn't wait on AWS every
+//! time a shell enters the directory
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    output::{Header, create_private, write_atomic},
+    shellenv::Shell,
+};
+
+/// Directory direnv keeps per-project state in, usually ignored by git
+pub const CACHE_DIR: &str = ".direnv";
+
+const CACHE_PREFIX: &str = "awsm-env-";
+
+/// Identifies the cached output of a spec resolved with the given inputs, e.g.
+/// placeholders and overrides
+pub fn fingerprint(spec: &[u8], inputs: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(spec);
+    for input in inputs {
+        hasher.update([0]);
+        hasher.update(input.as_bytes());
+    }
+
+    hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Where the output for `fingerprint` is cached, in the spec's directory
+pub fn cache_path(spec: &Path, fingerprint: &str) -> PathBuf {
+    spec.parent()
+        .unwrap_or(Path::new(""))
+        .join(CACHE_DIR)
+        .join(format!("{}{}.sh", CACHE_PREFIX, fingerprint))
+}
+
+/// Reads cached output, `None` when there's none or it's past its expiry or `max_age`
+pub fn read_cache(path: &Path, max_age: Duration, now: SystemTime) -> Result<Option<String>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+
+    Ok(Header::parse(&contents)
+        .filter(|header| !header.is_stale(now, max_age))
+        .map(|_| contents))
+}
+
+/// Caches `contents` readable only by the owner, removing output cached for other
+/// fingerprints
+pub fn write_cache(path: &Path, contents: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    create_private(path)?;
+    write_atomic(path, contents.as_bytes())?;
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(CACHE_PREFIX) && entry.path() != path {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+/// `watch_file` calls making direnv reload when any of `paths` changes
+pub fn watch_files(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| {
+            format!(
+                "watch_file {}\n",
+                Shell::Bash.quote(&path.to_string_lossy())
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variable::Variables;
+
+    use super::*;
+
+    #[test]
+    fn test_caches_output() {
+        let spec = std::env::temp_dir()
+            .join("awsm_env_test_direnv")
+            .join(".env.example");
+        let now = SystemTime::now();
+        let minute = Duration::from_secs(60);
+
+        let path = cache_path(&spec, &fingerprint(b"PORT=\n", &["environment=dev".into()]));
+        let other = cache_path(
+            &spec,
+            &fingerprint(b"PORT=\n", &["environment=prod".into()]),
+        );
+        assert_ne!(path, other);
+
+        let contents = format!(
+            "{}export PORT='8080'\n",
+            Header::new(b"PORT=\n", &Variables::new(), now)
+        );
+        write_cache(&other, &contents).unwrap();
+        write_cache(&path, &contents).unwrap();
+
+        assert!(!other.exists());
+        assert_eq!(read_cache(&path, minute, now).unwrap(), Some(contents));
+        assert_eq!(read_cache(&path, minute, now + minute * 2).unwrap(), None);
+        assert_eq!(read_cache(&other, minute, now).unwrap(), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_dir_all(spec.parent().unwrap());
+    }
+}
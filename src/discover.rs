@@ -0,0 +1,217 @@
+//! Discovery of the specs in a directory tree, so `render-all` can refresh every service
+//! in a monorepo with one command
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// File name suffix identifying a spec, e.g. `.env.example` or `api.env.example`
+pub const SPEC_SUFFIX: &str = ".env.example";
+
+/// File in the root of the tree listing paths to skip
+pub const IGNORE_FILE: &str = ".awsm-ignore";
+
+/// Patterns from an `.awsm-ignore` file, a subset of the `.gitignore` syntax: `*`, `?`
+/// and `**` wildcards, a leading `/` anchoring a pattern to the root and a trailing `/`
+/// only matching directories. Patterns without a `/` match names at any depth.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Regex,
+    /// Matched against the whole path relative to the root instead of the name
+    anchored: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRules {
+    /// Reads the ignore file in `root`, having none ignores nothing
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(IGNORE_FILE);
+        if !path
+            .try_exists()
+            .with_context(|| format!("Failed to check {}", path.display()))?
+        {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let rules = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (line, dir_only) = match line.strip_suffix('/') {
+                    Some(line) => (line, true),
+                    None => (line, false),
+                };
+                let anchored = line.contains('/');
+                let line = line.strip_prefix('/').unwrap_or(line);
+
+                Ok(Rule {
+                    pattern: Regex::new(&glob_to_regex(line))
+                        .with_context(|| format!("Invalid pattern '{}'", line))?,
+                    anchored,
+                    dir_only,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(IgnoreRules { rules })
+    }
+
+    /// Whether `path`, relative to the root and separated by `/`, is ignored
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        self.rules.iter().any(|rule| {
+            (is_dir || !rule.dir_only)
+                && rule
+                    .pattern
+                    .is_match(if rule.anchored { path } else { name })
+        })
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.next_if_eq(&'/').is_some() {
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+/// Finds the specs under `root`, in path order. `.git` directories, symlinks and paths
+/// matching the root's ignore file are skipped.
+pub fn find_specs(root: &Path) -> Result<Vec<PathBuf>> {
+    let rules = IgnoreRules::load(root)?;
+    let mut specs = Vec::new();
+    walk(root, "", &rules, &mut specs)?;
+    Ok(specs)
+}
+
+fn walk(dir: &Path, relative: &str, rules: &IgnoreRules, specs: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let Some(name) = entry.file_name().to_str().map(String::from) else {
+            continue;
+        };
+        let path = match relative {
+            "" => name.clone(),
+            _ => format!("{}/{}", relative, name),
+        };
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+
+        if file_type.is_dir() {
+            if name != ".git" && !rules.is_ignored(&path, true) {
+                walk(&entry.path(), &path, rules, specs)?;
+            }
+        } else if file_type.is_file()
+            && name.ends_with(SPEC_SUFFIX)
+            && !rules.is_ignored(&path, false)
+        {
+            specs.push(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+/// Where a spec is rendered, next to it with the `.example` suffix removed
+pub fn rendered_path(spec: &Path) -> PathBuf {
+    let name = spec
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(".example"))
+        .unwrap_or(".env");
+
+    spec.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        let rules = IgnoreRules::parse(
+            "# dependencies\nnode_modules/\n/legacy\nservices/*/fixtures/**\n*.tmp.env.example\n",
+        )
+        .unwrap();
+
+        assert!(rules.is_ignored("web/node_modules", true));
+        assert!(!rules.is_ignored("web/node_modules", false));
+        assert!(rules.is_ignored("legacy", true));
+        assert!(!rules.is_ignored("services/legacy", true));
+        assert!(rules.is_ignored("services/api/fixtures/a/.env.example", false));
+        assert!(rules.is_ignored("api/old.tmp.env.example", false));
+        assert!(!rules.is_ignored("api/.env.example", false));
+    }
+
+    #[test]
+    fn test_finds_specs() {
+        let root = std::env::temp_dir().join("awsm_env_test_find_specs");
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["api", "web/node_modules/pkg", "worker", ".git"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "api/.env.example",
+            "web/node_modules/pkg/.env.example",
+            "worker/jobs.env.example",
+            "worker/.env",
+            ".git/.env.example",
+        ] {
+            fs::write(root.join(file), "KEY=\n").unwrap();
+        }
+        fs::write(root.join(IGNORE_FILE), "node_modules/\n").unwrap();
+
+        let specs = find_specs(&root).unwrap();
+        assert_eq!(
+            specs,
+            [
+                root.join("api/.env.example"),
+                root.join("worker/jobs.env.example")
+            ]
+        );
+        assert_eq!(rendered_path(&specs[0]), root.join("api/.env"));
+        assert_eq!(rendered_path(&specs[1]), root.join("worker/jobs.env"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,166 @@
+//! Reference documentation generated from the clap definitions, for packagers to install
+//! alongside the binary
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{Arg, Command};
+use clap_mangen::Man;
+use itertools::Itertools;
+
+/// Writes a man page for `cmd` and one for each of its visible subcommands to `dir`,
+/// e.g. `awsm-env.1` and `awsm-env-tui.1`, returning their paths
+pub fn write_man_pages(cmd: &Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    // Building names subcommands after their parents, e.g. `awsm-env-tui`
+    let mut cmd = cmd.clone();
+    cmd.build();
+
+    let mut written = Vec::new();
+    let mut pending = vec![cmd];
+
+    while let Some(cmd) = pending.pop() {
+        let name = cmd.get_display_name().unwrap_or(cmd.get_name()).to_string();
+
+        pending.extend(
+            cmd.get_subcommands()
+                .filter(|sub| is_documented(sub))
+                .cloned(),
+        );
+
+        let path = dir.join(format!("{}.1", name));
+        let mut file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Man::new(cmd)
+            .render(&mut file)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+/// Renders a markdown reference of `cmd` and its visible subcommands
+pub fn markdown(cmd: &Command) -> String {
+    let mut out = String::new();
+    write_command(&mut out, &mut cmd.clone(), cmd.get_name(), 1);
+    out
+}
+
+fn write_command(out: &mut String, cmd: &mut Command, path: &str, depth: usize) {
+    let _ = writeln!(out, "{} `{}`\n", "#".repeat(depth), path);
+
+    if let Some(about) = cmd.get_long_about().or(cmd.get_about()) {
+        let _ = writeln!(out, "{}\n", about);
+    }
+
+    let usage = cmd.render_usage().to_string();
+    let usage = usage.strip_prefix("Usage: ").unwrap_or(&usage);
+    let _ = writeln!(out, "```text\n{}\n```\n", usage);
+
+    let args = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .map(format_arg)
+        .collect::<Vec<_>>();
+
+    if !args.is_empty() {
+        out.push_str(&args.join("\n"));
+        out.push_str("\n\n");
+    }
+
+    for sub in cmd.get_subcommands_mut().filter(|sub| is_documented(sub)) {
+        let path = format!("{} {}", path, sub.get_name());
+        write_command(out, sub, &path, depth + 1);
+    }
+}
+
+/// Skips hidden subcommands and clap's `help`
+fn is_documented(cmd: &Command) -> bool {
+    !cmd.is_hide_set() && cmd.get_name() != "help"
+}
+
+fn format_arg(arg: &Arg) -> String {
+    let names = match (arg.get_short(), arg.get_long()) {
+        (Some(short), Some(long)) => format!("-{}, --{}", short, long),
+        (Some(short), None) => format!("-{}", short),
+        (None, Some(long)) => format!("--{}", long),
+        (None, None) => String::new(),
+    };
+
+    let value = arg
+        .get_value_names()
+        .map(|names| names.iter().map(|name| format!("<{}>", name)).join(" "))
+        .filter(|_| arg.get_action().takes_values())
+        .unwrap_or_default();
+
+    let mut line = match (names.is_empty(), value.is_empty()) {
+        (true, _) => format!("- `{}`", value),
+        (false, true) => format!("- `{}`", names),
+        (false, false) => format!("- `{} {}`", names, value),
+    };
+
+    if let Some(help) = arg.get_long_help().or(arg.get_help()) {
+        let _ = write!(line, ": {}", help.to_string().replace('\n', " "));
+    }
+
+    let defaults = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy())
+        .join(", ");
+    if !defaults.is_empty() && arg.get_action().takes_values() && !arg.is_hide_default_value_set() {
+        let _ = write!(line, " (default: `{}`)", defaults);
+    }
+
+    if let Some(env) = arg.get_env().filter(|_| !arg.is_hide_env_set()) {
+        let _ = write!(line, " (env: `{}`)", env.to_string_lossy());
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use clap::CommandFactory;
+
+    use crate::cli::Args;
+
+    use super::*;
+
+    #[test]
+    fn test_renders_markdown_reference() {
+        let reference = markdown(&Args::command());
+
+        assert!(reference.starts_with("# `awsm-env`\n"));
+        assert!(reference.contains("\n## `awsm-env tui`\n"));
+        assert!(reference.contains("- `-o, --output <OUTPUT>`"));
+        assert!(!reference.contains("`awsm-env generate`"));
+    }
+
+    #[test]
+    fn test_writes_man_pages() {
+        let dir = env::temp_dir().join(format!("awsm-env-man-{}", process::id()));
+
+        let written = write_man_pages(&Args::command(), &dir).unwrap();
+
+        assert!(written.contains(&dir.join("awsm-env.1")));
+        assert!(written.contains(&dir.join("awsm-env-doctor.1")));
+        assert!(!written.contains(&dir.join("awsm-env-generate.1")));
+        assert!(
+            fs::read_to_string(dir.join("awsm-env-doctor.1"))
+                .unwrap()
+                .contains("awsm\\-env\\-doctor")
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::SystemTime;
+
+use crate::{
+    provider::{AwsOptions, CredentialSource, probe_credentials},
+    term::format_table,
+};
+
+/// Outcome of asking a credential source for credentials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    /// Excluded by `--credential-source` or `--no-imds`, not probed
+    Disabled,
+    Available {
+        expiry: Option<SystemTime>,
+    },
+    Unavailable(String),
+}
+
+/// Result of probing a single credential source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialProbe {
+    pub source: CredentialSource,
+    pub outcome: ProbeOutcome,
+}
+
+/// Probes every credential source the SDK knows about, skipping those excluded by `options`
+pub async fn probe_credential_sources(options: &AwsOptions) -> Vec<CredentialProbe> {
+    let chain = options.credential_chain();
+    let mut probes = Vec::new();
+
+    for source in CredentialSource::DEFAULT_CHAIN {
+        let outcome = match chain.contains(&source) {
+            false => ProbeOutcome::Disabled,
+            true => match probe_credentials(source).await {
+                Ok(expiry) => ProbeOutcome::Available { expiry },
+                Err(err) => ProbeOutcome::Unavailable(format!("{:#}", err)),
+            },
+        };
+
+        probes.push(CredentialProbe { source, outcome });
+    }
+
+    probes
+}
+
+/// The source that will provide credentials: the first available one in chain order
+pub fn selected_source(
+    probes: &[CredentialProbe],
+    options: &AwsOptions,
+) -> Option<CredentialSource> {
+    options.credential_chain().into_iter().find(|source| {
+        probes.iter().any(|probe| {
+            probe.source == *source && matches!(probe.outcome, ProbeOutcome::Available { .. })
+        })
+    })
+}
+
+/// Formats `probes` as a table, marking the source that will be used
+pub fn format_report(
+    probes: &[CredentialProbe],
+    selected: Option<CredentialSource>,
+    now: SystemTime,
+) -> String {
+    let rows = probes
+        .iter()
+        .map(|probe| {
+            let (status, expires) = match &probe.outcome {
+                ProbeOutcome::Disabled => ("disabled".to_string(), "-".to_string()),
+                ProbeOutcome::Unavailable(err) => {
+                    (format!("unavailable: {}", err), "-".to_string())
+                }
+                ProbeOutcome::Available { expiry } => (
+                    match selected == Some(probe.source) {
+                        true => "available (used)".to_string(),
+                        false => "available".to_string(),
+                    },
+                    expiry
+                        .map(|expiry| format_expiry(now, expiry))
+                        .unwrap_or("never".to_string()),
+                ),
+            };
+
+            [probe.source.name().to_string(), status, expires]
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = format_table(["SOURCE", "STATUS", "EXPIRES"], &rows);
+
+    if selected.is_none() {
+        output.push_str("\nNo credential source is available\n");
+    }
+
+    output
+}
+
+fn format_expiry(now: SystemTime, expiry: SystemTime) -> String {
+    match expiry.duration_since(now) {
+        Ok(until) => format!("in {}m", until.as_secs() / 60),
+        Err(_) => "expired".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn probes() -> Vec<CredentialProbe> {
+        vec![
+            CredentialProbe {
+                source: CredentialSource::Env,
+                outcome: ProbeOutcome::Unavailable("not set".to_string()),
+            },
+            CredentialProbe {
+                source: CredentialSource::WebIdentity,
+                outcome: ProbeOutcome::Available {
+                    expiry: Some(UNIX_EPOCH + Duration::from_secs(3600)),
+                },
+            },
+            CredentialProbe {
+                source: CredentialSource::Ecs,
+                outcome: ProbeOutcome::Available { expiry: None },
+            },
+            CredentialProbe {
+                source: CredentialSource::Imds,
+                outcome: ProbeOutcome::Disabled,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_selected_source_follows_chain_order() {
+        let options = AwsOptions::default();
+        assert_eq!(
+            selected_source(&probes(), &options),
+            Some(CredentialSource::WebIdentity)
+        );
+
+        let options = AwsOptions {
+            credential_sources: Some(vec![CredentialSource::Ecs, CredentialSource::WebIdentity]),
+            ..Default::default()
+        };
+        assert_eq!(
+            selected_source(&probes(), &options),
+            Some(CredentialSource::Ecs)
+        );
+    }
+
+    #[test]
+    fn test_format_report() {
+        let output = format_report(&probes(), Some(CredentialSource::WebIdentity), UNIX_EPOCH);
+
+        assert_eq!(
+            output,
+            "\
+SOURCE        STATUS                EXPIRES
+env           unavailable: not set  -
+web-identity  available (used)      in 60m
+ecs           available             never
+imds          disabled              -
+"
+        );
+    }
+
+    #[test]
+    fn test_format_report_without_credentials() {
+        let output = format_report(&[], None, UNIX_EPOCH);
+        assert!(output.ends_with("No credential source is available\n"));
+    }
+}
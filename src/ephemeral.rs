@@ -0,0 +1,129 @@
+//! Memory-backed storage for `--ephemeral` output, for hosts where rendered secrets must
+//! never be persisted to disk
+
+use std::{
+    fs, mem,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+/// Prefix of the directories created by [`EphemeralDir::create`]
+pub const DIR_PREFIX: &str = "awsm-env-";
+
+/// A directory only its owner can access on a memory-backed filesystem, removed with its
+/// contents when dropped
+pub struct EphemeralDir(PathBuf);
+
+impl EphemeralDir {
+    /// Creates a directory with an unpredictable name in [`memory_dir`]
+    pub fn create() -> Result<Self> {
+        let mut bytes = [0; 8];
+        getrandom::fill(&mut bytes)
+            .map_err(|err| anyhow!("Failed to generate random bytes: {}", err))?;
+        let name = bytes.iter().fold(DIR_PREFIX.to_string(), |name, byte| {
+            name + &format!("{:02x}", byte)
+        });
+        let path = memory_dir()?.join(name);
+
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        Ok(EphemeralDir(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Gives up removing the directory when dropped, returning its path
+    pub fn into_path(self) -> PathBuf {
+        let path = self.0.clone();
+        mem::forget(self);
+        path
+    }
+}
+
+impl Drop for EphemeralDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Whether `path` looks like a directory created by [`EphemeralDir::create`], so that
+/// cleaning up never removes anything else
+pub fn is_ephemeral_dir(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(DIR_PREFIX))
+        && path.parent().is_some_and(|parent| {
+            memory_dir()
+                .is_ok_and(|dir| fs::canonicalize(parent).ok() == fs::canonicalize(dir).ok())
+        })
+}
+
+/// Directory on a memory-backed filesystem: `$XDG_RUNTIME_DIR`, which is private to the
+/// user, or else `/dev/shm`
+#[cfg(target_os = "linux")]
+pub fn memory_dir() -> Result<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain([PathBuf::from("/dev/shm")])
+        .find(|dir| is_memory_backed(dir))
+        .ok_or_else(|| {
+            anyhow!("No memory-backed directory found, checked $XDG_RUNTIME_DIR and /dev/shm")
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn memory_dir() -> Result<PathBuf> {
+    Err(anyhow!(
+        "--ephemeral needs a tmpfs, which is only available on Linux"
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn is_memory_backed(dir: &Path) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+
+    // The field and constant types differ between targets
+    stat.f_type as u64 == libc::TMPFS_MAGIC as u64
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ephemeral_dir() {
+        // Not every build host has a tmpfs
+        let Ok(dir) = EphemeralDir::create() else {
+            return;
+        };
+        let path = dir.path().to_path_buf();
+
+        assert!(is_ephemeral_dir(&path));
+        assert!(!is_ephemeral_dir(&std::env::temp_dir()));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
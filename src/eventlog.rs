@@ -0,0 +1,105 @@
+//! Logging to the Windows Event Log, for `awsm-env refresh` running as a scheduled task
+//! or service where nobody reads its console
+
+use anyhow::Result;
+
+/// Source the events are reported under, shown in Event Viewer's Source column
+pub const SOURCE: &str = "awsm-env";
+
+/// Severity of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A registered event source, deregistered when dropped
+pub struct EventLog {
+    #[cfg(windows)]
+    handle: windows_sys::Win32::Foundation::HANDLE,
+}
+
+// Event source handles aren't tied to the thread that registered them
+#[cfg(windows)]
+unsafe impl Send for EventLog {}
+
+#[cfg(windows)]
+impl EventLog {
+    /// Registers `source` in the Application log
+    pub fn open(source: &str) -> Result<Self> {
+        use windows_sys::Win32::System::EventLog::RegisterEventSourceW;
+
+        let source = wide(source);
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+        if handle.is_null() {
+            return Err(anyhow::Error::from(std::io::Error::last_os_error())
+                .context("Failed to register the event source"));
+        }
+
+        Ok(EventLog { handle })
+    }
+
+    /// Reports `message` at `level`
+    pub fn report(&self, level: Level, message: &str) -> Result<()> {
+        use windows_sys::Win32::System::EventLog::{
+            EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE, ReportEventW,
+        };
+
+        let kind = match level {
+            Level::Info => EVENTLOG_INFORMATION_TYPE,
+            Level::Warning => EVENTLOG_WARNING_TYPE,
+            Level::Error => EVENTLOG_ERROR_TYPE,
+        };
+        let message = wide(message);
+        let strings = [message.as_ptr()];
+
+        let reported = unsafe {
+            ReportEventW(
+                self.handle,
+                kind,
+                0,
+                0,
+                std::ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        if reported == 0 {
+            return Err(anyhow::Error::from(std::io::Error::last_os_error())
+                .context("Failed to report to the event log"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        unsafe {
+            windows_sys::Win32::System::EventLog::DeregisterEventSource(self.handle);
+        }
+    }
+}
+
+/// Null terminated UTF-16, as taken by the Windows API
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(not(windows))]
+impl EventLog {
+    pub fn open(_source: &str) -> Result<Self> {
+        Err(anyhow::anyhow!(
+            "The event log is only available on Windows"
+        ))
+    }
+
+    pub fn report(&self, _level: Level, _message: &str) -> Result<()> {
+        Ok(())
+    }
+}
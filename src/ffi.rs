@@ -0,0 +1,164 @@
+//! C ABI around parsing and resolving specs, allowing other languages to embed the same
+//! resolution logic as the CLI. Strings are UTF-8 and NUL-terminated, strings returned by
+//! this module must be released with [`awsm_env_free`]. See `include/awsm_env.h`.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char},
+    ptr,
+};
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+
+use crate::{
+    output::{JsonOutput, Output, spec_entries},
+    parser::EnvParser,
+    resolve::resolve,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, converting its result into an owned C string or storing the error for
+/// [`awsm_env_last_error`] and returning null
+fn into_c_string(f: impl FnOnce() -> Result<String>) -> *mut c_char {
+    let result = f().and_then(|output| CString::new(output).context("Output contains a NUL byte"));
+
+    match result {
+        Ok(output) => {
+            LAST_ERROR.with(|e| e.borrow_mut().take());
+            output.into_raw()
+        }
+        Err(err) => {
+            let message = CString::new(format!("{:#}", err).replace('\0', ""))
+                .expect("NUL bytes were removed");
+            LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `s` must be null or point to a valid NUL-terminated string
+unsafe fn from_c_str<'a>(s: *const c_char, name: &str) -> Result<Option<&'a str>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map(Some)
+        .with_context(|| format!("{} is not valid UTF-8", name))
+}
+
+/// Parses a spec, returning its entries as a JSON array of
+/// `{key, required, default, provider, id, tags, ttl, description, deprecated}` objects, or null on error
+///
+/// # Safety
+///
+/// `spec` must point to a valid NUL-terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn awsm_env_parse(spec: *const c_char) -> *mut c_char {
+    into_c_string(|| {
+        let spec = unsafe { from_c_str(spec, "spec")? }.ok_or_else(|| anyhow!("spec is null"))?;
+        let variables = EnvParser::parse_variables(spec)?;
+        Ok(spec_entries(&variables).to_string())
+    })
+}
+
+/// Parses a spec and fetches its secrets, returning a JSON object of the form
+/// `{"KEY": "value"}` as produced by `--format json`, or null on error.
+/// `placeholders` is an optional JSON object of placeholder values.
+///
+/// # Safety
+///
+/// `spec` must point to a valid NUL-terminated string, `placeholders` must be null or
+/// point to a valid NUL-terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn awsm_env_resolve(
+    spec: *const c_char,
+    placeholders: *const c_char,
+) -> *mut c_char {
+    into_c_string(|| {
+        let spec = unsafe { from_c_str(spec, "spec")? }.ok_or_else(|| anyhow!("spec is null"))?;
+        let placeholders: IndexMap<String, String> =
+            match unsafe { from_c_str(placeholders, "placeholders")? } {
+                Some(json) => serde_json::from_str(json).context("Failed to parse placeholders")?,
+                None => IndexMap::new(),
+            };
+
+        let mut variables = EnvParser::parse_variables(spec)?;
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start runtime")?
+            .block_on(resolve(&mut variables, placeholders))
+            .context("Failed to fetch secrets")?;
+
+        variables.drop_empty();
+        JsonOutput.format(variables)
+    })
+}
+
+/// Returns the error message of the last failed call on this thread, or null. The string
+/// is owned by the library and valid until the next call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn awsm_env_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Frees a string returned by this library
+///
+/// # Safety
+///
+/// `s` must be null or a pointer returned by this library that hasn't been freed yet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn awsm_env_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(s: *mut c_char) -> String {
+        let owned = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_owned();
+        unsafe { awsm_env_free(s) };
+        owned
+    }
+
+    #[test]
+    fn test_parse() {
+        let spec = CString::new("# @aws-sm foo/bar\nKEY1=value1\n").unwrap();
+        let result = take(unsafe { awsm_env_parse(spec.as_ptr()) });
+
+        assert_eq!(
+            result,
+            r#"[{"key":"KEY1","required":true,"default":"value1","provider":"aws-sm","id":"foo/bar","tags":{},"ttl":null,"description":null,"deprecated":null}]"#
+        );
+        assert!(awsm_env_last_error().is_null());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let spec = CString::new("not a declaration\n").unwrap();
+        let result = unsafe { awsm_env_parse(spec.as_ptr()) };
+
+        assert!(result.is_null());
+        let error = unsafe { CStr::from_ptr(awsm_env_last_error()) };
+        assert!(!error.to_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_null_spec() {
+        assert!(unsafe { awsm_env_parse(ptr::null()) }.is_null());
+        let error = unsafe { CStr::from_ptr(awsm_env_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "spec is null");
+    }
+}
@@ -0,0 +1,137 @@
+//! Named pipes for `--output-fifo`, handing the rendered output to a single reader without
+//! it ever being written to disk
+
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::{
+    ffi::CString,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::{
+        fd::AsRawFd,
+        unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    },
+    time::Duration,
+};
+
+#[cfg(unix)]
+use anyhow::Context;
+use anyhow::Result;
+
+/// How often to check whether a reader has opened the pipe
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A named pipe, removed when dropped
+pub struct Fifo {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Fifo {
+    /// Creates a named pipe at `path` that only the owner can open. Fails if `path` exists.
+    pub fn create(path: &Path) -> Result<Self> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("Failed to create named pipe {}", path.display()));
+        }
+
+        Ok(Fifo {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Waits for a reader to open the pipe and writes `contents` to it
+    pub async fn write(&self, contents: Vec<u8>) -> Result<()> {
+        // Opening for writing blocks until there's a reader, so poll without blocking to
+        // stay interruptible
+        let mut file = loop {
+            match self.try_open()? {
+                Some(file) => break file,
+                None => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        };
+        set_blocking(&file)?;
+
+        tokio::task::spawn_blocking(move || file.write_all(&contents)).await??;
+        Ok(())
+    }
+
+    /// Opens the write end, `None` while no reader has opened the pipe
+    fn try_open(&self) -> Result<Option<File>> {
+        match OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path)
+        {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("Failed to open {}", self.path.display())),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_blocking(file: &File) -> Result<()> {
+    let fd = file.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) } < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+impl Fifo {
+    pub fn create(_path: &Path) -> Result<Self> {
+        Err(anyhow::anyhow!("--output-fifo is only supported on Unix"))
+    }
+
+    pub async fn write(&self, _contents: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Fifo {
+    fn drop(&mut self) {
+        // A reader left waiting, e.g. because rendering failed, would block forever once
+        // the pipe is gone. Opening the write end and closing it again gives it an EOF.
+        #[cfg(unix)]
+        let _ = self.try_open();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, io::Read};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_writes_to_one_reader() {
+        let path = std::env::temp_dir().join(format!("awsm_env_test_fifo_{}", std::process::id()));
+        let fifo = Fifo::create(&path).unwrap();
+        assert!(Fifo::create(&path).is_err());
+
+        let reader = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut contents = String::new();
+                fs::File::open(path)
+                    .unwrap()
+                    .read_to_string(&mut contents)
+                    .unwrap();
+                contents
+            })
+        };
+
+        fifo.write(b"PORT=8080\n".to_vec()).await.unwrap();
+        drop(fifo);
+
+        assert_eq!(reader.join().unwrap(), "PORT=8080\n");
+        assert!(!path.exists());
+    }
+}
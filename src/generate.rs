@@ -0,0 +1,144 @@
+//! Random values for secrets declared with `@generate`, created on first use so new
+//! environments don't need a human to invent and upload them
+
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{Context, Error, Result, anyhow};
+
+#[cfg(feature = "aws")]
+const ALNUM: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// How to generate a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    /// `hex:<bytes>`, the given number of random bytes hex encoded
+    Hex(usize),
+    /// `alnum:<length>`, random ASCII letters and digits
+    Alnum(usize),
+    /// `uuid`, a random version 4 UUID
+    Uuid,
+}
+
+#[cfg(feature = "aws")]
+impl Generator {
+    pub fn generate(&self) -> Result<String> {
+        match *self {
+            Generator::Hex(bytes) => Ok(random_bytes(bytes)?
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()),
+            Generator::Alnum(length) => {
+                let mut value = String::with_capacity(length);
+
+                // Rejecting bytes past the largest multiple of the alphabet's size keeps
+                // every character equally likely
+                let limit = u8::MAX - u8::MAX % ALNUM.len() as u8;
+                while value.len() < length {
+                    for byte in random_bytes(length - value.len())? {
+                        if byte < limit {
+                            value.push(ALNUM[byte as usize % ALNUM.len()] as char);
+                        }
+                    }
+                }
+
+                Ok(value)
+            }
+            Generator::Uuid => {
+                let mut bytes = random_bytes(16)?;
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+                let hex = bytes
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+
+                Ok(format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "aws")]
+fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; len];
+    getrandom::fill(&mut bytes)
+        .map_err(|err| anyhow!("Failed to generate random bytes: {}", err))?;
+    Ok(bytes)
+}
+
+impl FromStr for Generator {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let length = |value: &str| -> Result<usize> {
+            match value.parse().context("Expected a length")? {
+                0 => Err(anyhow!("Length must be greater than 0")),
+                length => Ok(length),
+            }
+        };
+
+        match spec.split_once(':') {
+            Some(("hex", bytes)) => Ok(Generator::Hex(length(bytes)?)),
+            Some(("alnum", chars)) => Ok(Generator::Alnum(length(chars)?)),
+            None if spec == "uuid" => Ok(Generator::Uuid),
+            _ => Err(anyhow!(
+                "Unknown generator '{}', expected `hex:<bytes>`, `alnum:<length>` or `uuid`",
+                spec
+            )),
+        }
+    }
+}
+
+impl Display for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Generator::Hex(bytes) => write!(f, "hex:{}", bytes),
+            Generator::Alnum(length) => write!(f, "alnum:{}", length),
+            Generator::Uuid => write!(f, "uuid"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_generators() {
+        assert_eq!("hex:32".parse::<Generator>().unwrap(), Generator::Hex(32));
+        assert_eq!(
+            "alnum:24".parse::<Generator>().unwrap(),
+            Generator::Alnum(24)
+        );
+        assert_eq!("uuid".parse::<Generator>().unwrap(), Generator::Uuid);
+        assert_eq!(Generator::Hex(32).to_string(), "hex:32");
+        assert!("hex:0".parse::<Generator>().is_err());
+        assert!("hex:x".parse::<Generator>().is_err());
+        assert!("base32:8".parse::<Generator>().is_err());
+    }
+
+    #[cfg(feature = "aws")]
+    #[test]
+    fn test_generates_values() {
+        let hex = Generator::Hex(32).generate().unwrap();
+        assert_eq!(hex.len(), 64);
+        assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(hex, Generator::Hex(32).generate().unwrap());
+
+        let alnum = Generator::Alnum(40).generate().unwrap();
+        assert_eq!(alnum.len(), 40);
+        assert!(alnum.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        let uuid = Generator::Uuid.generate().unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+    }
+}
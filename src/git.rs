@@ -0,0 +1,98 @@
+//! Queries against the git repository in the working directory
+
+use std::{path::Path, process::Command};
+
+use anyhow::{Context, Result, anyhow};
+
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Paths and staged contents of the files added, copied, modified or renamed in the index
+pub fn staged_files() -> Result<Vec<(String, Vec<u8>)>> {
+    let names = git(&[
+        "diff",
+        "--cached",
+        "--name-only",
+        "-z",
+        "--diff-filter=ACMR",
+    ])?;
+
+    names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let path = String::from_utf8_lossy(name).into_owned();
+            let contents = git(&["show", &format!(":{}", path)])
+                .with_context(|| format!("Failed to read staged contents of {}", path))?;
+            Ok((path, contents))
+        })
+        .collect()
+}
+
+/// Whether `path` is inside a git work tree and not ignored, so `git add` would pick it
+/// up. `false` when git isn't installed. The path doesn't need to exist yet.
+pub fn is_committable(path: &Path) -> bool {
+    // git needs an existing directory to find the repository from
+    let Some(base) = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.as_os_str().is_empty() || dir.is_dir())
+    else {
+        return false;
+    };
+    let Ok(relative) = path.strip_prefix(base) else {
+        return false;
+    };
+    let base = match base.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => base,
+    };
+
+    // Exits with 0 when ignored, 1 when not and 128 outside of a repository
+    Command::new("git")
+        .arg("-C")
+        .arg(base)
+        .args(["check-ignore", "-q", "--"])
+        .arg(relative)
+        .output()
+        .is_ok_and(|output| output.status.code() == Some(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_is_committable() {
+        let repo = std::env::temp_dir().join("awsm_env_test_git_repo");
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(&repo).unwrap();
+
+        assert!(!is_committable(&repo.join(".env")));
+
+        git(&["-C", repo.to_str().unwrap(), "init", "-q"]).unwrap();
+        fs::write(repo.join(".gitignore"), ".env\n").unwrap();
+
+        assert!(!is_committable(&repo.join(".env")));
+        assert!(is_committable(&repo.join("env.json")));
+        assert!(is_committable(&repo.join("config/new/env.sh")));
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+}
@@ -0,0 +1,260 @@
+//! Rendering a spec's plan as a graph of keys, the providers and secrets they come from
+//! and the keys their defaults reference, for architecture reviews and onboarding docs
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::plan::PlanEntry;
+
+/// Graph description language to render
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz DOT, render with `dot -Tsvg`
+    #[default]
+    Dot,
+    /// A Mermaid flowchart, rendered by GitHub and GitLab in Markdown
+    Mermaid,
+}
+
+/// Nodes and edges of the graph, in the order they appear in the spec
+struct Graph<'a> {
+    /// Every key, including referenced ones the spec doesn't declare
+    keys: IndexSet<&'a str>,
+    declared: IndexSet<&'a str>,
+    /// Secret IDs of each provider
+    secrets: IndexMap<&'a str, IndexSet<&'a str>>,
+    /// Key to the provider and ID of its secret
+    fetches: Vec<(&'a str, &'a str, &'a str)>,
+    /// Key to a key its default references
+    references: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(plan: &'a [PlanEntry]) -> Self {
+        let mut graph = Graph {
+            keys: plan.iter().map(|entry| entry.key.as_str()).collect(),
+            declared: plan.iter().map(|entry| entry.key.as_str()).collect(),
+            secrets: IndexMap::new(),
+            fetches: Vec::new(),
+            references: Vec::new(),
+        };
+
+        for entry in plan {
+            if let (Some(provider), Some(id)) = (&entry.provider, &entry.id) {
+                graph.secrets.entry(provider).or_default().insert(id);
+                graph.fetches.push((&entry.key, provider, id));
+            }
+
+            for name in &entry.references {
+                graph.keys.insert(name);
+                graph.references.push((&entry.key, name));
+            }
+        }
+
+        graph
+    }
+
+    fn label(&self, key: &str) -> String {
+        match self.declared.contains(key) {
+            true => key.to_string(),
+            false => format!("{} (undeclared)", key),
+        }
+    }
+}
+
+/// Renders `plan` as a graph in `format`
+pub fn render(plan: &[PlanEntry], format: GraphFormat) -> String {
+    let graph = Graph::new(plan);
+
+    match format {
+        GraphFormat::Dot => dot(&graph),
+        GraphFormat::Mermaid => mermaid(&graph),
+    }
+}
+
+fn dot(graph: &Graph) -> String {
+    let mut lines = vec![
+        "digraph spec {".to_string(),
+        "  rankdir=LR;".to_string(),
+        "  node [shape=box];".to_string(),
+    ];
+
+    for key in &graph.keys {
+        let style = match graph.declared.contains(key) {
+            true => "",
+            false => ", style=dashed",
+        };
+        lines.push(format!(
+            "  {} [label={}{}];",
+            quote(&format!("key:{}", key)),
+            quote(&graph.label(key)),
+            style
+        ));
+    }
+
+    for (provider, ids) in &graph.secrets {
+        lines.push(format!(
+            "  subgraph {} {{",
+            quote(&format!("cluster_{}", provider))
+        ));
+        lines.push(format!("    label={};", quote(provider)));
+        for id in ids {
+            lines.push(format!(
+                "    {} [label={}, shape=note];",
+                quote(&format!("secret:{}:{}", provider, id)),
+                quote(id)
+            ));
+        }
+        lines.push("  }".to_string());
+    }
+
+    for (key, provider, id) in &graph.fetches {
+        lines.push(format!(
+            "  {} -> {};",
+            quote(&format!("key:{}", key)),
+            quote(&format!("secret:{}:{}", provider, id))
+        ));
+    }
+
+    for (key, name) in &graph.references {
+        lines.push(format!(
+            "  {} -> {} [style=dashed];",
+            quote(&format!("key:{}", key)),
+            quote(&format!("key:{}", name))
+        ));
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// A DOT string literal
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn mermaid(graph: &Graph) -> String {
+    // Mermaid IDs can't hold arbitrary characters, so nodes are numbered
+    let key_id = |key: &str| format!("k{}", graph.keys.get_index_of(key).unwrap_or_default());
+    let secret_ids = graph
+        .secrets
+        .iter()
+        .flat_map(|(provider, ids)| ids.iter().map(move |id| (*provider, *id)))
+        .enumerate()
+        .map(|(i, secret)| (secret, format!("s{}", i)))
+        .collect::<IndexMap<_, _>>();
+
+    let mut lines = vec!["flowchart LR".to_string()];
+
+    for (i, key) in graph.keys.iter().enumerate() {
+        let label = mermaid_label(&graph.label(key));
+        match graph.declared.contains(key) {
+            true => lines.push(format!("  k{}[{}]", i, label)),
+            false => lines.push(format!("  k{}([{}])", i, label)),
+        }
+    }
+
+    for (i, (provider, ids)) in graph.secrets.iter().enumerate() {
+        lines.push(format!("  subgraph p{}[{}]", i, mermaid_label(provider)));
+        for id in ids {
+            lines.push(format!(
+                "    {}[/{}/]",
+                secret_ids[&(*provider, *id)],
+                mermaid_label(id)
+            ));
+        }
+        lines.push("  end".to_string());
+    }
+
+    for (key, provider, id) in &graph.fetches {
+        lines.push(format!(
+            "  {} --> {}",
+            key_id(key),
+            secret_ids[&(*provider, *id)]
+        ));
+    }
+
+    for (key, name) in &graph.references {
+        lines.push(format!("  {} -.-> {}", key_id(key), key_id(name)));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// A quoted Mermaid label, `"` is written as an entity code
+fn mermaid_label(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "#quot;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use crate::{parser::EnvParser, plan::plan};
+
+    use super::*;
+
+    const SPEC: &str = "# @aws-sm app/db\nDB_PASSWORD=\n\n# @aws-sm app/db\nDB_USER=\n\n# @aws-ps /app/host\nDB_HOST=\n\nDB_URL=postgres://${DB_USER}@${DB_HOST}/${DB_NAME}\n";
+
+    #[test]
+    fn test_render_dot() {
+        let variables = EnvParser::parse_variables(SPEC).unwrap();
+        let plan = plan(&variables, &IndexMap::new());
+
+        assert_eq!(
+            render(&plan, GraphFormat::Dot),
+            r#"digraph spec {
+  rankdir=LR;
+  node [shape=box];
+  "key:DB_PASSWORD" [label="DB_PASSWORD"];
+  "key:DB_USER" [label="DB_USER"];
+  "key:DB_HOST" [label="DB_HOST"];
+  "key:DB_URL" [label="DB_URL"];
+  "key:DB_NAME" [label="DB_NAME (undeclared)", style=dashed];
+  subgraph "cluster_aws-sm" {
+    label="aws-sm";
+    "secret:aws-sm:app/db" [label="app/db", shape=note];
+  }
+  subgraph "cluster_aws-ps" {
+    label="aws-ps";
+    "secret:aws-ps:/app/host" [label="/app/host", shape=note];
+  }
+  "key:DB_PASSWORD" -> "secret:aws-sm:app/db";
+  "key:DB_USER" -> "secret:aws-sm:app/db";
+  "key:DB_HOST" -> "secret:aws-ps:/app/host";
+  "key:DB_URL" -> "key:DB_USER" [style=dashed];
+  "key:DB_URL" -> "key:DB_HOST" [style=dashed];
+  "key:DB_URL" -> "key:DB_NAME" [style=dashed];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let variables = EnvParser::parse_variables(SPEC).unwrap();
+        let plan = plan(&variables, &IndexMap::new());
+
+        assert_eq!(
+            render(&plan, GraphFormat::Mermaid),
+            r#"flowchart LR
+  k0["DB_PASSWORD"]
+  k1["DB_USER"]
+  k2["DB_HOST"]
+  k3["DB_URL"]
+  k4(["DB_NAME (undeclared)"])
+  subgraph p0["aws-sm"]
+    s0[/"app/db"/]
+  end
+  subgraph p1["aws-ps"]
+    s1[/"/app/host"/]
+  end
+  k0 --> s0
+  k1 --> s0
+  k2 --> s1
+  k3 -.-> k1
+  k3 -.-> k2
+  k3 -.-> k4
+"#
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Scanning of files about to be committed for resolved secret values, so rendered env
+//! files don't end up in version control
+
+use indexmap::IndexMap;
+
+/// Values shorter than this are too likely to appear by chance, e.g. `true` or `8080`
+pub const MIN_SECRET_LENGTH: usize = 6;
+
+/// A file containing the value of a secret-backed key
+#[derive(Debug, PartialEq, Eq)]
+pub struct Leak {
+    pub path: String,
+    pub key: String,
+}
+
+/// Finds the files containing any of `secrets`, a map of keys to their values. Values
+/// shorter than [`MIN_SECRET_LENGTH`] are ignored.
+pub fn find_leaks(secrets: &IndexMap<String, String>, files: &[(String, Vec<u8>)]) -> Vec<Leak> {
+    let secrets = secrets
+        .iter()
+        .filter(|(_, value)| value.len() >= MIN_SECRET_LENGTH)
+        .collect::<Vec<_>>();
+
+    files
+        .iter()
+        .flat_map(|(path, contents)| {
+            let contents = String::from_utf8_lossy(contents);
+
+            secrets
+                .iter()
+                .filter(move |(_, value)| contents.contains(value.as_str()))
+                .map(|(key, _)| Leak {
+                    path: path.clone(),
+                    key: key.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_leaks() {
+        let secrets = IndexMap::from([
+            ("API_TOKEN".to_string(), "tok_4f9a8b".to_string()),
+            ("DB_PASSWORD".to_string(), "hunter2hunter2".to_string()),
+            ("DEBUG".to_string(), "true".to_string()),
+        ]);
+        let files = vec![
+            (
+                ".env".to_string(),
+                b"API_TOKEN=\"tok_4f9a8b\"\nDB_PASSWORD=\"hunter2hunter2\"\n".to_vec(),
+            ),
+            ("src/main.rs".to_string(), b"let debug = true;\n".to_vec()),
+            ("notes.md".to_string(), b"token is tok_4f9a8b\n".to_vec()),
+        ];
+
+        let leaks = find_leaks(&secrets, &files);
+
+        assert_eq!(
+            leaks
+                .iter()
+                .map(|leak| (leak.path.as_str(), leak.key.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (".env", "API_TOKEN"),
+                (".env", "DB_PASSWORD"),
+                ("notes.md", "API_TOKEN")
+            ]
+        );
+    }
+}
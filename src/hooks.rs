@@ -0,0 +1,295 @@
+//! Hooks run around resolution and rendering, e.g. to compute placeholders or to restart
+//! a service once new values are written
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::variable::Variables;
+
+/// Describes a successful render, passed to [`PostRenderHook`]s. Never includes values.
+/// See [`Schema::Summary`](crate::schema::Schema::Summary).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderSummary {
+    /// File the output was written to, `None` for stdout
+    pub output: Option<PathBuf>,
+    pub format: String,
+    /// Keys in the output, in output order
+    pub keys: Vec<String>,
+    /// Number of keys whose value was fetched from a provider
+    pub secrets: usize,
+    pub rendered_at: String,
+}
+
+impl RenderSummary {
+    pub fn new(variables: &Variables, output: Option<PathBuf>, format: &str) -> Self {
+        RenderSummary {
+            output,
+            format: format.to_string(),
+            keys: variables.iter().map(|var| var.key.clone()).collect(),
+            secrets: variables
+                .iter()
+                .filter(|var| var.value.is_some() && var.provenance.is_some())
+                .count(),
+            rendered_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        }
+    }
+}
+
+/// Called after output has been written successfully
+pub trait PostRenderHook {
+    fn after_render(&self, summary: &RenderSummary) -> Result<()>;
+}
+
+impl<F: Fn(&RenderSummary) -> Result<()>> PostRenderHook for F {
+    fn after_render(&self, summary: &RenderSummary) -> Result<()> {
+        self(summary)
+    }
+}
+
+/// Called after the spec is parsed and before secrets are fetched, to supply values for
+/// placeholders that weren't given
+pub trait PlaceholderHook {
+    /// Returns values for any of the `missing` placeholder names, others are ignored
+    fn placeholders(&self, missing: &[String]) -> Result<IndexMap<String, String>>;
+}
+
+impl<F: Fn(&[String]) -> Result<IndexMap<String, String>>> PlaceholderHook for F {
+    fn placeholders(&self, missing: &[String]) -> Result<IndexMap<String, String>> {
+        self(missing)
+    }
+}
+
+/// Called before rendering to encrypt the values of keys marked `@seal`, so the output
+/// can be committed
+pub trait SealHook {
+    /// Returns the sealed form of `value`, which belongs to `key`
+    fn seal(&self, key: &str, value: &str) -> Result<String>;
+}
+
+impl<F: Fn(&str, &str) -> Result<String>> SealHook for F {
+    fn seal(&self, key: &str, value: &str) -> Result<String> {
+        self(key, value)
+    }
+}
+
+/// A shell command used as a hook, exchanging JSON over stdin and stdout
+pub struct CommandHook(pub String);
+
+impl CommandHook {
+    /// Runs the command with `input` on stdin. Its stdout is captured when `capture` is
+    /// set and goes to stderr otherwise, so it never mixes with output written to stdout.
+    fn run(
+        &self,
+        name: &str,
+        command: &mut Command,
+        input: &[u8],
+        capture: bool,
+    ) -> Result<Vec<u8>> {
+        let stdout = match capture {
+            true => Stdio::piped(),
+            false => io::stderr().into(),
+        };
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(stdout)
+            .spawn()
+            .with_context(|| format!("Failed to run {} `{}`", name, self.0))?;
+
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        // Hooks that don't read stdin may exit before it is written
+        let _ = stdin.write_all(input);
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("Failed to run {} `{}`", name, self.0))?;
+
+        match output.status.success() {
+            true => Ok(output.stdout),
+            false => Err(anyhow!(
+                "{} `{}` failed with {}",
+                capitalize(name),
+                self.0,
+                output.status
+            )),
+        }
+    }
+}
+
+/// Gets the summary as JSON on stdin and the output path in `AWSM_ENV_OUTPUT`
+impl PostRenderHook for CommandHook {
+    fn after_render(&self, summary: &RenderSummary) -> Result<()> {
+        let mut command = shell(&self.0);
+        if let Some(output) = &summary.output {
+            command.env("AWSM_ENV_OUTPUT", output);
+        }
+
+        let input = serde_json::to_vec(summary)?;
+        self.run("post-hook", &mut command, &input, false)?;
+
+        Ok(())
+    }
+}
+
+/// Gets the missing names as a JSON array on stdin and prints a JSON object mapping
+/// names to values
+impl PlaceholderHook for CommandHook {
+    fn placeholders(&self, missing: &[String]) -> Result<IndexMap<String, String>> {
+        let input = serde_json::to_vec(missing)?;
+        let output = self.run("placeholder hook", &mut shell(&self.0), &input, true)?;
+
+        serde_json::from_slice(&output).with_context(|| {
+            format!(
+                "Placeholder hook `{}` must print a JSON object of strings",
+                self.0
+            )
+        })
+    }
+}
+
+/// Gets the value on stdin and the key in `AWSM_ENV_KEY`, and prints the sealed value.
+/// A trailing newline is removed.
+impl SealHook for CommandHook {
+    fn seal(&self, key: &str, value: &str) -> Result<String> {
+        let mut command = shell(&self.0);
+        command.env("AWSM_ENV_KEY", key);
+
+        let output = self.run("seal command", &mut command, value.as_bytes(), true)?;
+        let sealed = String::from_utf8(output)
+            .with_context(|| format!("Seal command `{}` must print UTF-8", self.0))?;
+
+        Ok(sealed
+            .strip_suffix('\n')
+            .map(|sealed| sealed.strip_suffix('\r').unwrap_or(sealed))
+            .unwrap_or(&sealed)
+            .to_string())
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fs};
+
+    use crate::variable::Variable;
+
+    use super::*;
+
+    fn summary() -> RenderSummary {
+        let variables: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            value: Some("hunter2".to_string()),
+            ..Default::default()
+        }]
+        .into();
+
+        RenderSummary::new(&variables, Some(PathBuf::from(".env")), "env")
+    }
+
+    #[test]
+    fn test_closure_hook() {
+        let calls = Cell::new(0);
+        let hook = |summary: &RenderSummary| {
+            assert_eq!(summary.keys, ["KEY1"]);
+            calls.set(calls.get() + 1);
+            Ok(())
+        };
+
+        hook.after_render(&summary()).unwrap();
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_hook() {
+        let path = std::env::temp_dir().join("awsm_env_test_post_hook.json");
+        let hook = CommandHook(format!(
+            "cat > {} && test \"$AWSM_ENV_OUTPUT\" = .env",
+            path.display()
+        ));
+
+        hook.after_render(&summary()).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["keys"], serde_json::json!(["KEY1"]));
+        assert_eq!(written["output"], ".env");
+        assert!(!written.to_string().contains("hunter2"));
+
+        let err = CommandHook("exit 3".to_string())
+            .after_render(&summary())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Post-hook `exit 3` failed"));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_placeholder_hook() {
+        let missing = vec!["env".to_string(), "branch".to_string()];
+
+        let hook = CommandHook(
+            r#"test "$(cat)" = '["env","branch"]' && echo '{"env":"prod","branch":"main"}'"#
+                .to_string(),
+        );
+        let values = hook.placeholders(&missing).unwrap();
+        assert_eq!(values["env"], "prod");
+        assert_eq!(values["branch"], "main");
+
+        let err = CommandHook("echo not json".to_string())
+            .placeholders(&missing)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Placeholder hook `echo not json` must print a JSON object of strings"
+        );
+
+        let err = CommandHook("exit 1".to_string())
+            .placeholders(&missing)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Placeholder hook `exit 1` failed")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_seal_hook() {
+        let hook =
+            CommandHook(r#"printf 'sealed:%s:' "$AWSM_ENV_KEY"; tr a-z A-Z; echo"#.to_string());
+
+        assert_eq!(hook.seal("TOKEN", "abc").unwrap(), "sealed:TOKEN:ABC");
+    }
+}
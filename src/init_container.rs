@@ -0,0 +1,63 @@
+//! Exit codes and retry policy of `awsm-env init-container`, which materializes secrets
+//! into a shared volume before the application container of an ECS task or Kubernetes
+//! pod starts
+
+use std::{fmt, time::Duration};
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Step of `init-container` that failed, each with its own exit code so orchestrators
+/// and alerts can tell them apart. Other failures, such as an unreadable spec, exit with 1
+/// and usage errors with 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitFailure {
+    /// No credentials became available within `--wait-for-iam`
+    Credentials,
+    /// Resolving failed on every attempt
+    Resolve,
+    /// The output location couldn't be prepared
+    Output,
+}
+
+impl InitFailure {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            InitFailure::Credentials => 3,
+            InitFailure::Resolve => 4,
+            InitFailure::Output => 5,
+        }
+    }
+}
+
+impl fmt::Display for InitFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitFailure::Credentials => write!(f, "AWS credentials didn't become available"),
+            InitFailure::Resolve => write!(f, "Failed to resolve the spec"),
+            InitFailure::Output => write!(f, "Failed to prepare the output"),
+        }
+    }
+}
+
+/// How long to wait before retry number `attempt`, starting at 1. Doubles from `base`
+/// each time, up to 30s.
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let second = Duration::from_secs(1);
+
+        assert_eq!(backoff(second, 1), second);
+        assert_eq!(backoff(second, 3), second * 4);
+        assert_eq!(backoff(second, 6), MAX_BACKOFF);
+        assert_eq!(backoff(second, 100), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,171 @@
+//! Storing resolved values in the OS credential store instead of a file: the macOS
+//! Keychain, Windows Credential Manager or the Secret Service (libsecret) on Linux
+//!
+//! Values are stored under the service `awsm-env:<namespace>` with the key as the account,
+//! so apps can also read them with the platform's own APIs. Credential stores can't list
+//! their entries, so the stored keys are recorded in an extra index entry.
+
+use anyhow::Result;
+use indexmap::IndexMap;
+
+use crate::variable::Variables;
+
+/// Account of the entry listing the keys stored in a namespace, `@` can't start a key
+const INDEX_ACCOUNT: &str = "@keys";
+
+/// A store of secrets addressed by service and account
+pub trait CredentialStore {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>>;
+    fn set(&self, service: &str, account: &str, value: &str) -> Result<()>;
+    /// Deleting an entry that doesn't exist succeeds
+    fn delete(&self, service: &str, account: &str) -> Result<()>;
+}
+
+/// The platform's credential store
+#[cfg(feature = "keychain")]
+pub struct Keychain;
+
+#[cfg(feature = "keychain")]
+impl CredentialStore for Keychain {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>> {
+        match keyring::Entry::new(service, account)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, value: &str) -> Result<()> {
+        Ok(keyring::Entry::new(service, account)?.set_password(value)?)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<()> {
+        match keyring::Entry::new(service, account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Service the values of `namespace` are stored under
+pub fn service(namespace: &str) -> String {
+    format!("awsm-env:{}", namespace)
+}
+
+/// Replaces the values stored in `namespace` with those of `variables` that have one,
+/// removing keys stored previously that aren't in `variables`. Returns the number of
+/// values stored.
+pub fn store(store: &dyn CredentialStore, namespace: &str, variables: &Variables) -> Result<usize> {
+    let service = service(namespace);
+    let previous = keys(store, &service)?;
+
+    let mut stored = Vec::new();
+    for var in variables.iter() {
+        if let Some(value) = var.value.as_deref().or(var.default.as_deref()) {
+            store.set(&service, &var.key, value)?;
+            stored.push(var.key.as_str());
+        }
+    }
+
+    for key in previous
+        .iter()
+        .filter(|key| !stored.contains(&key.as_str()))
+    {
+        store.delete(&service, key)?;
+    }
+
+    match stored.is_empty() {
+        true => store.delete(&service, INDEX_ACCOUNT)?,
+        false => store.set(&service, INDEX_ACCOUNT, &stored.join("\n"))?,
+    }
+
+    Ok(stored.len())
+}
+
+/// Reads the value of `key` stored in `namespace`
+pub fn read(store: &dyn CredentialStore, namespace: &str, key: &str) -> Result<Option<String>> {
+    store.get(&service(namespace), key)
+}
+
+/// Reads every value stored in `namespace`, in the order they were stored
+pub fn read_all(store: &dyn CredentialStore, namespace: &str) -> Result<IndexMap<String, String>> {
+    let service = service(namespace);
+    let mut values = IndexMap::new();
+
+    for key in keys(store, &service)? {
+        if let Some(value) = store.get(&service, &key)? {
+            values.insert(key, value);
+        }
+    }
+
+    Ok(values)
+}
+
+fn keys(store: &dyn CredentialStore, service: &str) -> Result<Vec<String>> {
+    Ok(store
+        .get(service, INDEX_ACCOUNT)?
+        .map(|index| index.lines().map(str::to_string).collect())
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use crate::parser::EnvParser;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStore(RefCell<HashMap<(String, String), String>>);
+
+    impl CredentialStore for MemoryStore {
+        fn get(&self, service: &str, account: &str) -> Result<Option<String>> {
+            let entries = self.0.borrow();
+            Ok(entries.get(&(service.into(), account.into())).cloned())
+        }
+
+        fn set(&self, service: &str, account: &str, value: &str) -> Result<()> {
+            let mut entries = self.0.borrow_mut();
+            entries.insert((service.into(), account.into()), value.into());
+            Ok(())
+        }
+
+        fn delete(&self, service: &str, account: &str) -> Result<()> {
+            let mut entries = self.0.borrow_mut();
+            entries.remove(&(service.into(), account.into()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stores_namespaces() {
+        let keychain = MemoryStore::default();
+
+        let variables = EnvParser::parse_variables("TOKEN=abc\nPORT=8080\nEMPTY=\n").unwrap();
+        assert_eq!(store(&keychain, "app", &variables).unwrap(), 2);
+        let variables = EnvParser::parse_variables("PORT=9090\n").unwrap();
+        store(&keychain, "other", &variables).unwrap();
+
+        assert_eq!(
+            read(&keychain, "app", "TOKEN").unwrap().as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            read_all(&keychain, "app").unwrap(),
+            IndexMap::from([
+                ("TOKEN".to_string(), "abc".to_string()),
+                ("PORT".to_string(), "8080".to_string())
+            ])
+        );
+
+        // Keys no longer in the spec are removed
+        store(&keychain, "app", &variables).unwrap();
+        assert_eq!(read(&keychain, "app", "TOKEN").unwrap(), None);
+        assert_eq!(read_all(&keychain, "app").unwrap().len(), 1);
+        assert_eq!(
+            read(&keychain, "other", "PORT").unwrap().as_deref(),
+            Some("9090")
+        );
+    }
+}
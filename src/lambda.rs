@@ -0,0 +1,231 @@
+//! Client for the Lambda Extensions API, letting `awsm-env-lambda-extension` run as an
+//! external extension that renders the function's spec before it starts. The API is
+//! plain HTTP on the address in `AWS_LAMBDA_RUNTIME_API`, so a minimal HTTP/1.1 client
+//! over a TCP stream is enough.
+
+use std::env;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const API_VERSION: &str = "2020-01-01";
+
+/// Lifecycle event delivered to the extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Invoke,
+    Shutdown,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventBody {
+    event_type: String,
+}
+
+/// A registered extension
+pub struct ExtensionClient {
+    api: String,
+    id: String,
+}
+
+impl ExtensionClient {
+    /// Registers for `INVOKE` and `SHUTDOWN` events. `name` must be the file name of the
+    /// extension in `/opt/extensions`.
+    pub async fn register(name: &str) -> Result<Self> {
+        let api = env::var("AWS_LAMBDA_RUNTIME_API")
+            .context("AWS_LAMBDA_RUNTIME_API isn't set, not running in Lambda")?;
+
+        let response = request(
+            &api,
+            "POST",
+            &format!("/{}/extension/register", API_VERSION),
+            &[("Lambda-Extension-Name", name)],
+            br#"{"events":["INVOKE","SHUTDOWN"]}"#,
+        )
+        .await
+        .context("Failed to register extension")?;
+
+        let id = response
+            .header("Lambda-Extension-Identifier")
+            .ok_or_else(|| anyhow!("Registration response has no extension identifier"))?
+            .to_string();
+
+        Ok(ExtensionClient { api, id })
+    }
+
+    /// Waits for the next event, the extension is frozen in between invocations
+    pub async fn next_event(&self) -> Result<Event> {
+        let response = request(
+            &self.api,
+            "GET",
+            &format!("/{}/extension/event/next", API_VERSION),
+            &[("Lambda-Extension-Identifier", &self.id)],
+            b"",
+        )
+        .await
+        .context("Failed to get the next event")?;
+
+        parse_event(&response.body)
+    }
+}
+
+fn parse_event(body: &[u8]) -> Result<Event> {
+    let body: EventBody = serde_json::from_slice(body).context("Failed to parse event")?;
+
+    match body.event_type.as_str() {
+        "INVOKE" => Ok(Event::Invoke),
+        "SHUTDOWN" => Ok(Event::Shutdown),
+        other => Err(anyhow!("Unexpected event type {}", other)),
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+async fn request(
+    api: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Response> {
+    let mut stream = TcpStream::connect(api)
+        .await
+        .with_context(|| format!("Failed to connect to {}", api))?;
+
+    let mut message = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        api,
+        body.len()
+    );
+    for (name, value) in headers {
+        message.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    message.push_str("\r\n");
+
+    stream.write_all(message.as_bytes()).await?;
+    stream.write_all(body).await?;
+
+    // The connection is closed after the response, so it ends at EOF
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).await?;
+
+    let response = parse_response(&raw)?;
+    if !(200..300).contains(&response.status) {
+        return Err(anyhow!(
+            "{} {} returned {}: {}",
+            method,
+            path,
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        ));
+    }
+
+    Ok(response)
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response> {
+    let end = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("Incomplete HTTP response"))?;
+    let head = String::from_utf8_lossy(&raw[..end]);
+    let mut lines = head.split("\r\n");
+
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| anyhow!("Invalid HTTP status line"))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut response = Response {
+        status,
+        headers,
+        body: raw[end + 4..].to_vec(),
+    };
+    if response
+        .header("Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        response.body = decode_chunked(&response.body)?;
+    }
+
+    Ok(response)
+}
+
+fn decode_chunked(mut raw: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+
+    loop {
+        let line_end = raw
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(|| anyhow!("Incomplete chunked body"))?;
+        let size = String::from_utf8_lossy(&raw[..line_end]);
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16)
+            .context("Invalid chunk size")?;
+        raw = &raw[line_end + 2..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+
+        let chunk = raw
+            .get(..size)
+            .ok_or_else(|| anyhow!("Incomplete chunked body"))?;
+        body.extend_from_slice(chunk);
+        raw = raw.get(size + 2..).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_responses() {
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\nLambda-Extension-Identifier: abc-123\r\nContent-Length: 24\r\n\r\n{\"eventType\":\"SHUTDOWN\"}",
+        )
+        .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.header("lambda-extension-identifier"),
+            Some("abc-123")
+        );
+        assert_eq!(parse_event(&response.body).unwrap(), Event::Shutdown);
+
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n9\r\n{\"eventTy\r\nd\r\npe\":\"INVOKE\"}\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(parse_event(&response.body).unwrap(), Event::Invoke);
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+}
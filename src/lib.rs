@@ -2,9 +2,73 @@
 //!
 //! A lightweight utility for syncing AWS Secrets Manager secrets to environment variables.
 
+#[cfg(feature = "aws")]
+pub mod accounts;
+pub mod audit;
+#[cfg(feature = "aws")]
+pub mod bootstrap;
+pub mod build_info;
+#[cfg(feature = "aws")]
+pub mod cargo;
+#[cfg(feature = "aws")]
 pub mod cli;
+#[cfg(feature = "aws")]
+pub mod config;
+pub mod direnv;
+pub mod discover;
+#[cfg(feature = "aws")]
+pub mod docs;
+#[cfg(feature = "aws")]
+pub mod doctor;
+#[cfg(feature = "aws")]
+pub mod ephemeral;
+pub mod eventlog;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "aws")]
+pub mod fifo;
+pub mod generate;
+pub mod git;
+#[cfg(feature = "aws")]
+pub mod graph;
+pub mod guard;
+pub mod hooks;
+pub mod init_container;
+pub mod keychain;
+#[cfg(feature = "aws")]
+pub mod lambda;
+pub mod limits;
+pub mod lock;
 pub mod output;
 pub mod parser;
+#[cfg(feature = "aws")]
+pub mod plan;
+#[cfg(feature = "aws")]
 pub mod provider;
+#[cfg(feature = "aws")]
+pub mod prune;
+#[cfg(feature = "aws")]
+pub mod remote;
+#[cfg(feature = "aws")]
 pub mod resolve;
-mod variable;
+#[cfg(feature = "aws")]
+pub mod rotation;
+pub mod scan;
+pub mod schema;
+pub mod seal;
+pub mod shellenv;
+#[cfg(feature = "aws")]
+pub mod shutdown;
+#[cfg(feature = "aws")]
+pub mod signing;
+pub mod snapshot;
+#[cfg(feature = "aws")]
+pub mod sso;
+pub mod term;
+#[cfg(feature = "aws")]
+pub mod tui;
+pub mod validate;
+pub mod variable;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,123 @@
+use anyhow::{Result, anyhow};
+
+use crate::variable::Variables;
+
+/// Linux refuses to `execve` with any single environment string larger than this
+pub const DEFAULT_MAX_VALUE_BYTES: usize = 128 * 1024;
+
+/// Size and count limits enforced on rendered output, `None` disables a limit
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Limits {
+    pub max_value_bytes: Option<usize>,
+    pub max_output_bytes: Option<usize>,
+    pub max_entries: Option<usize>,
+}
+
+impl Limits {
+    /// Checks the number of entries and the size of each value
+    pub fn check_variables(&self, variables: &Variables) -> Result<()> {
+        if let Some(max) = self.max_entries.filter(|max| variables.len() > *max) {
+            return Err(anyhow!(
+                "Output has {} entries, exceeding the limit of {} (--max-entries)",
+                variables.len(),
+                max
+            ));
+        }
+
+        if let Some(max) = self.max_value_bytes {
+            let oversized = variables
+                .iter()
+                .filter_map(|var| {
+                    let len = var.value.as_ref().or(var.default.as_ref())?.len();
+                    (len > max).then(|| format!("{} ({} bytes)", var.key, len))
+                })
+                .collect::<Vec<_>>();
+
+            if !oversized.is_empty() {
+                return Err(anyhow!(
+                    "Values exceed the limit of {} bytes (--max-value-bytes): {}",
+                    max,
+                    oversized.join(", ")
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the size of the formatted output
+    pub fn check_output(&self, output: &str) -> Result<()> {
+        match self.max_output_bytes {
+            Some(max) if output.len() > max => Err(anyhow!(
+                "Output is {} bytes, exceeding the limit of {} bytes (--max-output-bytes)",
+                output.len(),
+                max
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variable::Variable;
+
+    use super::*;
+
+    fn variables() -> Variables {
+        vec![
+            Variable {
+                key: "SMALL".to_string(),
+                value: Some("1234".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "LARGE".to_string(),
+                default: Some("12345678".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let limits = Limits::default();
+
+        assert!(limits.check_variables(&variables()).is_ok());
+        assert!(limits.check_output("output").is_ok());
+    }
+
+    #[test]
+    fn test_max_value_bytes() {
+        let limits = Limits {
+            max_value_bytes: Some(4),
+            ..Default::default()
+        };
+
+        let err = limits.check_variables(&variables()).unwrap_err();
+        assert!(err.to_string().contains("LARGE (8 bytes)"));
+        assert!(!err.to_string().contains("SMALL"));
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let limits = Limits {
+            max_entries: Some(1),
+            ..Default::default()
+        };
+
+        assert!(limits.check_variables(&variables()).is_err());
+    }
+
+    #[test]
+    fn test_max_output_bytes() {
+        let limits = Limits {
+            max_output_bytes: Some(4),
+            ..Default::default()
+        };
+
+        assert!(limits.check_output("1234").is_ok());
+        assert!(limits.check_output("12345").is_err());
+    }
+}
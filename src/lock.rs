@@ -0,0 +1,189 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    output::write_atomic,
+    variable::{ProviderConfig, Variables},
+};
+
+/// Default path of the lockfile written by `awsm-env lock`
+pub const DEFAULT_LOCKFILE: &str = "awsm-env.lock";
+
+/// A secret pinned to an exact version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedSecret {
+    pub provider: String,
+    pub id: String,
+    pub version: String,
+    /// When the version's value was set, so lockfile diffs show how old pinned values are
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_at: Option<String>,
+}
+
+/// Records the secret versions used for each key so renders can be reproduced
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub secrets: IndexMap<String, LockedSecret>,
+}
+
+impl Lockfile {
+    /// Builds a lockfile from the provenance of resolved variables
+    pub fn from_variables(variables: &Variables) -> Self {
+        let secrets = variables
+            .iter()
+            .filter_map(|var| {
+                let provenance = var.provenance.as_ref()?;
+                let locked = LockedSecret {
+                    provider: provenance.provider.to_owned(),
+                    id: provenance.id.clone(),
+                    version: provenance.version.clone()?,
+                    changed_at: provenance.changed_at_rfc3339(),
+                };
+                Some((var.key.clone(), locked))
+            })
+            .collect();
+
+        Self { secrets }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let input = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lockfile {}", path.display()))?;
+        serde_json::from_str(&input).context("Failed to parse lockfile")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let output = serde_json::to_string_pretty(self)? + "\n";
+        write_atomic(path, output.as_bytes())
+            .with_context(|| format!("Failed to write lockfile {}", path.display()))
+    }
+
+    /// Returns the locked version for `key`, checking that the lockfile entry
+    /// still matches the provider and resolved secret `id`. Keys missing from
+    /// the lockfile are an error unless they are optional.
+    pub fn version_for(
+        &self,
+        key: &str,
+        config: &ProviderConfig,
+        id: &str,
+        required: bool,
+    ) -> Result<Option<&str>> {
+        let Some(locked) = self.secrets.get(key) else {
+            return match required {
+                true => Err(anyhow!("{} is not in the lockfile", key)),
+                false => Ok(None),
+            };
+        };
+
+        if locked.provider != config.name() || locked.id != id {
+            return Err(anyhow!(
+                "Lockfile entry for {} ({} {}) doesn't match the spec ({} {}), run `awsm-env lock` again",
+                key,
+                locked.provider,
+                locked.id,
+                config.name(),
+                id
+            ));
+        }
+
+        Ok(Some(&locked.version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use crate::variable::{Provenance, Variable};
+
+    use super::*;
+
+    fn lockfile() -> Lockfile {
+        let variables: Variables = vec![
+            Variable {
+                key: "KEY1".to_string(),
+                value: Some("value1".to_string()),
+                provenance: Some(Provenance {
+                    provider: "aws-sm".to_string(),
+                    id: "prod/key1".to_string(),
+                    version: Some("v1".to_string()),
+                    fetched_at: UNIX_EPOCH,
+                    expires_at: None,
+                    changed_at: Some(UNIX_EPOCH),
+                }),
+                ..Default::default()
+            },
+            Variable {
+                key: "KEY2".to_string(),
+                value: Some("value2".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        Lockfile::from_variables(&variables)
+    }
+
+    #[test]
+    fn test_from_variables_only_locks_secrets() {
+        let lockfile = lockfile();
+
+        assert_eq!(lockfile.secrets.len(), 1);
+        assert_eq!(
+            lockfile.secrets["KEY1"],
+            LockedSecret {
+                provider: "aws-sm".to_string(),
+                id: "prod/key1".to_string(),
+                version: "v1".to_string(),
+                changed_at: Some("1970-01-01T00:00:00Z".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_version_for() {
+        let lockfile = lockfile();
+        let config = ProviderConfig::AwsSecretsManager("$env/key1".to_string());
+
+        assert_eq!(
+            lockfile
+                .version_for("KEY1", &config, "prod/key1", true)
+                .unwrap(),
+            Some("v1")
+        );
+        assert!(
+            lockfile
+                .version_for("KEY1", &config, "staging/key1", true)
+                .is_err()
+        );
+        assert!(
+            lockfile
+                .version_for(
+                    "KEY1",
+                    &ProviderConfig::AwsParameterStore("x".to_string()),
+                    "prod/key1",
+                    true
+                )
+                .is_err()
+        );
+        assert!(lockfile.version_for("KEY3", &config, "x", true).is_err());
+        assert_eq!(
+            lockfile.version_for("KEY3", &config, "x", false).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let path = std::env::temp_dir().join("awsm_env_test_lockfile.lock");
+        let lockfile = lockfile();
+
+        lockfile.save(&path).unwrap();
+        assert_eq!(Lockfile::load(&path).unwrap(), lockfile);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
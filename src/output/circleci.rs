@@ -0,0 +1,65 @@
+use std::fs::File;
+
+use crate::{output::Output, shellenv::Shell, validate::NameRules, variable::Variables};
+
+use anyhow::Result;
+
+/// Formats environment variables as export statements appended to CircleCI's `$BASH_ENV`
+/// using [`CircleCiOutput::format`]
+pub struct CircleCiOutput;
+
+impl Output for CircleCiOutput {
+    /// Formats environment variables as bash export statements with literally quoted values
+    fn format(&self, variables: Variables) -> Result<String> {
+        Ok(variables
+            .iter()
+            .map(|var| {
+                let value = var.value.as_deref().or(var.default.as_deref());
+                format!(
+                    "{}\n",
+                    Shell::Bash.assignment(&var.key, value.unwrap_or_default())
+                )
+            })
+            .collect())
+    }
+
+    /// `$BASH_ENV` is an arbitrary script shared with other steps, so nothing is loaded
+    /// from it and output is appended instead
+    fn load_existing(&self, _file: File) -> Result<Variables> {
+        Ok(Variables::new())
+    }
+
+    fn is_line_based(&self) -> bool {
+        true
+    }
+
+    fn name_rules(&self) -> Option<NameRules> {
+        Some(NameRules::Posix)
+    }
+
+    fn appends(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variable::Variable;
+
+    use super::*;
+
+    #[test]
+    fn test_circleci_output() {
+        let input: Variables = vec![Variable {
+            key: "TOKEN".to_string(),
+            value: Some("it's $secret".to_string()),
+            ..Default::default()
+        }]
+        .into();
+
+        assert_eq!(
+            CircleCiOutput.format(input).unwrap(),
+            "export TOKEN='it'\\''s $secret'\n"
+        );
+    }
+}
@@ -53,6 +53,10 @@ impl Output for ClaudeOutput {
         let env: IndexMap<_, _> = serde_json::from_value(env)?;
         Ok(env.into())
     }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some(".claude/settings.json")
+    }
 }
 
 #[cfg(test)]
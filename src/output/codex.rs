@@ -63,6 +63,10 @@ impl Output for CodexOutput {
 
         Ok(set.into())
     }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some(".codex/config.toml")
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,96 @@
+use std::{fs::File, io};
+
+use crate::{output::Output, parser::EnvParser, validate::NameRules, variable::Variables};
+
+use anyhow::Result;
+
+/// Formats environment variables for `docker compose --env-file` using [`ComposeEnvOutput::format`]
+pub struct ComposeEnvOutput;
+
+impl Output for ComposeEnvOutput {
+    /// Formats environment variables as a compose env file. Compose interpolates `$` in
+    /// double quoted values, so it's escaped as `$$`.
+    fn format(&self, variables: Variables) -> Result<String> {
+        let mut output = String::new();
+
+        for var in variables {
+            if let Some(provenance) = &var.provenance {
+                output.push_str(&format!("# {}\n", provenance));
+            }
+
+            output.push_str(&format!(
+                "{}={}\n",
+                var.key,
+                serde_json::to_string(&var.value.or(var.default))?.replace('$', "$$")
+            ));
+        }
+
+        Ok(output)
+    }
+
+    /// Loads existing environment variables from a compose env file
+    fn load_existing(&self, file: File) -> Result<Variables> {
+        let input = io::read_to_string(file)?;
+        let mut variables = EnvParser::parse_variables(&input)?;
+        for var in variables.iter_mut() {
+            var.promote_default();
+            if let Some(value) = &mut var.value {
+                *value = value.replace("$$", "$");
+            }
+        }
+        Ok(variables)
+    }
+
+    fn is_line_based(&self) -> bool {
+        true
+    }
+
+    fn name_rules(&self) -> Option<NameRules> {
+        Some(NameRules::Docker)
+    }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some("compose.env")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::variable::Variable;
+
+    use super::*;
+
+    #[test]
+    fn test_compose_env_round_trip() {
+        let input: Variables = vec![
+            Variable {
+                key: "PASSWORD".to_string(),
+                value: Some("pa$$word\"1".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "PORT".to_string(),
+                default: Some("8080".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let result = ComposeEnvOutput.format(input).unwrap();
+        assert_eq!(result, "PASSWORD=\"pa$$$$word\\\"1\"\nPORT=\"8080\"\n");
+
+        let path = std::env::temp_dir().join("awsm_env_test_compose.env");
+        fs::write(&path, &result).unwrap();
+        let loaded = ComposeEnvOutput
+            .load_existing(File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!(
+            loaded.find_by_key("PASSWORD").unwrap().value.as_deref(),
+            Some("pa$$word\"1")
+        );
+
+        let _ = fs::remove_file(&path);
+    }
+}
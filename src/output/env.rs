@@ -1,6 +1,6 @@
 use std::{fs::File, io};
 
-use crate::{output::Output, parser::EnvParser, variable::Variables};
+use crate::{output::Output, parser::EnvParser, validate::NameRules, variable::Variables};
 
 use anyhow::Result;
 
@@ -13,6 +13,15 @@ impl Output for EnvOutput {
         let mut output = String::new();
 
         for var in variables {
+            // Written as an annotation so the description survives loading the file back
+            if let Some(description) = &var.description {
+                output.push_str(&format!("# @desc {}\n", description));
+            }
+
+            if let Some(provenance) = &var.provenance {
+                output.push_str(&format!("# {}\n", provenance));
+            }
+
             output.push_str(&format!(
                 "{}={}\n",
                 var.key,
@@ -30,13 +39,28 @@ impl Output for EnvOutput {
         variables.iter_mut().for_each(|v| v.promote_default());
         Ok(variables)
     }
+
+    fn is_line_based(&self) -> bool {
+        true
+    }
+
+    fn name_rules(&self) -> Option<NameRules> {
+        Some(NameRules::Docker)
+    }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some(".env")
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::fs;
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
 
-    use crate::variable::Variable;
+    use crate::variable::{Provenance, Variable};
 
     use super::*;
 
@@ -92,6 +116,74 @@ mod tests {
         assert_eq!(result, "ONLY_DEFAULT=\"def\"\nBOTH=\"val\"\n");
     }
 
+    #[test]
+    fn test_env_output_description() {
+        let input: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            value: Some("value1".to_string()),
+            description: Some("Connection string for the primary Postgres".to_string()),
+            provenance: Some(Provenance {
+                provider: "aws-sm".to_string(),
+                id: "foobar/123".to_string(),
+                version: None,
+                fetched_at: UNIX_EPOCH,
+                expires_at: None,
+                changed_at: None,
+            }),
+            ..Default::default()
+        }]
+        .into();
+
+        let result = EnvOutput.format(input).unwrap();
+        assert_eq!(
+            result,
+            "# @desc Connection string for the primary Postgres\n# aws-sm foobar/123 fetched=1970-01-01T00:00:00Z\nKEY1=\"value1\"\n"
+        );
+
+        let path = write_temp("env_description.env", &result);
+        let loaded = EnvOutput.load_existing(File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            loaded.find_by_key("KEY1").unwrap().description.as_deref(),
+            Some("Connection string for the primary Postgres")
+        );
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_env_output_provenance() {
+        let input: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            value: Some("value1".to_string()),
+            provenance: Some(Provenance {
+                provider: "aws-sm".to_string(),
+                id: "foobar/123".to_string(),
+                version: Some("v1".to_string()),
+                fetched_at: UNIX_EPOCH,
+                expires_at: Some(UNIX_EPOCH + Duration::from_secs(300)),
+                changed_at: Some(UNIX_EPOCH + Duration::from_secs(60)),
+            }),
+            ..Default::default()
+        }]
+        .into();
+
+        let result = EnvOutput.format(input).unwrap();
+        assert_eq!(
+            result,
+            "# aws-sm foobar/123 version=v1 changed=1970-01-01T00:01:00Z fetched=1970-01-01T00:00:00Z expires=1970-01-01T00:05:00Z\nKEY1=\"value1\"\n"
+        );
+
+        // Provenance comments are ignored when loaded back
+        let path = write_temp("env_provenance.env", &result);
+        let loaded = EnvOutput.load_existing(File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            loaded.find_by_key("KEY1").unwrap().value.as_deref(),
+            Some("value1")
+        );
+
+        let _ = fs::remove_file(&path);
+    }
+
     #[test]
     fn test_env_load_existing() {
         let path = write_temp("env_load.env", "KEY1=\"value1\"\nKEY2=\"val\\\"ue2\"\n");
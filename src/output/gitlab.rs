@@ -0,0 +1,115 @@
+use std::{fs::File, io};
+
+use crate::{
+    output::Output,
+    validate::NameRules,
+    variable::{Variable, Variables},
+};
+
+use anyhow::{Result, anyhow};
+
+/// Formats environment variables as a GitLab CI `artifacts:reports:dotenv` file using
+/// [`GitlabDotenvOutput::format`]
+pub struct GitlabDotenvOutput;
+
+impl Output for GitlabDotenvOutput {
+    /// Formats environment variables as unquoted `KEY=value` lines. GitLab takes values
+    /// literally and doesn't support multiline values.
+    fn format(&self, variables: Variables) -> Result<String> {
+        let mut output = String::new();
+
+        for var in variables {
+            let value = var.value.or(var.default).unwrap_or_default();
+            if value.contains(['\n', '\r']) {
+                return Err(anyhow!(
+                    "GitLab dotenv reports don't support multiline values, found one for {}",
+                    var.key
+                ));
+            }
+
+            output.push_str(&format!("{}={}\n", var.key, value));
+        }
+
+        Ok(output)
+    }
+
+    /// Loads existing environment variables from a dotenv report
+    fn load_existing(&self, file: File) -> Result<Variables> {
+        let input = io::read_to_string(file)?;
+
+        let variables = input
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| Variable {
+                key: key.trim().to_string(),
+                value: Some(value.to_string()),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(variables.into())
+    }
+
+    fn is_line_based(&self) -> bool {
+        true
+    }
+
+    fn name_rules(&self) -> Option<NameRules> {
+        Some(NameRules::Posix)
+    }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some("build.env")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_gitlab_dotenv_round_trip() {
+        let input: Variables = vec![
+            Variable {
+                key: "DATABASE_URL".to_string(),
+                value: Some("postgres://u:p@db/app?ssl=true#frag".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "GREETING".to_string(),
+                default: Some("\"hi\"".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let result = GitlabDotenvOutput.format(input).unwrap();
+        assert_eq!(
+            result,
+            "DATABASE_URL=postgres://u:p@db/app?ssl=true#frag\nGREETING=\"hi\"\n"
+        );
+
+        let path = std::env::temp_dir().join("awsm_env_test_gitlab.env");
+        fs::write(&path, &result).unwrap();
+        let loaded = GitlabDotenvOutput
+            .load_existing(File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!(
+            loaded.find_by_key("DATABASE_URL").unwrap().value.as_deref(),
+            Some("postgres://u:p@db/app?ssl=true#frag")
+        );
+
+        let multiline: Variables = vec![Variable {
+            key: "CERT".to_string(),
+            value: Some("a\nb".to_string()),
+            ..Default::default()
+        }]
+        .into();
+        assert!(GitlabDotenvOutput.format(multiline).is_err());
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::variable::Variables;
+
+const GENERATED_BY: &str = "# Generated by awsm-env ";
+const SPEC_SHA256: &str = "# spec-sha256: ";
+const EXPIRES: &str = "# expires: ";
+
+/// Comment lines at the top of an `env` or `shell` file recording when, by which
+/// version and from which spec it was generated, so stale copies can be detected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: String,
+    pub generated_at: SystemTime,
+    /// Hex encoded SHA-256 of the spec
+    pub spec_sha256: String,
+    /// Earliest expiry of the values in the file, from their `@ttl`
+    pub expires_at: Option<SystemTime>,
+}
+
+impl Header {
+    pub fn new(spec: &[u8], variables: &Variables, generated_at: SystemTime) -> Self {
+        Header {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at,
+            spec_sha256: Sha256::digest(spec)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+            expires_at: variables
+                .iter()
+                .filter_map(|var| var.provenance.as_ref()?.expires_at)
+                .min(),
+        }
+    }
+
+    /// Reads the header from the leading comments of a file, `None` if it has none
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut header = None;
+
+        for line in contents.lines().take_while(|line| line.starts_with('#')) {
+            if let Some(rest) = line.strip_prefix(GENERATED_BY) {
+                let (version, generated_at) = rest.split_once(" at ")?;
+                header = Some(Header {
+                    version: version.to_string(),
+                    generated_at: humantime::parse_rfc3339(generated_at).ok()?,
+                    spec_sha256: String::new(),
+                    expires_at: None,
+                });
+            } else if let Some(hash) = line.strip_prefix(SPEC_SHA256) {
+                header.as_mut()?.spec_sha256 = hash.to_string();
+            } else if let Some(expires_at) = line.strip_prefix(EXPIRES) {
+                header.as_mut()?.expires_at = Some(humantime::parse_rfc3339(expires_at).ok()?);
+            }
+        }
+
+        header
+    }
+
+    /// Whether the file is past its expiry or was generated more than `max_age` ago
+    pub fn is_stale(&self, now: SystemTime, max_age: Duration) -> bool {
+        let expired = self.expires_at.is_some_and(|expires_at| expires_at <= now);
+        let age = now.duration_since(self.generated_at).unwrap_or_default();
+
+        expired || age > max_age
+    }
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}{} at {}",
+            GENERATED_BY,
+            self.version,
+            humantime::format_rfc3339_seconds(self.generated_at)
+        )?;
+        writeln!(f, "{}{}", SPEC_SHA256, self.spec_sha256)?;
+
+        if let Some(expires_at) = self.expires_at {
+            writeln!(
+                f,
+                "{}{}",
+                EXPIRES,
+                humantime::format_rfc3339_seconds(expires_at)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use crate::{
+        output::{EnvOutput, Output},
+        variable::{Provenance, Variable},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let generated_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let variables: Variables = vec![Variable {
+            key: "TOKEN".to_string(),
+            value: Some("secret".to_string()),
+            provenance: Some(Provenance {
+                provider: "aws-sm".to_string(),
+                id: "token".to_string(),
+                version: None,
+                fetched_at: generated_at,
+                expires_at: Some(generated_at + Duration::from_secs(3600)),
+                changed_at: None,
+            }),
+            ..Default::default()
+        }]
+        .into();
+
+        let header = Header::new(b"TOKEN=\n", &variables, generated_at);
+        let rendered = header.to_string();
+        assert!(rendered.starts_with("# Generated by awsm-env "));
+        assert!(rendered.contains(" at 2023-11-14T22:13:20Z\n"));
+        assert!(rendered.ends_with("# expires: 2023-11-14T23:13:20Z\n"));
+        assert_eq!(header.spec_sha256.len(), 64);
+
+        let file = format!("{}TOKEN=\"secret\"\n", rendered);
+        assert_eq!(Header::parse(&file), Some(header));
+        assert_eq!(Header::parse("TOKEN=\"secret\"\n"), None);
+
+        let path = std::env::temp_dir().join("awsm_env_test_header");
+        std::fs::write(&path, &file).unwrap();
+        let loaded = EnvOutput
+            .load_existing(std::fs::File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!(
+            loaded.find_by_key("TOKEN").unwrap().value.as_deref(),
+            Some("secret")
+        );
+    }
+
+    #[test]
+    fn test_header_staleness() {
+        let generated_at = UNIX_EPOCH + Duration::from_secs(1_000);
+        let header = Header {
+            version: "1.0.0".to_string(),
+            generated_at,
+            spec_sha256: String::new(),
+            expires_at: Some(generated_at + Duration::from_secs(60)),
+        };
+        let hour = Duration::from_secs(3600);
+
+        assert!(!header.is_stale(generated_at + Duration::from_secs(30), hour));
+        assert!(header.is_stale(generated_at + Duration::from_secs(90), hour));
+
+        let header = Header {
+            expires_at: None,
+            ..header
+        };
+        assert!(!header.is_stale(generated_at + Duration::from_secs(90), hour));
+        assert!(header.is_stale(generated_at + hour * 2, hour));
+    }
+}
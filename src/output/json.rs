@@ -1,35 +1,124 @@
 use std::{fs::File, io};
 
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
 
-use crate::{output::Output, variable::Variables};
+use crate::{
+    output::Output,
+    variable::{Variable, Variables},
+};
 
 use anyhow::Result;
 
+/// Key under which per-key provenance is written
+const META_KEY: &str = "_meta";
+
 /// Formats environment variables into JSON using [`JsonOutput::format`]
 pub struct JsonOutput;
 
 impl Output for JsonOutput {
-    /// Formats environment variables into JSON of the form `{"KEY": "value"}`
+    /// Formats environment variables into JSON of the form `{"KEY": "value"}`.
+    /// Provenance, when present, is written to a `_meta` object keyed by variable name
     fn format(&self, variables: Variables) -> Result<String> {
+        let meta: Map<_, _> = variables
+            .iter()
+            .filter_map(|var| {
+                let provenance = var.provenance.as_ref()?;
+                let mut entry = json!({
+                    "provider": provenance.provider,
+                    "id": provenance.id,
+                    "version": provenance.version,
+                    "fetched_at": provenance.fetched_at_rfc3339(),
+                });
+                if let Some(changed_at) = provenance.changed_at_rfc3339() {
+                    entry["changed_at"] = changed_at.into();
+                }
+                if let Some(expires_at) = provenance.expires_at_rfc3339() {
+                    entry["expires_at"] = expires_at.into();
+                }
+                if let Some(description) = &var.description {
+                    entry["description"] = description.as_str().into();
+                }
+                Some((var.key.clone(), entry))
+            })
+            .collect();
+
         let map: IndexMap<_, _> = variables.into();
-        Ok(serde_json::to_string(&map)? + "\n")
+        let mut obj: Map<_, _> = map.into_iter().map(|(k, v)| (k, v.into())).collect();
+
+        if !meta.is_empty() {
+            obj.insert(META_KEY.to_string(), Value::Object(meta));
+        }
+
+        Ok(serde_json::to_string(&obj)? + "\n")
     }
 
-    /// Loads existing environment variables from a JSON file
+    /// Loads existing environment variables from a JSON file, ignoring any `_meta` object
     fn load_existing(&self, file: File) -> Result<Variables> {
         let input = io::read_to_string(file)?;
-        let obj: IndexMap<_, _> = serde_json::from_str(&input)?;
+        let mut obj: Map<_, _> = serde_json::from_str(&input)?;
+        obj.remove(META_KEY);
+        let obj: IndexMap<_, _> = serde_json::from_value(Value::Object(obj))?;
 
         Ok(obj.into())
     }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some("env.json")
+    }
+}
+
+/// A single entry of a parsed spec, see [`Schema::Spec`](crate::schema::Schema::Spec)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecEntry {
+    pub key: String,
+    pub required: bool,
+    pub default: Option<String>,
+    pub provider: Option<String>,
+    /// Secret ID before placeholder substitution
+    pub id: Option<String>,
+    pub tags: IndexMap<String, String>,
+    /// In seconds
+    pub ttl: Option<u64>,
+    pub description: Option<String>,
+    /// The note of `@deprecated` keys, possibly empty
+    pub deprecated: Option<String>,
+}
+
+impl From<&Variable> for SpecEntry {
+    fn from(var: &Variable) -> Self {
+        Self {
+            key: var.key.clone(),
+            required: var.required,
+            default: var.default.clone(),
+            provider: var.provider_config.as_ref().map(|c| c.name().to_string()),
+            id: var.provider_config.as_ref().map(|c| c.id().to_string()),
+            tags: var.tags.clone(),
+            ttl: var.ttl.map(|ttl| ttl.as_secs()),
+            description: var.description.clone(),
+            deprecated: var.deprecated.clone(),
+        }
+    }
+}
+
+/// Describes the entries of a parsed spec as a JSON array of
+/// `{key, required, default, provider, id, tags, ttl, description, deprecated}` objects, `ttl` in seconds
+pub fn spec_entries(variables: &Variables) -> Value {
+    variables
+        .iter()
+        .map(|var| serde_json::to_value(SpecEntry::from(var)).expect("Expected serializable entry"))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use std::fs;
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
 
-    use crate::variable::Variable;
+    use crate::variable::{Provenance, Variable};
 
     use super::*;
 
@@ -101,6 +190,80 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_json_output_empty() {
+        let result = JsonOutput.format(Variables::new()).unwrap();
+        assert_eq!(result, "{}\n");
+    }
+
+    #[test]
+    fn test_json_output_provenance() {
+        let input: Variables = vec![
+            Variable {
+                key: "KEY1".to_string(),
+                value: Some("value1".to_string()),
+                provenance: Some(Provenance {
+                    provider: "aws-ps".to_string(),
+                    id: "foobar/123".to_string(),
+                    version: Some("3".to_string()),
+                    fetched_at: UNIX_EPOCH,
+                    expires_at: Some(UNIX_EPOCH + Duration::from_secs(60)),
+                    changed_at: Some(UNIX_EPOCH),
+                }),
+                ..Default::default()
+            },
+            Variable {
+                key: "KEY2".to_string(),
+                value: Some("value2".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let result = JsonOutput.format(input).unwrap();
+
+        let expected = serde_json::json!({
+            "KEY1": "value1",
+            "KEY2": "value2",
+            "_meta": {
+                "KEY1": {
+                    "provider": "aws-ps",
+                    "id": "foobar/123",
+                    "version": "3",
+                    "fetched_at": "1970-01-01T00:00:00Z",
+                    "expires_at": "1970-01-01T00:01:00Z",
+                    "changed_at": "1970-01-01T00:00:00Z",
+                },
+            },
+        });
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&result).unwrap(),
+            expected
+        )
+    }
+
+    #[test]
+    fn test_json_load_existing_ignores_meta() {
+        let path = write_temp(
+            "json_load_meta.json",
+            r#"{"KEY1":"value1","_meta":{"KEY1":{"provider":"aws-sm"}}}"#,
+        );
+
+        let result = JsonOutput
+            .load_existing(File::open(&path).unwrap())
+            .unwrap();
+
+        let expected: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            value: Some("value1".to_string()),
+            ..Default::default()
+        }]
+        .into();
+        assert_eq!(result, expected);
+
+        let _ = fs::remove_file(&path);
+    }
+
     #[test]
     fn test_json_load_existing() {
         let path = write_temp(
@@ -1,24 +1,423 @@
+mod circleci;
 mod claude;
 mod codex;
+mod compose;
 mod env;
+mod gitlab;
+mod header;
 mod json;
+mod registry;
 mod shell;
 
+pub use circleci::CircleCiOutput;
 pub use claude::ClaudeOutput;
 pub use codex::CodexOutput;
+pub use compose::ComposeEnvOutput;
 pub use env::EnvOutput;
-pub use json::JsonOutput;
+pub use gitlab::GitlabDotenvOutput;
+pub use header::Header;
+pub use json::{JsonOutput, SpecEntry, spec_entries};
+pub use registry::{FormatterFactory, FormatterRegistry};
 pub use shell::ShellOutput;
 
-use std::fs::File;
+use std::{
+    ffi::OsStr,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
 
-use crate::variable::Variables;
+use crate::{validate::NameRules, variable::Variables};
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
+
+/// How values containing newlines or control characters are handled
+#[derive(clap::ValueEnum, Clone, Copy, Eq, PartialEq, Default, Debug)]
+pub enum ControlChars {
+    /// Fail when a value contains newlines or control characters
+    Error,
+
+    /// Keep such values and rely on the format's escape sequences, e.g. `\n`
+    #[default]
+    Escape,
+
+    /// Remove newlines and control characters from values
+    Strip,
+}
 
 /// By implementing `Output` a type provides a way to format [`Variables`]
 /// and to load existing values back from a file in that format.
 pub trait Output {
     fn format(&self, variables: Variables) -> Result<String>;
     fn load_existing(&self, file: File) -> Result<Variables>;
+
+    /// Whether the format writes a value per line, in which case `--control-chars`
+    /// applies to it
+    fn is_line_based(&self) -> bool {
+        false
+    }
+
+    /// Rules key names must follow for the output to be usable, checked unless
+    /// `--name-rules` picks others
+    fn name_rules(&self) -> Option<NameRules> {
+        None
+    }
+
+    /// Conventional name of the file the format is written to with `--output-dir`,
+    /// relative to the directory. `None` uses `env.<format>`.
+    fn file_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether output is appended to an existing file instead of replacing it, e.g. for
+    /// files other tools write to as well
+    fn appends(&self) -> bool {
+        false
+    }
+}
+
+/// Applies `policy` to values containing newlines or control characters, for formats
+/// whose consumers can't represent them (e.g. docker `--env-file` or systemd `EnvironmentFile`)
+pub fn apply_control_chars(variables: &mut Variables, policy: ControlChars) -> Result<()> {
+    match policy {
+        ControlChars::Escape => Ok(()),
+        ControlChars::Strip => {
+            for var in variables.iter_mut() {
+                for value in [&mut var.value, &mut var.default].into_iter().flatten() {
+                    value.retain(|c| !is_control(c));
+                }
+            }
+            Ok(())
+        }
+        ControlChars::Error => {
+            let keys = variables
+                .iter()
+                .filter(|var| {
+                    [&var.value, &var.default]
+                        .into_iter()
+                        .flatten()
+                        .any(|value| value.chars().any(is_control))
+                })
+                .map(|var| var.key.as_str())
+                .collect::<Vec<_>>();
+
+            if keys.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Values contain newlines or control characters: {}",
+                    keys.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+/// Creates an empty file at `path` readable only by its owner, unless it exists. Writing
+/// it with [`write_atomic`] keeps these permissions.
+pub fn create_private(path: &Path) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    Ok(())
+}
+
+/// Appends `contents` to the file at `path`, creating it if needed
+pub fn append(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    io::Write::write_all(&mut file, contents)
+        .with_context(|| format!("Failed to append to {}", path.display()))
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into place, so
+/// readers and interrupted runs never see a partially written file. An existing file
+/// keeps its permissions, a new one is readable only by its owner. Missing parent
+/// directories are created.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        fs::create_dir_all(parent).context("Failed to create parent directory")?;
+    }
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Not a file path: {}", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    write_and_rename(&temp, path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// File in an output directory listing the files written to it by the last run
+pub const OUTPUTS_MANIFEST: &str = ".awsm-env-outputs";
+
+/// Records `written` in the manifest of `dir` and, if `clean` is set, deletes the files
+/// the previous run wrote that weren't written this time. Files that aren't in the
+/// manifest are never touched, nor are manifest entries that aren't plain file names, so
+/// an edited manifest can't delete files outside `dir`. Returns the paths that were
+/// deleted.
+pub fn update_outputs_manifest(
+    dir: &Path,
+    written: &[String],
+    clean: bool,
+) -> Result<Vec<PathBuf>> {
+    let manifest = dir.join(OUTPUTS_MANIFEST);
+    let mut removed = Vec::new();
+
+    if clean && let Ok(previous) = fs::read_to_string(&manifest) {
+        let stale = previous
+            .lines()
+            .filter(|name| Path::new(name).file_name() == Some(OsStr::new(name)))
+            .filter(|name| !written.iter().any(|written| written == name));
+
+        for name in stale {
+            let path = dir.join(name);
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Failed to remove {}", path.display()));
+                }
+            }
+        }
+    }
+
+    let contents = written
+        .iter()
+        .map(|name| format!("{}\n", name))
+        .collect::<String>();
+    write_atomic(&manifest, contents.as_bytes())?;
+
+    Ok(removed)
+}
+
+fn write_and_rename(temp: &Path, path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    // Fails instead of following anything already at `temp`, e.g. a planted symlink
+    let file = options.open(temp)?;
+
+    let result = fill_and_rename(file, temp, path, contents);
+    if result.is_err() {
+        let _ = fs::remove_file(temp);
+    }
+
+    result
+}
+
+fn fill_and_rename(mut file: File, temp: &Path, path: &Path, contents: &[u8]) -> Result<()> {
+    io::Write::write_all(&mut file, contents)?;
+
+    if let Ok(existing) = fs::metadata(path) {
+        file.set_permissions(existing.permissions())?;
+    }
+
+    file.sync_all()?;
+    // Windows can't rename a file that's still open
+    drop(file);
+    fs::rename(temp, path)?;
+
+    sync_dir(path.parent().filter(|p| !p.as_os_str().is_empty()))
+}
+
+/// Persists a rename into `dir`, the current directory if `None`
+#[cfg(unix)]
+fn sync_dir(dir: Option<&Path>) -> Result<()> {
+    File::open(dir.unwrap_or(Path::new(".")))?.sync_all()?;
+    Ok(())
+}
+
+/// Directories can't be opened for syncing on Windows, renames are journaled there
+#[cfg(not(unix))]
+fn sync_dir(_dir: Option<&Path>) -> Result<()> {
+    Ok(())
+}
+
+/// Tabs are left alone since every env file consumer handles them
+fn is_control(c: char) -> bool {
+    c.is_control() && c != '\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variable::Variable;
+
+    use super::*;
+
+    fn variables() -> Variables {
+        vec![
+            Variable {
+                key: "PLAIN".to_string(),
+                value: Some("a\tb".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "MULTILINE".to_string(),
+                value: Some("line1\nline2\r\n".to_string()),
+                default: Some("x\u{7}y".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_control_chars_escape() {
+        let mut input = variables();
+        apply_control_chars(&mut input, ControlChars::Escape).unwrap();
+        assert_eq!(input, variables());
+    }
+
+    #[test]
+    fn test_control_chars_strip() {
+        let mut input = variables();
+        apply_control_chars(&mut input, ControlChars::Strip).unwrap();
+
+        assert_eq!(
+            input.find_by_key("PLAIN").unwrap().value.as_deref(),
+            Some("a\tb")
+        );
+        let multiline = input.find_by_key("MULTILINE").unwrap();
+        assert_eq!(multiline.value.as_deref(), Some("line1line2"));
+        assert_eq!(multiline.default.as_deref(), Some("xy"));
+    }
+
+    #[test]
+    fn test_control_chars_error() {
+        let err = apply_control_chars(&mut variables(), ControlChars::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Values contain newlines or control characters: MULTILINE"
+        );
+    }
+
+    fn unordered() -> Variables {
+        ["ZETA", "ALPHA", "MIKE"]
+            .into_iter()
+            .map(|key| Variable {
+                key: key.to_string(),
+                value: Some(key.to_lowercase()),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn key_order(output: &str) -> Vec<usize> {
+        ["ZETA", "ALPHA", "MIKE"]
+            .iter()
+            .map(|key| output.find(key).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_formatters_follow_spec_order() {
+        let outputs: [&dyn Output; 3] = [&EnvOutput, &ShellOutput, &JsonOutput];
+
+        for output in outputs {
+            let positions = key_order(&output.format(unordered()).unwrap());
+            assert!(positions.is_sorted(), "{:?}", positions);
+        }
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join("awsm_env_test_write_atomic");
+        let path = dir.join("nested").join(".env");
+        let _ = fs::remove_dir_all(&dir);
+
+        write_atomic(&path, b"A=1\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "A=1\n");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = || fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(), 0o600);
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+            write_atomic(&path, b"A=2\n").unwrap();
+            assert_eq!(mode(), 0o640);
+
+            // A symlink planted at the temporary path is neither followed nor removed
+            let target = dir.join("target");
+            let temp = path.with_file_name(format!("..env.{}.tmp", std::process::id()));
+            std::os::unix::fs::symlink(&target, &temp).unwrap();
+            assert!(write_atomic(&path, b"A=3\n").is_err());
+            assert!(!target.exists());
+            fs::remove_file(&temp).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "A=2\n");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_cleans_stale_outputs() {
+        let dir = std::env::temp_dir().join("awsm_env_test_outputs_manifest");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        for name in [".env", "env.json", "notes.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let written = [".env".to_string(), "env.json".to_string()];
+        assert!(
+            update_outputs_manifest(&dir, &written, true)
+                .unwrap()
+                .is_empty()
+        );
+
+        let removed = update_outputs_manifest(&dir, &written[..1], false).unwrap();
+        assert!(removed.is_empty());
+        assert!(dir.join("env.json").exists());
+
+        let outside = std::env::temp_dir().join("awsm_env_test_outputs_outside");
+        fs::write(&outside, "").unwrap();
+        fs::write(
+            dir.join(OUTPUTS_MANIFEST),
+            format!(
+                ".env\nenv.json\n../awsm_env_test_outputs_outside\n{}\n",
+                outside.display()
+            ),
+        )
+        .unwrap();
+        let removed = update_outputs_manifest(&dir, &written[..1], true).unwrap();
+        assert_eq!(removed, [dir.join("env.json")]);
+        assert!(outside.exists());
+        assert!(dir.join(".env").exists());
+        assert!(dir.join("notes.txt").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join(OUTPUTS_MANIFEST)).unwrap(),
+            ".env\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_by_key() {
+        let mut input = unordered();
+        input.sort_by_key();
+
+        let keys = input.iter().map(|v| v.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["ALPHA", "MIKE", "ZETA"]);
+    }
 }
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::output::{
+    CircleCiOutput, ClaudeOutput, CodexOutput, ComposeEnvOutput, EnvOutput, GitlabDotenvOutput,
+    JsonOutput, Output, ShellOutput,
+};
+
+/// Creates a formatter given the path of the output file, `None` when writing to stdout
+pub type FormatterFactory = Box<dyn Fn(Option<&Path>) -> Box<dyn Output> + Send + Sync>;
+
+/// Formatters keyed by the name used with `--format`, in registration order
+pub struct FormatterRegistry {
+    formatters: IndexMap<String, FormatterFactory>,
+}
+
+impl FormatterRegistry {
+    /// Creates a registry with the built-in formatters
+    pub fn new() -> Self {
+        let mut registry = FormatterRegistry {
+            formatters: IndexMap::new(),
+        };
+
+        registry
+            .register("env", |_| Box::new(EnvOutput))
+            .register("shell", |_| Box::new(ShellOutput))
+            .register("json", |_| Box::new(JsonOutput))
+            .register("claude", |path| {
+                Box::new(ClaudeOutput::new(path.map(Path::to_path_buf)))
+            })
+            .register("codex", |path| {
+                Box::new(CodexOutput::new(path.map(Path::to_path_buf)))
+            })
+            .register("compose-env", |_| Box::new(ComposeEnvOutput))
+            .register("gitlab-dotenv", |_| Box::new(GitlabDotenvOutput))
+            .register("circleci", |_| Box::new(CircleCiOutput));
+
+        registry
+    }
+
+    /// Adds a formatter, replacing any registered under the same name
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn(Option<&Path>) -> Box<dyn Output> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.formatters.insert(name.into(), Box::new(factory));
+        self
+    }
+
+    /// Creates the formatter registered under `name`
+    pub fn create(&self, name: &str, path: Option<&Path>) -> Option<Box<dyn Output>> {
+        self.formatters.get(name).map(|factory| factory(path))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.formatters.contains_key(name)
+    }
+
+    /// Names of the registered formatters, in registration order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formatters.keys().map(String::as_str)
+    }
+}
+
+impl Default for FormatterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use anyhow::Result;
+
+    use crate::variable::{Variable, Variables};
+
+    use super::*;
+
+    struct UpperOutput;
+
+    impl Output for UpperOutput {
+        fn format(&self, variables: Variables) -> Result<String> {
+            Ok(variables
+                .iter()
+                .map(|var| format!("{}\n", var.key.to_uppercase()))
+                .collect())
+        }
+
+        fn load_existing(&self, _file: File) -> Result<Variables> {
+            Ok(Variables::new())
+        }
+    }
+
+    #[test]
+    fn test_lists_builtin_formatters() {
+        let registry = FormatterRegistry::new();
+
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            [
+                "env",
+                "shell",
+                "json",
+                "claude",
+                "codex",
+                "compose-env",
+                "gitlab-dotenv",
+                "circleci"
+            ]
+        );
+        assert!(registry.create("env", None).unwrap().is_line_based());
+        assert!(!registry.create("json", None).unwrap().is_line_based());
+        assert!(registry.create("yaml", None).is_none());
+    }
+
+    #[test]
+    fn test_registers_custom_formatters() {
+        let mut registry = FormatterRegistry::new();
+        registry
+            .register("upper", |_| Box::new(UpperOutput))
+            .register("env", |_| Box::new(UpperOutput));
+
+        let variables: Variables = vec![Variable {
+            key: "key1".to_string(),
+            ..Default::default()
+        }]
+        .into();
+
+        assert!(registry.contains("upper"));
+        assert_eq!(registry.names().count(), 9);
+        assert_eq!(
+            registry
+                .create("env", None)
+                .unwrap()
+                .format(variables)
+                .unwrap(),
+            "KEY1\n"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fs::File, io};
 
-use crate::{output::Output, parser::EnvParser, variable::Variables};
+use crate::{output::Output, parser::EnvParser, validate::NameRules, variable::Variables};
 
 use anyhow::Result;
 
@@ -13,6 +13,15 @@ impl Output for ShellOutput {
         let mut output = String::new();
 
         for var in variables {
+            // Written as an annotation so the description survives loading the file back
+            if let Some(description) = &var.description {
+                output.push_str(&format!("# @desc {}\n", description));
+            }
+
+            if let Some(provenance) = &var.provenance {
+                output.push_str(&format!("# {}\n", provenance));
+            }
+
             output.push_str(&format!(
                 "export {}={}\n",
                 var.key,
@@ -30,6 +39,18 @@ impl Output for ShellOutput {
         variables.iter_mut().for_each(|v| v.promote_default());
         Ok(variables)
     }
+
+    fn is_line_based(&self) -> bool {
+        true
+    }
+
+    fn name_rules(&self) -> Option<NameRules> {
+        Some(NameRules::Posix)
+    }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some("env.sh")
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,54 @@
-use crate::variable::{ProviderConfig, Variable, Variables};
-use anyhow::Result;
+use std::{borrow::Cow, time::Duration};
+
+use crate::{
+    term,
+    validate::Validation,
+    variable::{ProviderConfig, SecretVersion, Variable, Variables},
+};
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
 use pest::Parser;
 use pest_derive::Parser;
 
+/// How keys declared more than once in a spec are handled
+#[derive(clap::ValueEnum, Clone, Copy, Eq, PartialEq, Default, Debug)]
+pub enum Duplicates {
+    /// Fail when a key is declared more than once
+    Error,
+
+    /// Keep the first declaration of a key
+    FirstWins,
+
+    /// Keep the last declaration of a key
+    #[default]
+    LastWins,
+}
+
+/// Directives that macros can't redefine
+const BUILTIN_DIRECTIVES: [&str; 21] = [
+    "aws-sm",
+    "aws-ps",
+    "aws-ps-path",
+    "plugin",
+    "optional",
+    "tags",
+    "ttl",
+    "max-age",
+    "generate",
+    "version-id",
+    "version-stage",
+    "mirror",
+    "desc",
+    "deprecated",
+    "validate",
+    "min-length",
+    "unique",
+    "exclude-from",
+    "no-export",
+    "seal",
+    "define",
+];
+
 #[derive(Parser)]
 #[grammar = "env.pest"]
 pub struct EnvParser;
@@ -44,22 +90,39 @@ impl EnvParser {
     /// )
     /// ```
     pub fn parse_variables(input: &str) -> Result<Variables> {
-        let file = EnvParser::parse(Rule::file, input)?
+        Self::parse_variables_with(input, Duplicates::LastWins)
+    }
+
+    /// Like [`EnvParser::parse_variables`] but handles keys declared more than once
+    /// according to `duplicates`
+    pub fn parse_variables_with(input: &str, duplicates: Duplicates) -> Result<Variables> {
+        let input = Self::expand_macros(input)?;
+        let file = EnvParser::parse(Rule::file, &input)?
             .next()
             .expect("should have one file");
 
         let mut variables = Variables::new();
+        let mut lines = IndexMap::new();
 
         for line in file.into_inner() {
             match line.as_rule() {
                 Rule::declaration => {
-                    let mut pairs = line.into_inner();
-                    let (directive, pair) = match (pairs.next(), pairs.next()) {
-                        (Some(directive), Some(pair)) => (Some(directive), pair),
-                        (Some(pair), None) => (None, pair),
-                        _ => unreachable!(),
-                    };
+                    let mut annotations = Vec::new();
+                    let mut directive = None;
+                    let mut pair = None;
 
+                    for inner in line.into_inner() {
+                        match inner.as_rule() {
+                            Rule::annotation => annotations.push(inner),
+                            Rule::directive => directive = Some(inner),
+                            Rule::pair => pair = Some(inner),
+                            _ => unreachable!(),
+                        }
+                    }
+
+                    let pair = pair.expect("should have pair");
+
+                    let (line_no, _) = pair.line_col();
                     let mut pairs = pair.into_inner();
 
                     let pair_ident = pairs.next().expect("should have pair_ident").as_str();
@@ -95,21 +158,28 @@ impl EnvParser {
                         Some(pair_value)
                     };
 
-                    let (required, config) = match directive {
+                    let mut json_key = None;
+                    let mut ps_path = None;
+                    let mut version = None;
+
+                    let (required, config, tags, ttl, max_age, generate, mirror) = match directive {
                         Some(directive) => {
                             let mut pairs = directive.into_inner();
                             let inner_directive =
                                 pairs.next().expect("should have inner directive");
 
                             let config = match inner_directive.as_rule() {
-                                Rule::aws_sm_directive => ProviderConfig::AwsSecretsManager(
-                                    inner_directive
+                                Rule::aws_sm_directive => {
+                                    let mut secret = inner_directive
                                         .into_inner()
                                         .next()
                                         .expect("should have value")
-                                        .as_str()
-                                        .to_owned(),
-                                ),
+                                        .into_inner();
+                                    let id = secret.next().expect("should have secret id");
+                                    json_key = secret.next().map(|key| key.as_str().to_owned());
+
+                                    ProviderConfig::AwsSecretsManager(id.as_str().to_owned())
+                                }
                                 Rule::aws_ps_directive => ProviderConfig::AwsParameterStore(
                                     inner_directive
                                         .into_inner()
@@ -118,31 +188,295 @@ impl EnvParser {
                                         .as_str()
                                         .to_owned(),
                                 ),
+                                Rule::aws_ps_path_directive => {
+                                    let path = inner_directive
+                                        .into_inner()
+                                        .next()
+                                        .expect("should have path")
+                                        .as_str()
+                                        .to_owned();
+                                    ps_path = Some(path.clone());
+
+                                    ProviderConfig::AwsParameterStore(path)
+                                }
+                                Rule::plugin_directive => {
+                                    let mut inner = inner_directive.into_inner();
+                                    let name = inner.next().expect("should have plugin name");
+                                    let id = inner.next().expect("should have value");
+
+                                    ProviderConfig::Plugin {
+                                        name: name.as_str().to_owned(),
+                                        id: id.as_str().to_owned(),
+                                    }
+                                }
                                 _ => unreachable!(),
                             };
 
-                            let optional_indicator = pairs.next();
+                            let mut required = true;
+                            let mut tags = IndexMap::new();
+                            let mut ttl = None;
+                            let mut max_age = None;
+                            let mut generate = None;
+                            let mut mirror = None;
+
+                            for modifier in pairs {
+                                match modifier.as_rule() {
+                                    Rule::optional_directive_indicator => required = false,
+                                    Rule::tags_directive => {
+                                        for tag in modifier.into_inner() {
+                                            let mut tag = tag.into_inner();
+                                            let key = tag.next().expect("should have tag key");
+                                            let value = tag.next().expect("should have tag value");
+                                            tags.insert(
+                                                key.as_str().to_owned(),
+                                                value.as_str().to_owned(),
+                                            );
+                                        }
+                                    }
+                                    Rule::ttl_directive => {
+                                        let value = modifier
+                                            .into_inner()
+                                            .next()
+                                            .expect("should have ttl value")
+                                            .as_str();
+                                        ttl = Some(parse_duration(value).with_context(|| {
+                                            format!("Invalid @ttl for key '{}'", pair_ident)
+                                        })?);
+                                    }
+                                    Rule::max_age_directive => {
+                                        let value = modifier
+                                            .into_inner()
+                                            .next()
+                                            .expect("should have max age value")
+                                            .as_str();
+                                        max_age =
+                                            Some(parse_duration(value).with_context(|| {
+                                                format!("Invalid @max-age for key '{}'", pair_ident)
+                                            })?);
+                                    }
+                                    Rule::generate_directive => {
+                                        let spec = modifier
+                                            .into_inner()
+                                            .next()
+                                            .expect("should have generator")
+                                            .as_str();
+                                        generate = Some(spec.parse().with_context(|| {
+                                            format!("Invalid @generate for key '{}'", pair_ident)
+                                        })?);
+                                    }
+                                    Rule::version_id_directive | Rule::version_stage_directive => {
+                                        let rule = modifier.as_rule();
+                                        let value = modifier
+                                            .into_inner()
+                                            .next()
+                                            .expect("should have version")
+                                            .as_str()
+                                            .to_owned();
+
+                                        if version.is_some() {
+                                            return Err(anyhow!(
+                                                "Invalid version for key '{}', only one of @version-id and @version-stage can be given",
+                                                pair_ident
+                                            ));
+                                        }
+                                        version = Some(match rule {
+                                            Rule::version_id_directive => SecretVersion::Id(value),
+                                            _ => SecretVersion::Stage(value),
+                                        });
+                                    }
+                                    Rule::mirror_directive => {
+                                        let id = modifier
+                                            .into_inner()
+                                            .next()
+                                            .expect("should have mirror id")
+                                            .as_str()
+                                            .to_owned();
+                                        mirror = Some(id);
+                                    }
+                                    _ => unreachable!(),
+                                }
+                            }
+
+                            // Mirrors are kept in the other AWS service
+                            let mirror = match (mirror, &config) {
+                                (None, _) => None,
+                                (Some(id), ProviderConfig::AwsSecretsManager(_)) => {
+                                    Some(ProviderConfig::AwsParameterStore(id))
+                                }
+                                (Some(id), ProviderConfig::AwsParameterStore(_)) => {
+                                    Some(ProviderConfig::AwsSecretsManager(id))
+                                }
+                                (Some(_), ProviderConfig::Plugin { .. }) => {
+                                    return Err(anyhow!(
+                                        "Invalid @mirror for key '{}', only @aws-sm and @aws-ps secrets can be mirrored",
+                                        pair_ident
+                                    ));
+                                }
+                            };
+
+                            // A generated value would replace the whole JSON secret
+                            if generate.is_some() && json_key.is_some() {
+                                return Err(anyhow!(
+                                    "Invalid @generate for key '{}', fields of JSON secrets can't be generated",
+                                    pair_ident
+                                ));
+                            }
+
+                            // Versions are those of a single Secrets Manager secret
+                            if version.is_some()
+                                && (!matches!(config, ProviderConfig::AwsSecretsManager(_))
+                                    || generate.is_some()
+                                    || mirror.is_some())
+                            {
+                                return Err(anyhow!(
+                                    "Invalid version for key '{}', only @aws-sm secrets without @generate or @mirror can select one",
+                                    pair_ident
+                                ));
+                            }
 
-                            (optional_indicator.is_none(), Some(config))
+                            // The entries of a path are the parameters found under it
+                            if ps_path.is_some() && (generate.is_some() || mirror.is_some()) {
+                                return Err(anyhow!(
+                                    "Invalid @aws-ps-path for key '{}', only single secrets can be generated or mirrored",
+                                    pair_ident
+                                ));
+                            }
+                            let config = ps_path.is_none().then_some(config);
+
+                            (required, config, tags, ttl, max_age, generate, mirror)
                         }
-                        None => (true, None),
+                        None => (true, None, IndexMap::new(), None, None, None, None),
                     };
 
+                    let mut description: Option<String> = None;
+                    let mut deprecated = None;
+                    let mut validations = Vec::new();
+                    let mut unique = None;
+                    let mut exclude_from = Vec::new();
+                    let mut no_export = false;
+                    let mut seal = false;
+
+                    for annotation in annotations {
+                        let inner = annotation
+                            .into_inner()
+                            .next()
+                            .expect("should have inner annotation");
+
+                        match inner.as_rule() {
+                            // Consecutive `@desc` lines continue the same description
+                            Rule::desc_directive => {
+                                let text = inner
+                                    .into_inner()
+                                    .next()
+                                    .expect("should have desc text")
+                                    .as_str()
+                                    .trim();
+
+                                description = Some(match description {
+                                    Some(description) => format!("{} {}", description, text),
+                                    None => text.to_owned(),
+                                });
+                            }
+                            Rule::deprecated_directive => {
+                                deprecated = Some(
+                                    inner
+                                        .into_inner()
+                                        .next()
+                                        .map(|note| note.as_str().trim().to_owned())
+                                        .unwrap_or_default(),
+                                );
+                            }
+                            Rule::validate_directive => {
+                                let rule = inner
+                                    .into_inner()
+                                    .next()
+                                    .expect("should have validate rule")
+                                    .as_str()
+                                    .trim();
+                                validations.push(rule.parse().with_context(|| {
+                                    format!("Invalid @validate for key '{}'", pair_ident)
+                                })?);
+                            }
+                            Rule::min_length_directive => {
+                                let min = inner
+                                    .into_inner()
+                                    .next()
+                                    .expect("should have min length")
+                                    .as_str();
+                                validations.push(Validation::MinLength(min.parse().with_context(
+                                    || format!("Invalid @min-length for key '{}'", pair_ident),
+                                )?));
+                            }
+                            Rule::unique_directive => {
+                                unique = Some(
+                                    inner
+                                        .into_inner()
+                                        .next()
+                                        .map(|group| group.as_str().to_owned())
+                                        .unwrap_or_default(),
+                                );
+                            }
+                            Rule::exclude_from_directive => {
+                                exclude_from.extend(
+                                    inner.into_inner().map(|format| format.as_str().to_owned()),
+                                );
+                            }
+                            Rule::no_export_directive => no_export = true,
+                            Rule::seal_directive => seal = true,
+                            _ => unreachable!(),
+                        }
+                    }
+
                     let variable = Variable {
                         key: pair_ident.to_owned(),
                         required,
                         default,
                         provider_config: config,
+                        tags,
+                        ttl,
+                        max_age,
+                        generate,
+                        mirror,
+                        json_key,
+                        version,
+                        ps_path,
+                        description,
+                        deprecated,
+                        validations,
+                        unique,
+                        exclude_from,
+                        no_export,
+                        seal,
                         ..Default::default()
                     };
 
-                    if variables.find_by_key(pair_ident).is_some() {
-                        eprintln!(
-                            "Warning: Duplicate variable declaration for key '{}'.",
-                            pair_ident
-                        );
+                    if let Some(first_line) = lines.get(pair_ident) {
+                        match duplicates {
+                            Duplicates::Error => {
+                                return Err(anyhow!(
+                                    "Duplicate declaration for key '{}' on line {}, first declared on line {}",
+                                    pair_ident,
+                                    line_no,
+                                    first_line
+                                ));
+                            }
+                            Duplicates::FirstWins => {
+                                term::warn(format!(
+                                    "Duplicate variable declaration for key '{}' on line {} ignored.",
+                                    pair_ident, line_no
+                                ));
+                                continue;
+                            }
+                            Duplicates::LastWins => {
+                                term::warn(format!(
+                                    "Duplicate variable declaration for key '{}'.",
+                                    pair_ident
+                                ));
+                            }
+                        }
                     }
 
+                    lines.entry(pair_ident.to_owned()).or_insert(line_no);
                     variables.insert(variable);
                 }
                 Rule::EOI => (),
@@ -152,10 +486,159 @@ impl EnvParser {
 
         Ok(variables)
     }
+
+    /// Expands macros defined with `# @define @name = <directive>` lines. A line starting
+    /// with `# @name arg` is replaced by the macro's directive, with `%s` replaced by the
+    /// first argument and any remaining arguments appended. Definition lines become
+    /// empty so line numbers in later errors still match the input.
+    pub fn expand_macros(input: &str) -> Result<Cow<'_, str>> {
+        Self::expand_macros_with(input, &[])
+    }
+
+    /// Like [`EnvParser::expand_macros`] with macros defined beforehand, e.g. by a config
+    /// file. `definitions` are of the form `@name = <directive>`.
+    pub fn expand_macros_with<'a>(input: &'a str, definitions: &[String]) -> Result<Cow<'a, str>> {
+        if !input.contains("@define") && definitions.is_empty() {
+            return Ok(Cow::Borrowed(input));
+        }
+
+        let mut macros: IndexMap<&str, String> = IndexMap::new();
+        for definition in definitions {
+            let (name, body) = parse_definition(definition, &macros)
+                .with_context(|| format!("Invalid macro definition `{}`", definition))?;
+            macros.insert(name, body);
+        }
+        let mut output = String::with_capacity(input.len());
+
+        for (idx, line) in input.split_inclusive('\n').enumerate() {
+            let content = line.trim_end_matches(['\r', '\n']);
+            let ending = &line[content.len()..];
+
+            let Some(directive) = content
+                .trim_start()
+                .strip_prefix('#')
+                .and_then(|rest| rest.trim_start().strip_prefix('@'))
+            else {
+                output.push_str(line);
+                continue;
+            };
+
+            let (name, args) = directive
+                .split_once(char::is_whitespace)
+                .map(|(name, args)| (name, args.trim()))
+                .unwrap_or((directive, ""));
+
+            if name == "define" {
+                let (name, body) = parse_definition(args, &macros)
+                    .with_context(|| format!("Invalid macro definition on line {}", idx + 1))?;
+                macros.insert(name, body);
+                output.push_str(ending);
+                continue;
+            }
+
+            match macros.get(name) {
+                Some(body) => {
+                    let expanded = expand(name, body, args)
+                        .with_context(|| format!("Invalid macro use on line {}", idx + 1))?;
+                    let indent = &content[..content.len() - content.trim_start().len()];
+                    output.push_str(&format!("{}# {}{}", indent, expanded, ending));
+                }
+                None => output.push_str(line),
+            }
+        }
+
+        Ok(Cow::Owned(output))
+    }
+}
+
+/// Parses `@name = <directive>`, expanding macros defined earlier in the directive
+fn parse_definition<'a>(
+    definition: &'a str,
+    macros: &IndexMap<&str, String>,
+) -> Result<(&'a str, String)> {
+    let (name, body) = definition
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected `@define @name = @directive ...`"))?;
+
+    let name = name
+        .trim()
+        .strip_prefix('@')
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .ok_or_else(|| anyhow!("Macro names must be of the form `@name`"))?;
+
+    if BUILTIN_DIRECTIVES.contains(&name) {
+        return Err(anyhow!(
+            "Macro @{} would replace a built-in directive",
+            name
+        ));
+    }
+
+    let body = body.trim();
+    let Some(directive) = body.strip_prefix('@') else {
+        return Err(anyhow!("Macro @{} must expand to a directive", name));
+    };
+
+    let (inner, args) = directive
+        .split_once(char::is_whitespace)
+        .map(|(inner, args)| (inner, args.trim()))
+        .unwrap_or((directive, ""));
+
+    let body = match macros.get(inner) {
+        Some(inner_body) => expand(inner, inner_body, args)?,
+        None => body.to_owned(),
+    };
+
+    Ok((name, body))
+}
+
+fn expand(name: &str, body: &str, args: &str) -> Result<String> {
+    if !body.contains("%s") {
+        return Ok(match args.is_empty() {
+            true => body.to_owned(),
+            false => format!("{} {}", body, args),
+        });
+    }
+
+    let (arg, rest) = args
+        .split_once(char::is_whitespace)
+        .map(|(arg, rest)| (arg, rest.trim()))
+        .unwrap_or((args, ""));
+
+    if arg.is_empty() {
+        return Err(anyhow!("Macro @{} expects an argument", name));
+    }
+
+    let expanded = body.replace("%s", arg);
+    Ok(match rest.is_empty() {
+        true => expanded,
+        false => format!("{} {}", expanded, rest),
+    })
+}
+
+/// Parses a `@ttl` or `@max-age` given either in seconds, e.g. `300`, or with a unit,
+/// e.g. `5m` or `1d`
+fn parse_duration(value: &str) -> Result<Duration> {
+    let duration = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => humantime::parse_duration(value)?,
+    };
+
+    if duration.is_zero() {
+        return Err(anyhow!("must be greater than zero"));
+    }
+
+    Ok(duration)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::generate::Generator;
+
     use super::*;
 
     #[test]
@@ -285,6 +768,30 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parses_plugin_directive() {
+        let input = r#"
+            # @plugin vault secret/data/app:password @optional
+            KEY1=value1
+        "#;
+        let result = EnvParser::parse_variables(input);
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Variable {
+                key: "KEY1".to_owned(),
+                required: false,
+                default: Some("value1".to_owned()),
+                provider_config: Some(ProviderConfig::Plugin {
+                    name: "vault".to_owned(),
+                    id: "secret/data/app:password".to_owned(),
+                }),
+                ..Default::default()
+            }]
+            .into()
+        )
+    }
+
     #[test]
     fn test_parses_optional_directive() {
         let input = r#"
@@ -306,6 +813,206 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parses_tags_directive() {
+        let input = r#"
+            # @aws-sm foobar/123 @tags team=payments, env=$env
+            KEY1=value1
+            # @aws-ps foobar/456 @tags team=payments @optional
+            KEY2=value2
+        "#;
+        let result = EnvParser::parse_variables(input);
+
+        let tags: IndexMap<_, _> = [("team", "payments"), ("env", "$env")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Variable {
+                    key: "KEY1".to_owned(),
+                    required: true,
+                    default: Some("value1".to_owned()),
+                    provider_config: Some(ProviderConfig::AwsSecretsManager(
+                        "foobar/123".to_owned()
+                    )),
+                    tags,
+                    ..Default::default()
+                },
+                Variable {
+                    key: "KEY2".to_owned(),
+                    required: false,
+                    default: Some("value2".to_owned()),
+                    provider_config: Some(ProviderConfig::AwsParameterStore(
+                        "foobar/456".to_owned()
+                    )),
+                    tags: [("team".to_owned(), "payments".to_owned())].into(),
+                    ..Default::default()
+                }
+            ]
+            .into()
+        )
+    }
+
+    #[test]
+    fn test_parses_ttl_directive() {
+        let input = r#"
+            # @aws-sm db/creds @ttl 300 @optional
+            DB=
+            # @aws-ps /app/key @ttl 1d
+            KEY=
+        "#;
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        let db = result.find_by_key("DB").unwrap();
+        assert_eq!(db.ttl, Some(Duration::from_secs(300)));
+        assert!(!db.required);
+        assert_eq!(
+            result.find_by_key("KEY").unwrap().ttl,
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn test_parses_json_keys() {
+        let input =
+            "# @aws-sm prod/$env/db#password @optional\nDB_PASSWORD=\n# @aws-sm prod/db\nDB=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].provider_config,
+            Some(ProviderConfig::AwsSecretsManager("prod/$env/db".to_owned()))
+        );
+        assert_eq!(result[0].json_key.as_deref(), Some("password"));
+        assert!(!result[0].required);
+        assert_eq!(result[1].json_key, None);
+
+        let err =
+            EnvParser::parse_variables("# @aws-sm key#field @generate hex:32\nKEY=\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Invalid @generate for key 'KEY'")
+        );
+    }
+
+    #[test]
+    fn test_parses_ps_path_directive() {
+        let input =
+            "# @aws-ps-path /myapp/$env/ @optional @ttl 5m\nMYAPP=\n# @aws-ps /myapp/key\nKEY=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(result[0].ps_path.as_deref(), Some("/myapp/$env/"));
+        assert_eq!(result[0].provider_config, None);
+        assert!(!result[0].required);
+        assert_eq!(result[0].ttl, Some(Duration::from_secs(300)));
+        assert_eq!(result[1].ps_path, None);
+        assert!(result.has_secrets());
+
+        let err =
+            EnvParser::parse_variables("# @aws-ps-path /myapp/ @mirror app\nAPP=\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Invalid @aws-ps-path for key 'APP'")
+        );
+    }
+
+    #[test]
+    fn test_parses_secret_versions() {
+        let input = "# @aws-sm prod/db#password @version-stage=AWSPREVIOUS\nPREVIOUS=\n# @aws-sm prod/db @version-id=a1b2-c3\nPINNED=\n# @aws-sm app@PROD\nAPP=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].provider_config,
+            Some(ProviderConfig::AwsSecretsManager("prod/db".to_owned()))
+        );
+        assert_eq!(
+            result[0].version,
+            Some(SecretVersion::Stage("AWSPREVIOUS".to_owned()))
+        );
+        assert_eq!(result[0].json_key.as_deref(), Some("password"));
+        assert_eq!(
+            result[1].version,
+            Some(SecretVersion::Id("a1b2-c3".to_owned()))
+        );
+        assert_eq!(
+            result[2].provider_config,
+            Some(ProviderConfig::AwsSecretsManager("app@PROD".to_owned()))
+        );
+        assert_eq!(result[2].version, None);
+
+        let err = EnvParser::parse_variables(
+            "# @aws-sm prod/db @version-stage=AWSPENDING @version-id=a1\nKEY=\n",
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Invalid version for key 'KEY', only one of")
+        );
+
+        let err =
+            EnvParser::parse_variables("# @aws-ps /app/key @version-id=3\nKEY=\n").unwrap_err();
+        assert!(err.to_string().starts_with("Invalid version for key 'KEY'"));
+    }
+
+    #[test]
+    fn test_parses_max_age_directive() {
+        let input = "# @aws-sm db/creds @max-age 30d @ttl 5m\nDB=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].max_age,
+            Some(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(result[0].ttl, Some(Duration::from_secs(300)));
+
+        let err = EnvParser::parse_variables("# @aws-sm key @max-age 0\nKEY=\n").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid @max-age for key 'KEY'");
+    }
+
+    #[test]
+    fn test_parses_generate_directive() {
+        let input = "# @aws-sm myapp/$env/session-key @generate hex:32 @optional\nSESSION_KEY=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(result[0].generate, Some(Generator::Hex(32)));
+        assert!(!result[0].required);
+
+        let err = EnvParser::parse_variables("# @aws-sm key @generate hex:0\nKEY=\n").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid @generate for key 'KEY'");
+    }
+
+    #[test]
+    fn test_parses_mirror_directive() {
+        let input = "# @aws-sm app/$env/db @mirror /app/$env/db @optional\nDB=\n# @aws-ps /app/key @mirror app/key\nKEY=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].mirror,
+            Some(ProviderConfig::AwsParameterStore("/app/$env/db".to_owned()))
+        );
+        assert!(!result[0].required);
+        assert_eq!(
+            result[1].mirror,
+            Some(ProviderConfig::AwsSecretsManager("app/key".to_owned()))
+        );
+
+        let err =
+            EnvParser::parse_variables("# @plugin vault kv/key @mirror /key\nKEY=\n").unwrap_err();
+        assert!(err.to_string().contains("Invalid @mirror for key 'KEY'"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_ttl() {
+        for ttl in ["0", "soon"] {
+            let input = format!("# @aws-sm db/creds @ttl {}\nDB=\n", ttl);
+            let err = EnvParser::parse_variables(&input).unwrap_err();
+
+            assert_eq!(err.to_string(), "Invalid @ttl for key 'DB'");
+        }
+    }
+
     #[test]
     fn test_parses_multiple_directives() {
         let input = r#"
@@ -613,4 +1320,246 @@ mod tests {
             .into()
         )
     }
+
+    #[test]
+    fn test_parses_descriptions() {
+        let input = r#"
+            # @desc Connection string for the primary Postgres
+            # @aws-sm prod/db-url @optional
+            DATABASE_URL=
+
+            # @desc Port the server
+            # @desc listens on
+            PORT=8080
+        "#;
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].description.as_deref(),
+            Some("Connection string for the primary Postgres")
+        );
+        assert!(!result[0].required);
+        assert_eq!(
+            result[1].description.as_deref(),
+            Some("Port the server listens on")
+        );
+    }
+
+    #[test]
+    fn test_parses_description_after_directive() {
+        let input = "# @aws-ps /app/key\n# @desc API key\nKEY1=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(result[0].description.as_deref(), Some("API key"));
+        assert_eq!(
+            result[0].provider_config,
+            Some(ProviderConfig::AwsParameterStore("/app/key".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parses_deprecations() {
+        let input = r#"
+            # @deprecated use DATABASE_URL
+            # @aws-sm prod/db-url
+            DB_URL=
+
+            # @deprecated
+            OLD_PORT=8080
+            PORT=8080
+        "#;
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(result[0].deprecated.as_deref(), Some("use DATABASE_URL"));
+        assert_eq!(result[1].deprecated.as_deref(), Some(""));
+        assert_eq!(result[2].deprecated, None);
+    }
+
+    #[test]
+    fn test_parses_validations() {
+        let input = r#"
+            # @validate regex:^postgres://
+            # @aws-sm prod/db-url
+            # @min-length 32
+            DATABASE_URL=
+        "#;
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].validations,
+            vec![
+                "regex:^postgres://".parse().unwrap(),
+                Validation::MinLength(32)
+            ]
+        );
+
+        let err = EnvParser::parse_variables("# @validate regex:(\nKEY1=\n").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid @validate for key 'KEY1'");
+    }
+
+    #[test]
+    fn test_parses_output_exclusions() {
+        let input = "# @exclude-from json, shell\nDEBUG=1\n# @no-export\nLOCAL=1\nPORT=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(result[0].exclude_from, vec!["json", "shell"]);
+        assert!(!result[0].is_exported_to("json"));
+        assert!(result[0].is_exported_to("env"));
+        assert!(result[1].no_export);
+        assert!(!result[1].is_exported_to("env"));
+        assert!(result[2].is_exported_to("json"));
+    }
+
+    #[test]
+    fn test_parses_seal() {
+        let input = "# @aws-sm app/token\n# @seal\nTOKEN=\nPORT=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert!(result[0].seal);
+        assert!(!result[1].seal);
+    }
+
+    #[test]
+    fn test_parses_unique_groups() {
+        let input = "# @unique\nJWT_SECRET=\n# @unique dbs\nPRIMARY_DB=\nPORT=\n";
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(result[0].unique.as_deref(), Some(""));
+        assert_eq!(result[1].unique.as_deref(), Some("dbs"));
+        assert_eq!(result[2].unique, None);
+    }
+
+    #[test]
+    fn test_expands_macros() {
+        let input = r#"
+            # @define @db = @aws-sm myapp/$env/db/%s
+            # @define @ps = @aws-ps /myapp/$env @optional
+            # @define @replica = @db replica
+
+            # @db password @tags team=data
+            DB_PASSWORD=
+            # @ps
+            FLAG=
+            # @replica
+            REPLICA_URL=
+        "#;
+        let result = EnvParser::parse_variables(input).unwrap();
+
+        assert_eq!(
+            result[0].provider_config,
+            Some(ProviderConfig::AwsSecretsManager(
+                "myapp/$env/db/password".to_string()
+            ))
+        );
+        assert_eq!(result[0].tags["team"], "data");
+        assert_eq!(
+            result[1].provider_config,
+            Some(ProviderConfig::AwsParameterStore("/myapp/$env".to_string()))
+        );
+        assert!(!result[1].required);
+        assert_eq!(
+            result[2].provider_config,
+            Some(ProviderConfig::AwsSecretsManager(
+                "myapp/$env/db/replica".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_keeps_line_numbers_when_expanding_macros() {
+        let input = "# @define @db = @aws-sm db/%s\n# @db\nKEY1=\n";
+        let err = EnvParser::parse_variables(input).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid macro use on line 2");
+
+        let expanded = EnvParser::expand_macros("# @define @db = @aws-sm db/%s\n# @db a\nKEY1=\n");
+        assert_eq!(expanded.unwrap(), "\n# @aws-sm db/a\nKEY1=\n");
+    }
+
+    #[test]
+    fn test_expands_predefined_macros() {
+        let definitions = vec!["@db = @aws-sm myapp/db/%s".to_string()];
+        let expanded = EnvParser::expand_macros_with(
+            "# @define @replica = @db replica\n# @db password\nKEY1=\n# @replica\nKEY2=\n",
+            &definitions,
+        );
+        assert_eq!(
+            expanded.unwrap(),
+            "\n# @aws-sm myapp/db/password\nKEY1=\n# @aws-sm myapp/db/replica\nKEY2=\n"
+        );
+
+        let definitions = vec!["@aws-sm = @aws-ps a".to_string()];
+        assert!(EnvParser::expand_macros_with("KEY1=\n", &definitions).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_macros() {
+        let err = EnvParser::expand_macros("# @define @optional = @aws-sm a\n").unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Invalid macro definition on line 1: Macro @optional would replace a built-in directive"
+        );
+
+        assert!(EnvParser::expand_macros("# @define db = @aws-sm a\n").is_err());
+        assert!(EnvParser::expand_macros("# @define @db = db\n").is_err());
+    }
+
+    #[test]
+    fn test_handles_empty_input() {
+        let result = EnvParser::parse_variables("");
+
+        assert_eq!(result.unwrap(), Variables::new())
+    }
+
+    #[test]
+    fn test_handles_comment_only_input() {
+        let input = r#"
+            # test 123
+
+            # test 456"#;
+        let result = EnvParser::parse_variables(input);
+
+        assert_eq!(result.unwrap(), Variables::new())
+    }
+
+    #[test]
+    fn test_handles_trailing_comment_without_newline() {
+        let input = "KEY1=value1 # test 123";
+        let result = EnvParser::parse_variables(input);
+
+        assert_eq!(
+            result.unwrap(),
+            vec![Variable {
+                key: "KEY1".to_owned(),
+                required: true,
+                default: Some("value1".to_owned()),
+                ..Default::default()
+            }]
+            .into()
+        )
+    }
+
+    #[test]
+    fn test_duplicate_keys_first_wins() {
+        let input = r#"
+            KEY1=value1
+            KEY2=value2
+            KEY1=overridden
+        "#;
+        let result = EnvParser::parse_variables_with(input, Duplicates::FirstWins).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].key, "KEY1");
+        assert_eq!(result[0].default.as_deref(), Some("value1"));
+    }
+
+    #[test]
+    fn test_duplicate_keys_error() {
+        let input = "KEY1=value1\nKEY2=value2\nKEY1=overridden\n";
+        let result = EnvParser::parse_variables_with(input, Duplicates::Error);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Duplicate declaration for key 'KEY1' on line 3, first declared on line 1"
+        );
+    }
 }
@@ -0,0 +1,228 @@
+//! What resolving a spec would do, worked out from the spec alone without fetching
+//! anything: which provider and secret each key comes from and which keys its default
+//! references
+
+use std::fmt::Display;
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+use crate::{
+    resolve::replace_placeholders, term::format_table, validate::references, variable::Variables,
+};
+
+/// How a single key is resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanEntry {
+    pub key: String,
+    /// Name of the provider as used in directives, `None` for keys without one
+    pub provider: Option<String>,
+    /// Secret ID with placeholders substituted, left as written where they're missing
+    pub id: Option<String>,
+    pub required: bool,
+    pub has_default: bool,
+    /// Keys referenced with `${NAME}` in the default value
+    pub references: Vec<String>,
+    pub status: Status,
+}
+
+/// Where the value of a key will come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Fetched from the provider
+    Fetch,
+    /// The secret ID uses a placeholder that wasn't given
+    MissingPlaceholder,
+    /// Taken from the spec's default
+    Default,
+    /// Nothing supplies a value
+    Unset,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Status::Fetch => "fetch",
+            Status::MissingPlaceholder => "missing-placeholder",
+            Status::Default => "default",
+            Status::Unset => "unset",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+/// How `awsm-env plan` prints the plan
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat {
+    /// Aligned columns for reading in a terminal
+    #[default]
+    Table,
+    /// Comma separated values with a header row, for spreadsheets
+    Csv,
+    /// Tab separated values with a header row
+    Tsv,
+}
+
+/// Plans the resolution of every key in `variables`
+pub fn plan(variables: &Variables, placeholders: &IndexMap<String, String>) -> Vec<PlanEntry> {
+    variables
+        .iter()
+        .map(|var| {
+            let config = var.provider_config.as_ref();
+            let id = config.map(|config| replace_placeholders(config.id(), placeholders));
+            let status = match (&id, &var.default) {
+                (Some(Ok(_)), _) => Status::Fetch,
+                (Some(Err(_)), _) => Status::MissingPlaceholder,
+                (None, Some(_)) => Status::Default,
+                (None, None) => Status::Unset,
+            };
+
+            PlanEntry {
+                key: var.key.clone(),
+                provider: config.map(|config| config.name().to_string()),
+                id: config.zip(id).map(|(config, id)| {
+                    id.map(|id| id.into_owned())
+                        .unwrap_or_else(|_| config.id().to_string())
+                }),
+                required: var.required,
+                has_default: var.default.is_some(),
+                references: references(var.default.as_deref().unwrap_or_default())
+                    .unique()
+                    .map(str::to_string)
+                    .collect(),
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Formats `plan` with a row of key, provider, ID, whether it's required and status per
+/// key. Keys without a provider have empty provider and ID cells, `-` in tables.
+pub fn format_plan(plan: &[PlanEntry], format: PlanFormat) -> String {
+    let header = ["KEY", "PROVIDER", "ID", "REQUIRED", "STATUS"];
+    let rows = plan.iter().map(|entry| {
+        [
+            entry.key.clone(),
+            entry.provider.clone().unwrap_or_default(),
+            entry.id.clone().unwrap_or_default(),
+            entry.required.to_string(),
+            entry.status.to_string(),
+        ]
+    });
+
+    match format {
+        PlanFormat::Table => format_table(
+            header,
+            &rows
+                .map(|row| {
+                    row.map(|cell| match cell.is_empty() {
+                        true => "-".into(),
+                        false => cell,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ),
+        PlanFormat::Csv => format_delimited(header, rows, ",", csv_field),
+        PlanFormat::Tsv => format_delimited(header, rows, "\t", tsv_field),
+    }
+}
+
+fn format_delimited<const N: usize>(
+    header: [&str; N],
+    rows: impl Iterator<Item = [String; N]>,
+    delimiter: &str,
+    field: fn(&str) -> String,
+) -> String {
+    let header = header.map(str::to_lowercase);
+
+    std::iter::once(header)
+        .chain(rows)
+        .map(|row| row.iter().map(|cell| field(cell)).join(delimiter) + "\n")
+        .collect()
+}
+
+/// Quotes fields holding a delimiter, quote or line break as in RFC 4180
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+/// TSV can't escape tabs and line breaks, so they're replaced with spaces
+fn tsv_field(s: &str) -> String {
+    s.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::EnvParser;
+
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let variables = EnvParser::parse_variables(
+            "# @aws-sm db/$env/password\nDB_PASSWORD=\n\n# @aws-ps /$region/host @optional\nDB_HOST=\n\nDB_URL=postgres://${DB_HOST}/${DB_HOST}\n",
+        )
+        .unwrap();
+        let placeholders = IndexMap::from([("env".to_string(), "prod".to_string())]);
+
+        assert_eq!(
+            plan(&variables, &placeholders),
+            vec![
+                PlanEntry {
+                    key: "DB_PASSWORD".to_string(),
+                    provider: Some("aws-sm".to_string()),
+                    id: Some("db/prod/password".to_string()),
+                    required: true,
+                    has_default: false,
+                    references: vec![],
+                    status: Status::Fetch,
+                },
+                PlanEntry {
+                    key: "DB_HOST".to_string(),
+                    provider: Some("aws-ps".to_string()),
+                    id: Some("/$region/host".to_string()),
+                    required: false,
+                    has_default: false,
+                    references: vec![],
+                    status: Status::MissingPlaceholder,
+                },
+                PlanEntry {
+                    key: "DB_URL".to_string(),
+                    provider: None,
+                    id: None,
+                    required: true,
+                    has_default: true,
+                    references: vec!["DB_HOST".to_string()],
+                    status: Status::Default,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_plan() {
+        let variables = EnvParser::parse_variables(
+            "# @aws-sm app/db\nDB_PASSWORD=\n\n# @aws-ps /app/port @optional\nPORT=8080\nDEBUG=\n",
+        )
+        .unwrap();
+        let plan = plan(&variables, &IndexMap::new());
+
+        assert_eq!(
+            format_plan(&plan, PlanFormat::Csv),
+            "key,provider,id,required,status\nDB_PASSWORD,aws-sm,app/db,true,fetch\nPORT,aws-ps,/app/port,false,fetch\nDEBUG,,,true,unset\n"
+        );
+        assert_eq!(
+            format_plan(&plan, PlanFormat::Tsv).lines().nth(2),
+            Some("PORT\taws-ps\t/app/port\tfalse\tfetch")
+        );
+        assert_eq!(
+            format_plan(&plan, PlanFormat::Table).lines().nth(3),
+            Some("DEBUG        -         -          true      unset")
+        );
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
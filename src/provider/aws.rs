@@ -0,0 +1,369 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, anyhow};
+use aws_config::{
+    AppName, Region,
+    ecs::EcsCredentialsProvider,
+    environment::{
+        credentials::EnvironmentVariableCredentialsProvider,
+        region::EnvironmentVariableRegionProvider,
+    },
+    imds::credentials::ImdsCredentialsProvider,
+    meta::{credentials::CredentialsProviderChain, region::RegionProviderChain},
+    profile::{ProfileFileCredentialsProvider, ProfileFileRegionProvider},
+    web_identity_token::WebIdentityTokenCredentialsProvider,
+};
+use aws_credential_types::{
+    Credentials,
+    provider::{ProvideCredentials, SharedCredentialsProvider, error::CredentialsError, future},
+};
+use aws_sdk_sts::{operation::assume_role::builders::AssumeRoleFluentBuilder, types::Tag};
+#[cfg(feature = "rustls")]
+use aws_smithy_http_client::{
+    Builder as HttpClientBuilder, Connector,
+    proxy::ProxyConfig,
+    tls::{self, TlsContext, TrustStore, rustls_provider::CryptoMode},
+};
+#[cfg(feature = "rustls")]
+use rustls_pki_types::{CertificateDer, pem::PemObject};
+
+pub use aws_config::SdkConfig;
+pub use aws_smithy_runtime_api::client::http::SharedHttpClient;
+
+use crate::provider::{AwsAccount, AwsOptions, CredentialSource};
+
+/// How long to wait for a single credential source when probing
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Environment variables checked for a proxy, AWS endpoints are always HTTPS
+#[cfg(feature = "rustls")]
+const PROXY_ENV_VARS: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// Environment variables listing hosts that bypass the proxy
+#[cfg(feature = "rustls")]
+const NO_PROXY_ENV_VARS: [&str; 2] = ["NO_PROXY", "no_proxy"];
+
+tokio::task_local! {
+    /// Set with [`with_account`]
+    static ACCOUNT: AwsAccount;
+}
+
+/// Runs `future` with AWS providers created within it using `account`, e.g. to render the
+/// same spec for several accounts in one process
+pub async fn with_account<F: Future>(account: AwsAccount, future: F) -> F::Output {
+    ACCOUNT.scope(account, future).await
+}
+
+/// Loads the AWS configuration shared by AWS providers, applying `options`. Fails when
+/// the proxy or CA bundle options can't be used.
+pub async fn load_config(options: &AwsOptions) -> Result<SdkConfig> {
+    let mut loader = aws_config::from_env();
+
+    if let Some(sources) = options.effective_credential_sources() {
+        loader = loader.credentials_provider(credentials_chain(&sources));
+    }
+
+    if options.no_imds {
+        loader = loader.region(
+            RegionProviderChain::first_try(EnvironmentVariableRegionProvider::new())
+                .or_else(ProfileFileRegionProvider::builder().build()),
+        );
+    }
+
+    // Only override when enabled so `AWS_USE_FIPS_ENDPOINT`, `AWS_USE_DUALSTACK_ENDPOINT`
+    // and the equivalent profile settings keep working
+    if options.use_fips {
+        loader = loader.use_fips(true);
+    }
+
+    if options.use_dual_stack {
+        loader = loader.use_dual_stack(true);
+    }
+
+    // Valid by construction, `--context` only allows characters app names can hold
+    if let Ok(app_name) = AppName::new(options.app_name()) {
+        loader = loader.app_name(app_name);
+    }
+
+    let http_client = match &options.http_client {
+        Some(client) => Some(client.clone()),
+        None => http_client(options)?,
+    };
+    if let Some(http_client) = http_client {
+        loader = loader.http_client(http_client);
+    }
+
+    let account = ACCOUNT.try_with(AwsAccount::clone).unwrap_or_default();
+
+    if let Some(profile) = &account.profile {
+        loader = loader.profile_name(profile);
+    }
+
+    if let Some(region) = &account.region {
+        loader = loader.region(Region::new(region.clone()));
+    }
+
+    let config = loader.load().await;
+
+    let config = match account.role_arn.as_ref().or(options.role_arn.as_ref()) {
+        Some(role_arn) => {
+            let assume_role = aws_sdk_sts::Client::new(&config)
+                .assume_role()
+                .role_arn(role_arn)
+                .role_session_name(options.role_session_name())
+                .set_tags(session_tags(&options.session_tags))
+                .set_source_identity(options.source_identity.clone());
+
+            config
+                .into_builder()
+                .credentials_provider(SharedCredentialsProvider::new(RoleProvider { assume_role }))
+                .build()
+        }
+        None => config,
+    };
+
+    Ok(config)
+}
+
+fn session_tags(tags: &[(String, String)]) -> Option<Vec<Tag>> {
+    if tags.is_empty() {
+        return None;
+    }
+
+    // Building a tag only fails without a key or value
+    tags.iter()
+        .map(|(key, value)| Tag::builder().key(key).value(value).build())
+        .collect::<Result<_, _>>()
+        .ok()
+}
+
+/// Assumes a role with session tags and a source identity, which the SDK's
+/// `AssumeRoleProvider` can't set. Credentials are cached by the clients using it.
+#[derive(Debug)]
+struct RoleProvider {
+    assume_role: AssumeRoleFluentBuilder,
+}
+
+impl ProvideCredentials for RoleProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(async move {
+            let output = self
+                .assume_role
+                .clone()
+                .send()
+                .await
+                .map_err(CredentialsError::provider_error)?;
+            let credentials = output.credentials.ok_or_else(|| {
+                CredentialsError::provider_error("STS returned no credentials for the role")
+            })?;
+
+            Ok(Credentials::new(
+                credentials.access_key_id,
+                credentials.secret_access_key,
+                Some(credentials.session_token),
+                SystemTime::try_from(credentials.expiration).ok(),
+                "RoleProvider",
+            ))
+        })
+    }
+}
+
+#[cfg(not(feature = "rustls"))]
+fn http_client(_options: &AwsOptions) -> Result<Option<SharedHttpClient>> {
+    Ok(None)
+}
+
+/// Builds an HTTP client when the SDK's default one can't be used, e.g. behind a proxy
+/// or with a custom CA bundle
+#[cfg(feature = "rustls")]
+fn http_client(options: &AwsOptions) -> Result<Option<SharedHttpClient>> {
+    let proxy = proxy_config(options)?;
+
+    if proxy.is_none() && options.ca_bundle.is_none() {
+        return Ok(None);
+    }
+
+    let mut trust_store = TrustStore::default();
+    if let Some(pem) = &options.ca_bundle {
+        check_ca_bundle(pem)?;
+        trust_store = trust_store.with_pem_certificate(pem.as_slice());
+    }
+
+    let context = TlsContext::builder()
+        .with_trust_store(trust_store)
+        .build()
+        .context("Invalid CA bundle")?;
+    let proxy = proxy.unwrap_or_else(ProxyConfig::disabled);
+
+    // Only connectors take a proxy, so they're built the way the SDK's default client does
+    let client = HttpClientBuilder::new().build_with_connector_fn(move |settings, components| {
+        let mut connector = Connector::builder()
+            .tls_provider(tls::Provider::Rustls(CryptoMode::AwsLc))
+            .tls_context(context.clone())
+            .proxy_config(proxy.clone());
+        connector.set_connector_settings(settings.cloned());
+        if let Some(components) = components {
+            connector.set_sleep_impl(components.sleep_impl());
+        }
+
+        connector.build()
+    });
+
+    Ok(Some(client))
+}
+
+/// Checks that `pem` holds certificates usable as trust anchors. The SDK only parses
+/// them once it connects, and panics if they're invalid.
+#[cfg(feature = "rustls")]
+fn check_ca_bundle(pem: &[u8]) -> Result<()> {
+    let certificates = CertificateDer::pem_slice_iter(pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| anyhow!("Invalid PEM in the CA bundle: {}", err))?;
+
+    if certificates.is_empty() {
+        return Err(anyhow!("No certificates found in the CA bundle"));
+    }
+
+    let mut roots = rustls::RootCertStore::empty();
+    for certificate in certificates {
+        roots
+            .add(certificate)
+            .context("Invalid certificate in the CA bundle")?;
+    }
+
+    Ok(())
+}
+
+/// `--proxy` takes precedence over the environment, `NO_PROXY` is honoured either way
+#[cfg(feature = "rustls")]
+fn proxy_config(options: &AwsOptions) -> Result<Option<ProxyConfig>> {
+    let Some(url) = &options.proxy else {
+        return Ok(first_env(&PROXY_ENV_VARS).map(|_| ProxyConfig::from_env()));
+    };
+
+    let config =
+        ProxyConfig::all(url.as_str()).with_context(|| format!("Invalid proxy URL {}", url))?;
+
+    Ok(Some(match first_env(&NO_PROXY_ENV_VARS) {
+        Some(no_proxy) => config.no_proxy(no_proxy),
+        None => config,
+    }))
+}
+
+#[cfg(feature = "rustls")]
+fn first_env(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+fn credentials_chain(sources: &[CredentialSource]) -> CredentialsProviderChain {
+    let mut providers = sources
+        .iter()
+        .map(|source| (source.name(), credentials_provider(*source)));
+
+    let (name, first) = providers
+        .next()
+        .expect("at least one credential source should be enabled");
+
+    providers.fold(
+        CredentialsProviderChain::first_try(name, first),
+        |chain, (name, provider)| chain.or_else(name, provider),
+    )
+}
+
+fn credentials_provider(source: CredentialSource) -> SharedCredentialsProvider {
+    match source {
+        CredentialSource::Env => {
+            SharedCredentialsProvider::new(EnvironmentVariableCredentialsProvider::new())
+        }
+        CredentialSource::Profile => {
+            SharedCredentialsProvider::new(ProfileFileCredentialsProvider::builder().build())
+        }
+        CredentialSource::WebIdentity => {
+            SharedCredentialsProvider::new(WebIdentityTokenCredentialsProvider::builder().build())
+        }
+        CredentialSource::Ecs => {
+            SharedCredentialsProvider::new(EcsCredentialsProvider::builder().build())
+        }
+        CredentialSource::Imds => {
+            SharedCredentialsProvider::new(ImdsCredentialsProvider::builder().build())
+        }
+    }
+}
+
+/// Returns the ARN of the principal the credentials of `config` belong to
+pub async fn caller_identity(config: &SdkConfig) -> Result<String> {
+    let output = aws_sdk_sts::Client::new(config)
+        .get_caller_identity()
+        .send()
+        .await?;
+
+    output
+        .arn
+        .ok_or_else(|| anyhow!("STS returned no caller identity"))
+}
+
+/// Downloads an S3 object, e.g. a shared config
+pub async fn get_object(config: &SdkConfig, bucket: &str, key: &str) -> Result<Vec<u8>> {
+    let output = aws_sdk_s3::Client::new(config)
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await?;
+
+    Ok(output.body.collect().await?.into_bytes().to_vec())
+}
+
+/// Asks a single credential source for credentials, returning when they expire
+pub async fn probe_credentials(source: CredentialSource) -> Result<Option<SystemTime>> {
+    let provider = credentials_provider(source);
+
+    let credentials = tokio::time::timeout(PROBE_TIMEOUT, provider.provide_credentials())
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", PROBE_TIMEOUT.as_secs()))??;
+
+    Ok(credentials.expiry())
+}
+
+#[cfg(all(test, feature = "rustls"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_invalid_ca_bundles() {
+        let bundles = [
+            "not a certificate",
+            "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n",
+            "-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydGlmaWNhdGU=\n-----END CERTIFICATE-----\n",
+        ];
+
+        for bundle in bundles {
+            let options = AwsOptions {
+                ca_bundle: Some(bundle.as_bytes().to_vec()),
+                ..Default::default()
+            };
+            assert!(http_client(&options).is_err(), "{}", bundle);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_proxy_urls() {
+        let options = AwsOptions {
+            proxy: Some("http://proxy.internal:3128".to_string()),
+            ..Default::default()
+        };
+        assert!(http_client(&options).unwrap().is_some());
+
+        let options = AwsOptions {
+            proxy: Some("http://[::1".to_string()),
+            ..Default::default()
+        };
+        assert!(http_client(&options).is_err());
+    }
+}
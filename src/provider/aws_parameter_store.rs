@@ -1,46 +1,196 @@
-use crate::provider::{Provider, ResolvedSecret};
+use crate::provider::{
+    AwsOptions, Batching, CreateOptions, Provider, SdkConfig, SecretMetadata, SecretResult,
+    SecretVersion, aws, fetch_batched,
+};
+use aws_sdk_ssm::types::{ParameterStringFilter, ParameterType, Tag};
 
-use anyhow::Result;
-use itertools::Itertools;
+use anyhow::{Context, Result};
 
 pub struct AwsParameterStoreProvider {
     client: aws_sdk_ssm::Client,
+    batching: Batching,
 }
 
 impl AwsParameterStoreProvider {
-    pub async fn new() -> Self {
-        let config = aws_config::load_from_env().await;
-        let client = aws_sdk_ssm::Client::new(&config);
+    pub async fn new(options: &AwsOptions) -> Result<Self> {
+        let config = aws::load_config(options).await?;
+        Ok(Self::from_config(&config).with_batching(options.batching))
+    }
+
+    /// Creates the provider from an existing AWS configuration with the default batching
+    pub fn from_config(config: &SdkConfig) -> Self {
+        let client = aws_sdk_ssm::Client::new(config);
+
+        Self {
+            client,
+            batching: Batching::default(),
+        }
+    }
+
+    /// Uses `batching` to split up fetches instead of the default batch size
+    pub fn with_batching(mut self, batching: Batching) -> Self {
+        self.batching = batching;
+        self
+    }
+
+    /// Fetches parameters by name, `name:version` and `name:label` included
+    async fn get_parameters(&self, names: Vec<String>) -> Result<Vec<SecretResult>> {
+        let Batching {
+            ps_batch_size,
+            max_concurrent_batches,
+            ..
+        } = self.batching;
+
+        fetch_batched(names, ps_batch_size, max_concurrent_batches, |batch| {
+            let client = self.client.clone();
 
-        Self { client }
+            async move {
+                let resp = client
+                    .get_parameters()
+                    .set_with_decryption(Some(true))
+                    .set_names(Some(batch.clone()))
+                    .send()
+                    .await?;
+
+                let parameters = resp.parameters.unwrap_or_default();
+
+                // Parameters fetched with a version or label have it as their selector
+                Ok(batch
+                    .into_iter()
+                    .map(|name| {
+                        let parameter = parameters.iter().find(|p| {
+                            let selector = p.selector.as_deref().unwrap_or_default();
+                            p.name
+                                .as_deref()
+                                .is_some_and(|n| n == name || format!("{}{}", n, selector) == name)
+                        });
+
+                        match parameter.and_then(|p| Some((p, p.value.clone()?))) {
+                            Some((p, value)) => SecretResult::Found {
+                                value,
+                                version: Some(p.version.to_string()),
+                                metadata: SecretMetadata {
+                                    changed_at: p
+                                        .last_modified_date
+                                        .and_then(|date| date.try_into().ok()),
+                                },
+                            },
+                            None => SecretResult::NotFound,
+                        }
+                    })
+                    .collect())
+            }
+        })
+        .await
     }
 }
 
 impl Provider for AwsParameterStoreProvider {
-    // All the expects are because the AWS SDK isn't idiomatic
-    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<ResolvedSecret>> {
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+        self.get_parameters(ids).await
+    }
+
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>> {
+        let names = ids
+            .into_iter()
+            .map(|(id, version)| match version {
+                SecretVersion::Id(version) => format!("{}:{}", id, version),
+                SecretVersion::Stage(label) => format!("{}:{}", id, label),
+            })
+            .collect();
+
+        self.get_parameters(names).await
+    }
+
+    async fn create_secret(&self, id: &str, value: &str, options: &CreateOptions) -> Result<()> {
+        let tags = options
+            .tags
+            .iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.client
+            .put_parameter()
+            .name(id)
+            .value(value)
+            .r#type(ParameterType::SecureString)
+            .set_key_id(options.kms_key_id.clone())
+            .set_tags((!tags.is_empty()).then_some(tags))
+            .overwrite(false)
+            .send()
+            .await
+            .with_context(|| format!("Failed to create parameter {}", id))?;
+
+        Ok(())
+    }
+
+    async fn list_secrets(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut result = Vec::new();
+
+        let mut pages = self
+            .client
+            .describe_parameters()
+            .parameter_filters(
+                ParameterStringFilter::builder()
+                    .key("Name")
+                    .option("BeginsWith")
+                    .values(prefix)
+                    .build()?,
+            )
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            result.extend(
+                page?
+                    .parameters
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|p| p.name),
+            );
+        }
+
+        Ok(result)
+    }
+
+    async fn list_secrets_by_path(&self, path: &str) -> Result<Vec<String>> {
         let mut result = Vec::new();
 
-        for chunk in &ids.into_iter().chunks(10) {
-            let resp = self
-                .client
-                .get_parameters()
-                .set_with_decryption(Some(true))
-                .set_names(Some(chunk.collect()))
-                .send()
-                .await?;
+        // Only the names are needed, the values are fetched like any other parameter
+        let mut pages = self
+            .client
+            .get_parameters_by_path()
+            .path(path)
+            .recursive(true)
+            .with_decryption(false)
+            .into_paginator()
+            .send();
 
+        while let Some(page) = pages.next().await {
             result.extend(
-                resp.parameters
-                    .expect("should have parameters")
+                page.with_context(|| format!("Failed to list parameters under {}", path))?
+                    .parameters
+                    .unwrap_or_default()
                     .into_iter()
-                    .map(|p| ResolvedSecret {
-                        id: p.name.expect("should have name"),
-                        secret: p.value.expect("should have value"),
-                    }),
+                    .filter_map(|p| p.name),
             );
         }
 
         Ok(result)
     }
+
+    // Parameter Store has no recovery window, parameters are deleted immediately
+    async fn delete_secret(&self, id: &str, _recovery_window_days: i64) -> Result<()> {
+        self.client
+            .delete_parameter()
+            .name(id)
+            .send()
+            .await
+            .with_context(|| format!("Failed to delete parameter {}", id))?;
+
+        Ok(())
+    }
 }
@@ -1,57 +1,262 @@
-use crate::provider::{Provider, ResolvedSecret};
-use anyhow::{Result, anyhow};
-use itertools::Itertools;
+use crate::{
+    provider::{
+        AwsOptions, Batching, CreateOptions, Provider, SdkConfig, SecretMetadata, SecretResult,
+        SecretVersion, aws, fetch_batched,
+    },
+    rotation::RotationStatus,
+};
+use anyhow::{Context, Result, anyhow};
+use aws_sdk_secretsmanager::error::ProvideErrorMetadata;
+use aws_sdk_secretsmanager::primitives::DateTime;
+use aws_sdk_secretsmanager::types::{Filter, FilterNameStringType, Tag};
 
 /// Fetches secrets from AWS Secrets Manager
 pub struct AwsSecretsManagerProvider {
     client: aws_sdk_secretsmanager::Client,
+    batching: Batching,
 }
 
 impl AwsSecretsManagerProvider {
-    pub async fn new() -> Self {
-        let config = aws_config::load_from_env().await;
-        let client = aws_sdk_secretsmanager::Client::new(&config);
+    pub async fn new(options: &AwsOptions) -> Result<Self> {
+        let config = aws::load_config(options).await?;
+        Ok(Self::from_config(&config).with_batching(options.batching))
+    }
+
+    /// Creates the provider from an existing AWS configuration with the default batching
+    pub fn from_config(config: &SdkConfig) -> Self {
+        let client = aws_sdk_secretsmanager::Client::new(config);
+
+        Self {
+            client,
+            batching: Batching::default(),
+        }
+    }
+
+    /// Uses `batching` to split up fetches instead of the default batch size
+    pub fn with_batching(mut self, batching: Batching) -> Self {
+        self.batching = batching;
+        self
+    }
+
+    /// Fetches the rotation configuration of a secret
+    pub async fn describe_rotation(&self, id: &str) -> Result<RotationStatus> {
+        let secret = self.client.describe_secret().secret_id(id).send().await?;
+
+        let to_system_time = |date: Option<DateTime>| date.and_then(|d| d.try_into().ok());
 
-        Self { client }
+        Ok(RotationStatus {
+            enabled: secret.rotation_enabled.unwrap_or_default(),
+            interval_days: secret
+                .rotation_rules
+                .and_then(|rules| rules.automatically_after_days),
+            last_rotated: to_system_time(secret.last_rotated_date),
+            next_rotation: to_system_time(secret.next_rotation_date),
+            created: to_system_time(secret.created_date),
+        })
+    }
+
+    /// Moves the `AWSCURRENT` stage of a secret to `version_id`, which also
+    /// moves `AWSPREVIOUS` to the version that was current before
+    pub async fn promote_version(&self, id: &str, version_id: &str) -> Result<()> {
+        let secret = self.client.describe_secret().secret_id(id).send().await?;
+
+        let current = secret
+            .version_ids_to_stages
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(_, stages)| stages.iter().any(|s| s == "AWSCURRENT"))
+            .map(|(version, _)| version);
+
+        if current.as_deref() == Some(version_id) {
+            return Ok(());
+        }
+
+        self.client
+            .update_secret_version_stage()
+            .secret_id(id)
+            .version_stage("AWSCURRENT")
+            .move_to_version_id(version_id)
+            .set_remove_from_version_id(current)
+            .send()
+            .await?;
+
+        Ok(())
     }
 }
 
 impl Provider for AwsSecretsManagerProvider {
-    // All the expects are because the AWS SDK isn't idiomatic
-    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<ResolvedSecret>> {
-        let mut result = Vec::new();
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+        let Batching {
+            sm_batch_size,
+            max_concurrent_batches,
+            ..
+        } = self.batching;
+
+        fetch_batched(ids, sm_batch_size, max_concurrent_batches, |batch| {
+            let client = self.client.clone();
 
-        for chunk in &ids.into_iter().chunks(20) {
-            let secrets = self
-                .client
-                .batch_get_secret_value()
-                .set_secret_id_list(Some(chunk.collect()))
-                .send()
-                .await?;
+            async move {
+                let response = client
+                    .batch_get_secret_value()
+                    .set_secret_id_list(Some(batch.clone()))
+                    .send()
+                    .await?;
 
-            let first_error = secrets.errors.and_then(|errors| {
-                errors
+                let values = response.secret_values.unwrap_or_default();
+                let errors = response.errors.unwrap_or_default();
+
+                // Secrets can be requested by name or ARN
+                Ok(batch
                     .into_iter()
-                    .filter(|e| e.error_code() != Some("ResourceNotFoundException"))
-                    .next()
-            });
+                    .map(|id| {
+                        let value = values.iter().find(|s| {
+                            s.name.as_deref() == Some(id.as_str())
+                                || s.arn.as_deref() == Some(id.as_str())
+                        });
+                        if let Some(s) = value {
+                            return found(
+                                &id,
+                                s.secret_string.clone(),
+                                s.version_id.clone(),
+                                s.created_date,
+                            );
+                        }
 
-            if let Some(error) = first_error {
-                return Err(anyhow!("Failed to fetch secrets: {:?}", error));
+                        match errors.iter().find(|e| e.secret_id() == Some(id.as_str())) {
+                            Some(error) => SecretResult::from_error_code(
+                                error.error_code(),
+                                anyhow!(
+                                    "Failed to fetch {}: {}",
+                                    id,
+                                    error.message().unwrap_or("unknown error")
+                                ),
+                            ),
+                            None => SecretResult::NotFound,
+                        }
+                    })
+                    .collect())
             }
+        })
+        .await
+    }
+
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>> {
+        let mut result = Vec::new();
 
+        // Batch fetching doesn't support versions so each secret is fetched individually
+        for (id, version) in ids {
+            let request = self.client.get_secret_value().secret_id(&id);
+            let request = match &version {
+                SecretVersion::Id(version_id) => request.version_id(version_id),
+                SecretVersion::Stage(stage) => request.version_stage(stage),
+            };
+
+            result.push(match request.send().await {
+                Ok(secret) => found(
+                    &id,
+                    secret.secret_string,
+                    secret.version_id,
+                    secret.created_date,
+                ),
+                Err(err) => {
+                    let code = err.code().map(str::to_string);
+                    let err = anyhow::Error::new(err)
+                        .context(format!("Failed to fetch version {:?} of {}", version, id));
+
+                    SecretResult::from_error_code(code.as_deref(), err)
+                }
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn create_secret(&self, id: &str, value: &str, options: &CreateOptions) -> Result<()> {
+        let tags = options
+            .tags
+            .iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect::<Vec<_>>();
+
+        self.client
+            .create_secret()
+            .name(id)
+            .secret_string(value)
+            .set_kms_key_id(options.kms_key_id.clone())
+            .set_tags((!tags.is_empty()).then_some(tags))
+            .send()
+            .await
+            .with_context(|| format!("Failed to create secret {}", id))?;
+
+        Ok(())
+    }
+
+    async fn list_secrets(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut result = Vec::new();
+
+        let mut pages = self
+            .client
+            .list_secrets()
+            .filters(
+                Filter::builder()
+                    .key(FilterNameStringType::Name)
+                    .values(prefix)
+                    .build(),
+            )
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
             result.extend(
-                secrets
-                    .secret_values
-                    .expect("should have secrets if there were no ResourceNotFound errors")
+                page?
+                    .secret_list
+                    .unwrap_or_default()
                     .into_iter()
-                    .map(|s| ResolvedSecret {
-                        id: s.name.expect("should have a name"),
-                        secret: s.secret_string.expect("should have a secret string"),
-                    }),
+                    .filter_map(|s| s.name)
+                    // The name filter isn't a strict prefix match
+                    .filter(|name| name.starts_with(prefix)),
             );
         }
 
         Ok(result)
     }
+
+    async fn delete_secret(&self, id: &str, recovery_window_days: i64) -> Result<()> {
+        self.client
+            .delete_secret()
+            .secret_id(id)
+            .recovery_window_in_days(recovery_window_days)
+            .send()
+            .await
+            .with_context(|| format!("Failed to delete secret {}", id))?;
+
+        Ok(())
+    }
+}
+
+/// A fetched secret, binary secrets can't be used in env files
+fn found(
+    id: &str,
+    value: Option<String>,
+    version: Option<String>,
+    created_date: Option<DateTime>,
+) -> SecretResult {
+    let Some(value) = value else {
+        return SecretResult::Error(anyhow!(
+            "{} is a binary secret, only strings are supported",
+            id
+        ));
+    };
+
+    SecretResult::Found {
+        value,
+        version,
+        metadata: SecretMetadata {
+            // Versions are immutable, so the value changed when the version was created
+            changed_at: created_date.and_then(|date| date.try_into().ok()),
+        },
+    }
 }
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use tokio::task::JoinSet;
+
+/// Fetches `items` in batches of `batch_size` with up to `max_concurrent` batches in
+/// flight, keeping the results in order. Stops at the first failed batch.
+pub(super) async fn fetch_batched<T, R, F, Fut>(
+    items: Vec<T>,
+    batch_size: usize,
+    max_concurrent: usize,
+    fetch: F,
+) -> Result<Vec<R>>
+where
+    R: Send + 'static,
+    F: Fn(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<Vec<R>>> + Send + 'static,
+{
+    let mut batches = items
+        .into_iter()
+        .chunks(batch_size.max(1))
+        .into_iter()
+        .map(|batch| batch.collect::<Vec<_>>())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .enumerate();
+
+    let mut in_flight = JoinSet::new();
+    let mut fetched = Vec::new();
+
+    loop {
+        while in_flight.len() < max_concurrent.max(1) {
+            let Some((index, batch)) = batches.next() else {
+                break;
+            };
+            let request = fetch(batch);
+            in_flight.spawn(async move { (index, request.await) });
+        }
+
+        let Some(joined) = in_flight.join_next().await else {
+            break;
+        };
+        let (index, secrets) = joined.context("Batch request panicked")?;
+        fetched.push((index, secrets?));
+    }
+
+    Ok(fetched
+        .into_iter()
+        .sorted_by_key(|(index, _)| *index)
+        .flat_map(|(_, secrets)| secrets)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_batched_keeps_order() {
+        let ids = (0..25).map(|i| i.to_string()).collect::<Vec<_>>();
+
+        let secrets = fetch_batched(ids.clone(), 4, 3, |batch| async move {
+            // Later batches finish first
+            let delay = 10 - batch[0].parse::<u64>().unwrap() / 4;
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+
+            Ok(batch)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(secrets, ids);
+
+        let failed = fetch_batched(ids, 10, 2, |batch| async move {
+            match batch.contains(&"12".to_string()) {
+                true => Err(anyhow::anyhow!("throttled")),
+                false => Ok(Vec::<String>::new()),
+            }
+        })
+        .await;
+        assert!(failed.is_err());
+    }
+}
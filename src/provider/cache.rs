@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::provider::{CreateOptions, Provider, SecretMetadata, SecretResult, SecretVersion};
+
+/// Wraps a provider, reusing the secrets it found for `ttl`. Secrets that weren't found
+/// or failed are fetched again, and so are specific versions.
+pub struct CachedProvider<P> {
+    inner: P,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Cached>>,
+}
+
+struct Cached {
+    value: String,
+    version: Option<String>,
+    metadata: SecretMetadata,
+    fetched_at: Instant,
+}
+
+impl<P> CachedProvider<P> {
+    /// Caches the secrets found by `inner` for `ttl`, nothing is cached if it's zero
+    pub fn new(inner: P, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// Forgets every cached secret
+    pub fn clear(&self) {
+        self.entries.lock().expect("cache lock poisoned").clear();
+    }
+
+    fn forget(&self, id: &str) {
+        self.entries.lock().expect("cache lock poisoned").remove(id);
+    }
+}
+
+impl<P: Provider> Provider for CachedProvider<P> {
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+        let mut results = {
+            let entries = self.entries.lock().expect("cache lock poisoned");
+
+            ids.iter()
+                .map(|id| {
+                    let cached = entries.get(id)?;
+                    (cached.fetched_at.elapsed() < self.ttl).then(|| SecretResult::Found {
+                        value: cached.value.clone(),
+                        version: cached.version.clone(),
+                        metadata: cached.metadata.clone(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let missing = ids
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_none())
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            let fetched = self.inner.provide_secrets(missing.clone()).await?;
+            let fetched_at = Instant::now();
+            let mut fetched = missing.into_iter().zip(fetched);
+            let mut entries = self.entries.lock().expect("cache lock poisoned");
+
+            for slot in results.iter_mut().filter(|result| result.is_none()) {
+                let Some((id, result)) = fetched.next() else {
+                    break;
+                };
+
+                if let SecretResult::Found {
+                    value,
+                    version,
+                    metadata,
+                } = &result
+                    && !self.ttl.is_zero()
+                {
+                    let cached = Cached {
+                        value: value.clone(),
+                        version: version.clone(),
+                        metadata: metadata.clone(),
+                        fetched_at,
+                    };
+                    entries.insert(id, cached);
+                }
+
+                *slot = Some(result);
+            }
+        }
+
+        // Fewer results than IDs when the provider returned too few, which callers check
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>> {
+        self.inner.provide_secret_versions(ids).await
+    }
+
+    async fn create_secret(&self, id: &str, value: &str, options: &CreateOptions) -> Result<()> {
+        self.forget(id);
+        self.inner.create_secret(id, value, options).await
+    }
+
+    async fn list_secrets(&self, prefix: &str) -> Result<Vec<String>> {
+        self.inner.list_secrets(prefix).await
+    }
+
+    async fn list_secrets_by_path(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.list_secrets_by_path(path).await
+    }
+
+    async fn delete_secret(&self, id: &str, recovery_window_days: i64) -> Result<()> {
+        self.forget(id);
+        self.inner.delete_secret(id, recovery_window_days).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Resolves every secret to its ID, counting the IDs requested
+    #[derive(Default)]
+    struct CountingProvider(AtomicUsize);
+
+    impl Provider for CountingProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            self.0.fetch_add(ids.len(), Ordering::SeqCst);
+
+            Ok(ids
+                .into_iter()
+                .map(|id| match id.ends_with("missing") {
+                    true => SecretResult::NotFound,
+                    false => SecretResult::Found {
+                        value: id,
+                        version: None,
+                        metadata: SecretMetadata::default(),
+                    },
+                })
+                .collect())
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            _: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            unimplemented!()
+        }
+
+        async fn create_secret(&self, _: &str, _: &str, _: &CreateOptions) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_reuses_found_secrets() {
+        let provider = CachedProvider::new(CountingProvider::default(), Duration::from_secs(60));
+        let requested = || provider.inner().0.load(Ordering::SeqCst);
+
+        provider
+            .provide_secrets(ids(&["a", "missing"]))
+            .await
+            .unwrap();
+        assert_eq!(requested(), 2);
+
+        let results = provider
+            .provide_secrets(ids(&["b", "a", "missing"]))
+            .await
+            .unwrap();
+        assert_eq!(requested(), 4);
+        assert!(matches!(
+            results.as_slice(),
+            [
+                SecretResult::Found { value: b, .. },
+                SecretResult::Found { value: a, .. },
+                SecretResult::NotFound,
+            ] if b == "b" && a == "a"
+        ));
+
+        provider
+            .create_secret("a", "", &CreateOptions::default())
+            .await
+            .unwrap();
+        provider.provide_secrets(ids(&["a", "b"])).await.unwrap();
+        assert_eq!(requested(), 5);
+
+        let uncached = CachedProvider::new(CountingProvider::default(), Duration::ZERO);
+        uncached.provide_secrets(ids(&["a"])).await.unwrap();
+        uncached.provide_secrets(ids(&["a"])).await.unwrap();
+        assert_eq!(uncached.inner().0.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,113 @@
+//! Stand-ins for providers whose feature is disabled, so callers don't need to be
+//! feature gated. Listing returns nothing, everything else fails with an error
+//! naming the missing feature.
+
+use anyhow::{Result, anyhow};
+
+use crate::provider::{
+    AwsOptions, Batching, CreateOptions, Provider, SdkConfig, SecretResult, SecretVersion,
+};
+#[cfg(not(feature = "aws-sm"))]
+use crate::rotation::RotationStatus;
+
+macro_rules! disabled_provider {
+    ($name:ident, $service:literal, $feature:literal) => {
+        #[doc = concat!("Stand-in for the ", $service, " provider, enable the `", $feature, "` feature to use it")]
+        pub struct $name;
+
+        impl $name {
+            pub async fn new(_options: &AwsOptions) -> Result<Self> {
+                Ok(Self)
+            }
+
+            pub fn from_config(_config: &SdkConfig) -> Self {
+                Self
+            }
+
+            pub fn with_batching(self, _batching: Batching) -> Self {
+                self
+            }
+
+            fn disabled() -> anyhow::Error {
+                anyhow!(concat!(
+                    $service,
+                    " support was not included in this build, rebuild with the `",
+                    $feature,
+                    "` feature"
+                ))
+            }
+        }
+
+        impl Provider for $name {
+            async fn provide_secrets(&self, _ids: Vec<String>) -> Result<Vec<SecretResult>> {
+                Err(Self::disabled())
+            }
+
+            async fn provide_secret_versions(
+                &self,
+                _ids: Vec<(String, SecretVersion)>,
+            ) -> Result<Vec<SecretResult>> {
+                Err(Self::disabled())
+            }
+
+            async fn create_secret(
+                &self,
+                _id: &str,
+                _value: &str,
+                _options: &CreateOptions,
+            ) -> Result<()> {
+                Err(Self::disabled())
+            }
+
+            async fn list_secrets(&self, _prefix: &str) -> Result<Vec<String>> {
+                Ok(Vec::new())
+            }
+
+            async fn list_secrets_by_path(&self, _path: &str) -> Result<Vec<String>> {
+                Err(Self::disabled())
+            }
+
+            async fn delete_secret(&self, _id: &str, _recovery_window_days: i64) -> Result<()> {
+                Err(Self::disabled())
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "aws-sm"))]
+disabled_provider!(AwsSecretsManagerProvider, "AWS Secrets Manager", "aws-sm");
+
+#[cfg(not(feature = "aws-sm"))]
+impl AwsSecretsManagerProvider {
+    pub async fn describe_rotation(&self, _id: &str) -> Result<RotationStatus> {
+        Err(Self::disabled())
+    }
+
+    pub async fn promote_version(&self, _id: &str, _version_id: &str) -> Result<()> {
+        Err(Self::disabled())
+    }
+}
+
+#[cfg(not(feature = "aws-ps"))]
+disabled_provider!(AwsParameterStoreProvider, "AWS Parameter Store", "aws-ps");
+
+#[cfg(all(test, not(feature = "aws-ps")))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_disabled_provider_fails_on_use() {
+        let provider = AwsParameterStoreProvider::new(&AwsOptions::default())
+            .await
+            .unwrap();
+
+        assert!(provider.list_secrets("/app").await.unwrap().is_empty());
+        let Err(err) = provider.provide_secrets(vec!["/app/key".to_string()]).await else {
+            panic!("expected the disabled provider to fail");
+        };
+        assert_eq!(
+            err.to_string(),
+            "AWS Parameter Store support was not included in this build, rebuild with the `aws-ps` feature"
+        );
+    }
+}
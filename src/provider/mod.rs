@@ -1,18 +1,376 @@
+mod aws;
+#[cfg(feature = "aws-ps")]
 mod aws_parameter_store;
+#[cfg(feature = "aws-sm")]
 mod aws_secrets_manager;
+#[cfg(any(feature = "aws-sm", feature = "aws-ps"))]
+mod batch;
+mod cache;
+#[cfg(not(all(feature = "aws-sm", feature = "aws-ps")))]
+mod disabled;
+mod plugin;
+#[cfg(feature = "wasm-plugins")]
+mod wasm_plugin;
 
-use anyhow::Result;
+use std::time::SystemTime;
 
+use anyhow::{Result, anyhow};
+use indexmap::IndexMap;
+
+use crate::variable::ProviderConfig;
+pub use crate::variable::SecretVersion;
+
+pub use aws::{
+    SdkConfig, SharedHttpClient, caller_identity, get_object, load_config, probe_credentials,
+    with_account,
+};
+#[cfg(feature = "aws-ps")]
 pub use aws_parameter_store::AwsParameterStoreProvider;
+#[cfg(feature = "aws-sm")]
 pub use aws_secrets_manager::AwsSecretsManagerProvider;
+#[cfg(any(feature = "aws-sm", feature = "aws-ps"))]
+use batch::fetch_batched;
+pub use cache::CachedProvider;
+#[cfg(not(feature = "aws-ps"))]
+pub use disabled::AwsParameterStoreProvider;
+#[cfg(not(feature = "aws-sm"))]
+pub use disabled::AwsSecretsManagerProvider;
+pub use plugin::{PLUGIN_PREFIX, PROTOCOL_VERSION, PluginProvider, discover_plugins};
+
+/// Outcome of fetching a single secret
+#[derive(Debug)]
+pub enum SecretResult {
+    Found {
+        value: String,
+        version: Option<String>,
+        metadata: SecretMetadata,
+    },
+    NotFound,
+    /// The caller isn't allowed to read the secret
+    AccessDenied,
+    /// Fetching this secret failed while others in the same request may have succeeded
+    Error(anyhow::Error),
+}
+
+/// Details about a secret's value besides its version
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SecretMetadata {
+    /// When the value was last changed, for providers that report it
+    pub changed_at: Option<SystemTime>,
+}
+
+impl SecretResult {
+    /// Classifies a failure by the error code AWS returned for it
+    pub fn from_error_code(code: Option<&str>, error: anyhow::Error) -> Self {
+        match code {
+            Some(
+                "ResourceNotFoundException" | "ParameterNotFound" | "ParameterVersionNotFound",
+            ) => SecretResult::NotFound,
+            Some("AccessDeniedException") => SecretResult::AccessDenied,
+            _ => SecretResult::Error(error),
+        }
+    }
+}
+
+/// Options applied when creating secrets
+#[derive(Debug, Default, Clone)]
+pub struct CreateOptions {
+    /// KMS key used to encrypt the secret instead of the account default
+    pub kms_key_id: Option<String>,
+    pub tags: IndexMap<String, String>,
+}
+
+/// A source of AWS credentials
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// `AWS_ACCESS_KEY_ID` and related environment variables
+    Env,
+    /// Shared config and credentials files, including SSO and `credential_process`
+    Profile,
+    /// Web identity tokens, e.g. IRSA on EKS
+    WebIdentity,
+    /// The ECS task role endpoint
+    Ecs,
+    /// The EC2 instance metadata service
+    Imds,
+}
+
+impl CredentialSource {
+    /// Order in which the SDK's default chain tries each source
+    pub const DEFAULT_CHAIN: [CredentialSource; 5] = [
+        CredentialSource::Env,
+        CredentialSource::Profile,
+        CredentialSource::WebIdentity,
+        CredentialSource::Ecs,
+        CredentialSource::Imds,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CredentialSource::Env => "env",
+            CredentialSource::Profile => "profile",
+            CredentialSource::WebIdentity => "web-identity",
+            CredentialSource::Ecs => "ecs",
+            CredentialSource::Imds => "imds",
+        }
+    }
+}
+
+/// Largest batch `BatchGetSecretValue` accepts
+pub const SM_MAX_BATCH_SIZE: usize = 20;
+/// Largest batch `GetParameters` accepts
+pub const PS_MAX_BATCH_SIZE: usize = 10;
+
+/// How secrets are split into batch requests, trading throughput for throttling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Batching {
+    /// Secrets per Secrets Manager request, at most [`SM_MAX_BATCH_SIZE`]
+    pub sm_batch_size: usize,
+    /// Parameters per Parameter Store request, at most [`PS_MAX_BATCH_SIZE`]
+    pub ps_batch_size: usize,
+    /// Batch requests in flight at once per provider
+    pub max_concurrent_batches: usize,
+}
+
+impl Default for Batching {
+    fn default() -> Self {
+        Batching {
+            sm_batch_size: SM_MAX_BATCH_SIZE,
+            ps_batch_size: PS_MAX_BATCH_SIZE,
+            max_concurrent_batches: 1,
+        }
+    }
+}
+
+/// Options used when loading the shared AWS configuration for providers
+#[derive(Debug, Default, Clone)]
+pub struct AwsOptions {
+    /// Credential sources to try, in order, instead of the SDK's default chain
+    pub credential_sources: Option<Vec<CredentialSource>>,
+    /// Never query the EC2 instance metadata service, for credentials or region
+    pub no_imds: bool,
+    /// Use FIPS 140-2 validated endpoints
+    pub use_fips: bool,
+    /// Use endpoints that support both IPv4 and IPv6
+    pub use_dual_stack: bool,
+    /// `http://` or `https://` proxy URL for all AWS requests, instead of the one set
+    /// in the environment. Requires the `rustls` feature.
+    pub proxy: Option<String>,
+    /// PEM encoded CA certificates trusted in addition to the system's. Requires the
+    /// `rustls` feature.
+    pub ca_bundle: Option<Vec<u8>>,
+    /// HTTP client for AWS requests, e.g. to control timeouts, collect metrics or use a
+    /// different TLS stack. Takes precedence over the proxy and CA bundle options.
+    ///
+    /// Required when the `rustls` feature is disabled, since the SDK then has no HTTP
+    /// client of its own.
+    pub http_client: Option<SharedHttpClient>,
+    pub batching: Batching,
+    /// Role to assume with the loaded credentials, unless an [`AwsAccount`] names one
+    pub role_arn: Option<String>,
+    /// Session tags of assumed roles, recorded in CloudTrail. The role's trust policy
+    /// must allow `sts:TagSession`.
+    pub session_tags: Vec<(String, String)>,
+    /// Source identity of assumed roles, e.g. the user or CI job, recorded in CloudTrail
+    /// and kept through role chaining. The role's trust policy must allow
+    /// `sts:SetSourceIdentity`.
+    pub source_identity: Option<String>,
+    /// What the run is part of, e.g. a CI job, added to the user agent and role session
+    /// name of AWS requests so CloudTrail events can be attributed to it
+    pub context: Option<String>,
+}
+
+impl AwsOptions {
+    /// Credential sources to try in order, or `None` for the SDK's default chain
+    pub fn effective_credential_sources(&self) -> Option<Vec<CredentialSource>> {
+        if self.credential_sources.is_none() && !self.no_imds {
+            return None;
+        }
+
+        Some(self.credential_chain())
+    }
+
+    /// Credential sources that will be tried, in order
+    pub fn credential_chain(&self) -> Vec<CredentialSource> {
+        self.credential_sources
+            .as_deref()
+            .unwrap_or(&CredentialSource::DEFAULT_CHAIN)
+            .iter()
+            .filter(|source| !(self.no_imds && **source == CredentialSource::Imds))
+            .copied()
+            .collect()
+    }
 
-pub struct ResolvedSecret {
-    pub id: String,
-    pub secret: String,
+    /// App name in the user agent of AWS requests, `awsm-env-<version>` followed by
+    /// `#<context>`
+    pub fn app_name(&self) -> String {
+        let name = format!("awsm-env-{}", env!("CARGO_PKG_VERSION"));
+
+        match &self.context {
+            Some(context) => format!("{}#{}", name, context),
+            None => name,
+        }
+    }
+
+    /// Session name of assumed roles, part of the ARN CloudTrail records as the caller
+    pub fn role_session_name(&self) -> String {
+        match &self.context {
+            Some(context) => format!("awsm-env-{}", context),
+            None => "awsm-env".to_string(),
+        }
+    }
+}
+
+/// AWS account to use instead of the one the shared configuration resolves to, see
+/// [`with_account`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AwsAccount {
+    /// Profile to load credentials and region from
+    pub profile: Option<String>,
+    /// Role to assume using the profile's credentials, or those of the default chain
+    pub role_arn: Option<String>,
+    pub region: Option<String>,
+}
+
+/// List price of a single request to the provider of `config`, in millionths of a USD.
+/// Secrets Manager charges $0.05 per 10,000 API calls while standard Parameter Store
+/// throughput is free, plugins have no known price.
+pub fn request_cost(config: &ProviderConfig) -> Option<u32> {
+    match config {
+        ProviderConfig::AwsSecretsManager(_) => Some(5),
+        ProviderConfig::AwsParameterStore(_) => Some(0),
+        ProviderConfig::Plugin { .. } => None,
+    }
 }
 
 /// A type that implements `Provider` allows provision of secret configurations
 pub trait Provider {
+    /// Fetches secrets by ID, returning a result for each ID in the same order. Fails
+    /// only when the request as a whole failed, e.g. without credentials.
+    #[allow(async_fn_in_trait)]
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>>;
+
+    /// Fetches specific versions of secrets given pairs of secret ID and version,
+    /// returning a result for each pair in the same order. Versions that don't exist
+    /// are [`SecretResult::NotFound`].
+    #[allow(async_fn_in_trait)]
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>>;
+
+    /// Creates a secret that doesn't exist yet
     #[allow(async_fn_in_trait)]
-    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<ResolvedSecret>>;
+    async fn create_secret(&self, id: &str, _value: &str, _options: &CreateOptions) -> Result<()> {
+        Err(anyhow!(
+            "Creating secrets isn't supported, can't create {}",
+            id
+        ))
+    }
+
+    /// Lists the IDs of all secrets whose names start with `prefix`
+    #[allow(async_fn_in_trait)]
+    async fn list_secrets(&self, prefix: &str) -> Result<Vec<String>> {
+        Err(anyhow!(
+            "Listing secrets isn't supported, can't list {}",
+            prefix
+        ))
+    }
+
+    /// Lists the IDs of all secrets under a `/` separated path, including nested ones.
+    /// Only Parameter Store supports paths.
+    #[allow(async_fn_in_trait)]
+    async fn list_secrets_by_path(&self, path: &str) -> Result<Vec<String>> {
+        Err(anyhow!(
+            "Listing secrets by path isn't supported, can't list {}",
+            path
+        ))
+    }
+
+    /// Deletes a secret. Providers that support it schedule the deletion after
+    /// `recovery_window_days` instead of deleting immediately.
+    #[allow(async_fn_in_trait)]
+    async fn delete_secret(&self, id: &str, _recovery_window_days: i64) -> Result<()> {
+        Err(anyhow!(
+            "Deleting secrets isn't supported, can't delete {}",
+            id
+        ))
+    }
+}
+
+impl<P: Provider> Provider for &P {
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+        (**self).provide_secrets(ids).await
+    }
+
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>> {
+        (**self).provide_secret_versions(ids).await
+    }
+
+    async fn create_secret(&self, id: &str, value: &str, options: &CreateOptions) -> Result<()> {
+        (**self).create_secret(id, value, options).await
+    }
+
+    async fn list_secrets(&self, prefix: &str) -> Result<Vec<String>> {
+        (**self).list_secrets(prefix).await
+    }
+
+    async fn list_secrets_by_path(&self, path: &str) -> Result<Vec<String>> {
+        (**self).list_secrets_by_path(path).await
+    }
+
+    async fn delete_secret(&self, id: &str, recovery_window_days: i64) -> Result<()> {
+        (**self).delete_secret(id, recovery_window_days).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_credential_sources() {
+        assert_eq!(AwsOptions::default().effective_credential_sources(), None);
+
+        let no_imds = AwsOptions {
+            no_imds: true,
+            ..Default::default()
+        };
+        let sources = no_imds.effective_credential_sources().unwrap();
+        assert!(!sources.contains(&CredentialSource::Imds));
+        assert_eq!(sources[0], CredentialSource::Env);
+
+        let explicit = AwsOptions {
+            credential_sources: Some(vec![CredentialSource::WebIdentity, CredentialSource::Imds]),
+            no_imds: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            explicit.effective_credential_sources(),
+            Some(vec![CredentialSource::WebIdentity])
+        );
+    }
+
+    #[test]
+    fn test_annotates_requests_with_context() {
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            AwsOptions::default().app_name(),
+            format!("awsm-env-{}", version)
+        );
+        assert_eq!(AwsOptions::default().role_session_name(), "awsm-env");
+
+        let options = AwsOptions {
+            context: Some("ci-job-123".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.app_name(),
+            format!("awsm-env-{}#ci-job-123", version)
+        );
+        assert_eq!(options.role_session_name(), "awsm-env-ci-job-123");
+    }
 }
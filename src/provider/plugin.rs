@@ -0,0 +1,498 @@
+//! Providers implemented by external executables named `awsm-env-provider-<name>`, found
+//! on `PATH`. Each call spawns the executable, writes a JSON request to its stdin and
+//! reads a JSON response from its stdout. Plugins named `awsm-env-provider-<name>.wasm`
+//! are WASI modules run in a sandbox instead, with the `wasm-plugins` feature:
+//!
+//! ```json
+//! {"protocol":1,"action":"get","secrets":[{"id":"app/db","version":null,"stage":null}]}
+//! {"secrets":[{"id":"app/db","value":"...","version":"3","changed_at":"2026-01-01T00:00:00Z"}]}
+//! ```
+//!
+//! `version` and `changed_at`, an RFC 3339 timestamp of when the value last changed, are
+//! optional. A secret that can't be fetched is answered with `"access_denied":true` or
+//! `"error":"..."` in place of its value, leaving the other secrets of the batch intact.
+//!
+//! Other actions are `create` (`id`, `value`, `kms_key_id`, `tags`), `list` (`prefix`,
+//! answered with `{"ids":[...]}`) and `delete` (`id`, `recovery_window_days`). Secrets
+//! that don't exist are left out of `get` responses. Failures are reported with
+//! `{"error":"..."}` or a non-zero exit status, stderr is passed through.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+#[cfg(feature = "wasm-plugins")]
+use crate::provider::wasm_plugin;
+use crate::provider::{CreateOptions, Provider, SecretMetadata, SecretResult, SecretVersion};
+
+/// Prefix of plugin executable names, followed by the plugin's name
+pub const PLUGIN_PREFIX: &str = "awsm-env-provider-";
+
+/// Version of the protocol sent with every request
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    protocol: u32,
+    #[serde(flatten)]
+    request: Request<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Request<'a> {
+    Get {
+        secrets: Vec<SecretRequest>,
+    },
+    Create {
+        id: &'a str,
+        value: &'a str,
+        kms_key_id: Option<&'a str>,
+        tags: &'a IndexMap<String, String>,
+    },
+    List {
+        prefix: &'a str,
+    },
+    Delete {
+        id: &'a str,
+        recovery_window_days: i64,
+    },
+}
+
+#[derive(Serialize)]
+struct SecretRequest {
+    id: String,
+    version: Option<String>,
+    stage: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct Response {
+    #[serde(default)]
+    secrets: Vec<SecretResponse>,
+    #[serde(default)]
+    ids: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SecretResponse {
+    id: String,
+    value: Option<String>,
+    version: Option<String>,
+    changed_at: Option<String>,
+    /// The plugin isn't allowed to read this secret
+    #[serde(default)]
+    access_denied: bool,
+    /// Why fetching this secret failed
+    error: Option<String>,
+}
+
+/// A provider plugin, see the [module docs](self) for the protocol
+pub struct PluginProvider {
+    name: String,
+    path: PathBuf,
+}
+
+impl PluginProvider {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+        }
+    }
+
+    /// Finds the executable of the plugin called `name` on `PATH`
+    pub fn find(name: &str) -> Result<Self> {
+        discover_plugins()
+            .into_iter()
+            .find(|(found, _)| found == name)
+            .map(|(name, path)| Self::new(name, path))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Provider plugin '{}' not found, expected an executable named {}{} on PATH",
+                    name,
+                    PLUGIN_PREFIX,
+                    name
+                )
+            })
+    }
+
+    async fn call(&self, request: Request<'_>) -> Result<Response> {
+        let input = serde_json::to_vec(&Envelope {
+            protocol: PROTOCOL_VERSION,
+            request,
+        })?;
+
+        let output = match is_wasm(&self.path) {
+            true => self.run_wasm(input).await?,
+            false => self.run_process(input).await?,
+        };
+
+        let response: Response = serde_json::from_slice(&output)
+            .with_context(|| format!("Provider plugin '{}' sent an invalid response", self.name))?;
+
+        match response.error {
+            Some(error) => Err(anyhow!("Provider plugin '{}': {}", self.name, error)),
+            None => Ok(response),
+        }
+    }
+
+    async fn run_process(&self, input: Vec<u8>) -> Result<Vec<u8>> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to run provider plugin '{}'", self.name))?;
+
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        // Written while the output is read, a plugin may fill its stdout before reading
+        // all of the request
+        let write = async move {
+            // Plugins that don't need the request may exit before it is written
+            let _ = stdin.write_all(&input).await;
+            drop(stdin);
+        };
+
+        let ((), output) = tokio::join!(write, child.wait_with_output());
+        let output =
+            output.with_context(|| format!("Failed to run provider plugin '{}'", self.name))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Provider plugin '{}' failed with {}",
+                self.name,
+                output.status
+            ));
+        }
+
+        Ok(output.stdout)
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    async fn run_wasm(&self, input: Vec<u8>) -> Result<Vec<u8>> {
+        let path = self.path.clone();
+
+        tokio::task::spawn_blocking(move || wasm_plugin::run(&path, input))
+            .await?
+            .with_context(|| format!("Provider plugin '{}' failed", self.name))
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    async fn run_wasm(&self, _input: Vec<u8>) -> Result<Vec<u8>> {
+        Err(anyhow!(
+            "Provider plugin '{}' is a WASM module, which requires the wasm-plugins feature",
+            self.name
+        ))
+    }
+
+    async fn get(&self, secrets: Vec<SecretRequest>) -> Result<Vec<SecretResult>> {
+        let ids = secrets
+            .iter()
+            .map(|secret| secret.id.clone())
+            .collect::<Vec<_>>();
+        let mut response = self.call(Request::Get { secrets }).await?;
+
+        ids.into_iter()
+            .map(|id| {
+                let Some(idx) = response.secrets.iter().position(|secret| secret.id == id) else {
+                    return Ok(SecretResult::NotFound);
+                };
+                let secret = response.secrets.swap_remove(idx);
+
+                if secret.access_denied {
+                    return Ok(SecretResult::AccessDenied);
+                }
+                if let Some(error) = secret.error {
+                    return Ok(SecretResult::Error(anyhow!(
+                        "Provider plugin '{}': {}",
+                        self.name,
+                        error
+                    )));
+                }
+                let value = secret.value.ok_or_else(|| {
+                    anyhow!(
+                        "Provider plugin '{}' returned no value for {}",
+                        self.name,
+                        secret.id
+                    )
+                })?;
+
+                let changed_at = secret
+                    .changed_at
+                    .as_deref()
+                    .map(humantime::parse_rfc3339)
+                    .transpose()
+                    .with_context(|| {
+                        format!(
+                            "Provider plugin '{}' returned an invalid changed_at for {}",
+                            self.name, secret.id
+                        )
+                    })?;
+
+                Ok(SecretResult::Found {
+                    value,
+                    version: secret.version,
+                    metadata: SecretMetadata { changed_at },
+                })
+            })
+            .collect()
+    }
+}
+
+impl Provider for PluginProvider {
+    async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+        let secrets = ids
+            .into_iter()
+            .map(|id| SecretRequest {
+                id,
+                version: None,
+                stage: None,
+            })
+            .collect();
+
+        self.get(secrets).await
+    }
+
+    async fn provide_secret_versions(
+        &self,
+        ids: Vec<(String, SecretVersion)>,
+    ) -> Result<Vec<SecretResult>> {
+        let secrets = ids
+            .into_iter()
+            .map(|(id, version)| match version {
+                SecretVersion::Id(version) => SecretRequest {
+                    id,
+                    version: Some(version),
+                    stage: None,
+                },
+                SecretVersion::Stage(stage) => SecretRequest {
+                    id,
+                    version: None,
+                    stage: Some(stage),
+                },
+            })
+            .collect();
+
+        self.get(secrets).await
+    }
+
+    async fn create_secret(&self, id: &str, value: &str, options: &CreateOptions) -> Result<()> {
+        self.call(Request::Create {
+            id,
+            value,
+            kms_key_id: options.kms_key_id.as_deref(),
+            tags: &options.tags,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_secrets(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.call(Request::List { prefix }).await?.ids)
+    }
+
+    async fn delete_secret(&self, id: &str, recovery_window_days: i64) -> Result<()> {
+        self.call(Request::Delete {
+            id,
+            recovery_window_days,
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Lists the plugins on `PATH` by name, with the path of their executable. Earlier
+/// `PATH` entries take precedence.
+pub fn discover_plugins() -> Vec<(String, PathBuf)> {
+    match env::var_os("PATH") {
+        Some(path) => discover_in(env::split_paths(&path)),
+        None => Vec::new(),
+    }
+}
+
+fn discover_in(dirs: impl Iterator<Item = PathBuf>) -> Vec<(String, PathBuf)> {
+    let mut plugins: IndexMap<String, PathBuf> = IndexMap::new();
+
+    for dir in dirs {
+        let Ok(entries) = dir.read_dir() else {
+            continue;
+        };
+
+        let mut found = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_executable(path))
+            .filter_map(|path| Some((plugin_name(&path)?, path)))
+            .collect::<Vec<_>>();
+        found.sort();
+
+        for (name, path) in found {
+            plugins.entry(name).or_insert(path);
+        }
+    }
+
+    plugins.into_iter().collect()
+}
+
+fn plugin_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = file_name.strip_prefix(PLUGIN_PREFIX)?;
+    let name = name
+        .strip_suffix(".wasm")
+        .or_else(|| name.strip_suffix(env::consts::EXE_SUFFIX))
+        .unwrap_or(name);
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn is_wasm(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "wasm")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata().is_ok_and(|metadata| {
+        metadata.is_file() && (is_wasm(path) || metadata.permissions().mode() & 0o111 != 0)
+    })
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, process, time::Duration};
+
+    use super::*;
+
+    fn write_plugin(dir: &Path, name: &str, script: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(format!("{}{}", PLUGIN_PREFIX, name));
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_resolves_secrets_with_plugin() {
+        let dir = env::temp_dir().join(format!("awsm-env-plugin-{}", process::id()));
+        let path = write_plugin(
+            &dir,
+            "echo",
+            r#"grep -q '"action":"get"' && echo '{"secrets":[{"id":"app/db","value":"hunter2","version":"3","changed_at":"2026-01-01T00:00:00Z"},{"id":"app/admin","access_denied":true},{"id":"app/flaky","error":"throttled"}]}'"#,
+        );
+
+        let plugin = PluginProvider::new("echo", path);
+        let ids = ["app/db", "app/missing", "app/admin", "app/flaky"];
+        let secrets = plugin
+            .provide_secrets(ids.iter().map(|id| id.to_string()).collect())
+            .await
+            .unwrap();
+
+        let [
+            SecretResult::Found {
+                value,
+                version,
+                metadata,
+            },
+            SecretResult::NotFound,
+            SecretResult::AccessDenied,
+            SecretResult::Error(err),
+        ] = secrets.as_slice()
+        else {
+            panic!("Unexpected results: {:?}", secrets);
+        };
+        assert_eq!(err.to_string(), "Provider plugin 'echo': throttled");
+        assert_eq!(value, "hunter2");
+        assert_eq!(version.as_deref(), Some("3"));
+        assert_eq!(
+            metadata.changed_at,
+            Some(humantime::parse_rfc3339("2026-01-01T00:00:00Z").unwrap())
+        );
+
+        let failing = write_plugin(
+            &dir,
+            "failing",
+            r#"cat >/dev/null; echo '{"error":"denied"}'"#,
+        );
+        let err = PluginProvider::new("failing", failing)
+            .list_secrets("app/")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Provider plugin 'failing': denied");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_answering_before_reading_request() {
+        let dir = env::temp_dir().join(format!("awsm-env-plugin-early-{}", process::id()));
+        // Fills the stdout pipe before reading a request larger than the stdin pipe
+        let path = write_plugin(
+            &dir,
+            "early",
+            r#"head -c 200000 /dev/zero | tr '\0' ' '; echo '{"ids":["app/db"]}'; cat >/dev/null"#,
+        );
+
+        let plugin = PluginProvider::new("early", path);
+        let prefix = "a".repeat(200_000);
+        let ids = tokio::time::timeout(Duration::from_secs(10), plugin.list_secrets(&prefix))
+            .await
+            .expect("the plugin call shouldn't deadlock")
+            .unwrap();
+        assert_eq!(ids, ["app/db"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_discovers_plugins() {
+        let root = env::temp_dir().join(format!("awsm-env-plugins-{}", process::id()));
+        let first = root.join("first");
+        let second = root.join("second");
+
+        write_plugin(&first, "vault", "true");
+        write_plugin(&second, "vault", "true");
+        write_plugin(&second, "onepassword", "true");
+        fs::write(second.join(format!("{}not-executable", PLUGIN_PREFIX)), "").unwrap();
+        fs::write(second.join(format!("{}keyring.wasm", PLUGIN_PREFIX)), "").unwrap();
+
+        let plugins = discover_in([first.clone(), second.clone()].into_iter());
+
+        assert_eq!(
+            plugins,
+            vec![
+                (
+                    "vault".to_string(),
+                    first.join(format!("{}vault", PLUGIN_PREFIX))
+                ),
+                (
+                    "keyring".to_string(),
+                    second.join(format!("{}keyring.wasm", PLUGIN_PREFIX))
+                ),
+                (
+                    "onepassword".to_string(),
+                    second.join(format!("{}onepassword", PLUGIN_PREFIX))
+                ),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,117 @@
+//! Runs provider plugins compiled to WASI modules. Modules speak the same protocol as
+//! executable plugins over stdin and stdout, but run without access to the filesystem,
+//! network, environment or other processes, and with a bounded amount of work.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use wasmtime::{Config, Engine, Linker, Module, Store};
+use wasmtime_wasi::{
+    I32Exit, WasiCtxBuilder,
+    pipe::{MemoryInputPipe, MemoryOutputPipe},
+    preview1::{self, WasiP1Ctx},
+};
+
+/// Instructions a plugin may execute per call before it's stopped
+const FUEL: u64 = 1_000_000_000;
+
+/// Most bytes a plugin may write to stdout per call
+const MAX_OUTPUT: usize = 16 * 1024 * 1024;
+
+/// Runs the module at `path` with `input` on stdin, returning what it wrote to stdout
+pub fn run(path: &Path, input: Vec<u8>) -> Result<Vec<u8>> {
+    let engine = engine()?;
+    let module = Module::from_file(&engine, path)
+        .with_context(|| format!("Failed to load {}", path.display()))?;
+
+    run_module(&engine, &module, input)
+}
+
+fn engine() -> Result<Engine> {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+
+    Engine::new(&config)
+}
+
+fn run_module(engine: &Engine, module: &Module, input: Vec<u8>) -> Result<Vec<u8>> {
+    let mut linker: Linker<WasiP1Ctx> = Linker::new(engine);
+    preview1::add_to_linker_sync(&mut linker, |ctx| ctx)?;
+
+    let stdout = MemoryOutputPipe::new(MAX_OUTPUT);
+    let ctx = WasiCtxBuilder::new()
+        .stdin(MemoryInputPipe::new(input))
+        .stdout(stdout.clone())
+        .inherit_stderr()
+        .build_p1();
+
+    let mut store = Store::new(engine, ctx);
+    store.set_fuel(FUEL)?;
+
+    let instance = linker.instantiate(&mut store, module)?;
+    let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+
+    match start.call(&mut store, ()) {
+        Ok(()) => {}
+        Err(err) => match err.downcast_ref::<I32Exit>() {
+            Some(I32Exit(0)) => {}
+            Some(I32Exit(code)) => return Err(anyhow!("Exited with status {}", code)),
+            None => return Err(err),
+        },
+    }
+
+    drop(store);
+    Ok(stdout.contents().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module that ignores its input and prints `output`
+    fn module(engine: &Engine, output: &str, exit_code: i32) -> Module {
+        let data = output.replace('\\', "\\\\").replace('"', "\\\"");
+        let wat = format!(
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "{}")
+                (func (export "_start")
+                    (i32.store (i32.const 0) (i32.const 16))
+                    (i32.store (i32.const 4) (i32.const {}))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+                    (call $proc_exit (i32.const {}))))"#,
+            data,
+            output.len(),
+            exit_code
+        );
+
+        Module::new(engine, wat).unwrap()
+    }
+
+    #[test]
+    fn test_runs_modules() {
+        let engine = engine().unwrap();
+        let response = r#"{"secrets":[{"id":"app/db","value":"hunter2"}]}"#;
+
+        let output = run_module(&engine, &module(&engine, response, 0), b"{}".to_vec()).unwrap();
+        assert_eq!(output, response.as_bytes());
+
+        let err = run_module(&engine, &module(&engine, "", 3), Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Exited with status 3");
+    }
+
+    #[test]
+    fn test_stops_runaway_modules() {
+        let engine = engine().unwrap();
+        let module = Module::new(
+            &engine,
+            r#"(module (func (export "_start") (loop $spin (br $spin))))"#,
+        )
+        .unwrap();
+
+        assert!(run_module(&engine, &module, Vec::new()).is_err());
+    }
+}
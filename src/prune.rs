@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{
+    provider::{
+        AwsOptions, AwsParameterStoreProvider, AwsSecretsManagerProvider, PluginProvider, Provider,
+    },
+    resolve::replace_placeholders,
+    variable::{ProviderConfig, Variables},
+};
+
+/// Lists remote secrets whose names start with `prefix` that no entry in
+/// `variables` references
+pub async fn find_orphans(
+    variables: &Variables,
+    placeholders: &IndexMap<String, String>,
+    prefix: &str,
+    aws: &AwsOptions,
+) -> Result<Vec<ProviderConfig>> {
+    let aws_sm = AwsSecretsManagerProvider::new(aws).await?;
+    let aws_ps = AwsParameterStoreProvider::new(aws).await?;
+
+    let mut remote = aws_sm
+        .list_secrets(prefix)
+        .await?
+        .into_iter()
+        .map(ProviderConfig::AwsSecretsManager)
+        .chain(
+            aws_ps
+                .list_secrets(prefix)
+                .await?
+                .into_iter()
+                .map(ProviderConfig::AwsParameterStore),
+        )
+        .collect::<Vec<_>>();
+
+    // Plugins are only listed when the spec uses them
+    let plugins = variables
+        .iter()
+        .filter_map(|var| match &var.provider_config {
+            Some(ProviderConfig::Plugin { name, .. }) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<IndexSet<_>>();
+
+    for name in plugins {
+        let ids = PluginProvider::find(name)?.list_secrets(prefix).await?;
+        remote.extend(ids.into_iter().map(|id| ProviderConfig::Plugin {
+            name: name.to_string(),
+            id,
+        }));
+    }
+
+    unreferenced(variables, placeholders, remote.into_iter())
+}
+
+fn unreferenced(
+    variables: &Variables,
+    placeholders: &IndexMap<String, String>,
+    remote: impl Iterator<Item = ProviderConfig>,
+) -> Result<Vec<ProviderConfig>> {
+    let referenced = variables
+        .iter()
+        .filter_map(|var| var.provider_config.as_ref())
+        .map(|config| {
+            let id = replace_placeholders(config.id(), placeholders)?.into_owned();
+            Ok(config.with_id(id))
+        })
+        .collect::<Result<HashSet<_>>>()?;
+
+    Ok(remote
+        .filter(|config| !referenced.contains(config))
+        .collect())
+}
+
+/// Deletes each of the `orphans`, Secrets Manager secrets can be restored
+/// until `recovery_window_days` have passed
+pub async fn delete_orphans(
+    orphans: &[ProviderConfig],
+    recovery_window_days: i64,
+    aws: &AwsOptions,
+) -> Result<()> {
+    let aws_sm = AwsSecretsManagerProvider::new(aws).await?;
+    let aws_ps = AwsParameterStoreProvider::new(aws).await?;
+
+    for orphan in orphans {
+        match orphan {
+            ProviderConfig::AwsSecretsManager(id) => {
+                aws_sm.delete_secret(id, recovery_window_days).await?
+            }
+            ProviderConfig::AwsParameterStore(id) => {
+                aws_ps.delete_secret(id, recovery_window_days).await?
+            }
+            ProviderConfig::Plugin { name, id } => {
+                PluginProvider::find(name)?
+                    .delete_secret(id, recovery_window_days)
+                    .await?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variable::Variable;
+
+    use super::*;
+
+    #[test]
+    fn test_unreferenced() {
+        let variables: Variables = vec![
+            Variable {
+                key: "KEY1".to_string(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager("$env/key1".to_string())),
+                ..Default::default()
+            },
+            Variable {
+                key: "KEY2".to_string(),
+                provider_config: Some(ProviderConfig::AwsParameterStore("$env/key2".to_string())),
+                ..Default::default()
+            },
+        ]
+        .into();
+        let placeholders: IndexMap<_, _> = [("env".to_string(), "prod".to_string())].into();
+
+        let remote = vec![
+            ProviderConfig::AwsSecretsManager("prod/key1".to_string()),
+            ProviderConfig::AwsSecretsManager("prod/key2".to_string()),
+            ProviderConfig::AwsParameterStore("prod/key2".to_string()),
+            ProviderConfig::AwsParameterStore("prod/old".to_string()),
+        ];
+
+        let result = unreferenced(&variables, &placeholders, remote.into_iter()).unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                ProviderConfig::AwsSecretsManager("prod/key2".to_string()),
+                ProviderConfig::AwsParameterStore("prod/old".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Fetching files from `https://` and `s3://` URLs, such as specs and base configs shared
+//! by every repository in an organization
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, anyhow};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    provider::{AwsOptions, get_object, load_config},
+    term,
+};
+
+/// Whether `location` is a URL that [`Fetcher`] supports rather than a path
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("s3://")
+}
+
+/// Downloads files from URLs, going through the same proxy and trusting the same CA
+/// bundle as AWS requests. `s3://` URLs are fetched with the same AWS options.
+#[derive(Default)]
+pub struct Fetcher {
+    client: reqwest::Client,
+    aws: AwsOptions,
+}
+
+impl Fetcher {
+    /// Builds the HTTP client used for every `https://` download from `aws`
+    pub fn new(aws: &AwsOptions) -> Result<Self> {
+        let mut builder = reqwest::Client::builder();
+
+        if let Some(url) = &aws.proxy {
+            let proxy = reqwest::Proxy::all(url)
+                .with_context(|| format!("Invalid proxy URL {}", url))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(pem) = &aws.ca_bundle {
+            for certificate in
+                reqwest::Certificate::from_pem_bundle(pem).context("Invalid CA bundle")?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(Self {
+            client: builder
+                .build()
+                .context("Failed to create the HTTP client")?,
+            aws: aws.clone(),
+        })
+    }
+
+    /// Downloads `url`, using AWS credentials for `s3://bucket/key` URLs
+    pub async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        if let Some(location) = url.strip_prefix("s3://") {
+            let (bucket, key) = location
+                .split_once('/')
+                .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+                .ok_or_else(|| anyhow!("Expected s3://<bucket>/<key> but found {}", url))?;
+
+            let fetched = async { get_object(&load_config(&self.aws).await?, bucket, key).await };
+            return fetched
+                .await
+                .with_context(|| format!("Failed to fetch {}", url));
+        }
+
+        if !url.starts_with("https://") {
+            return Err(anyhow!("Unsupported URL {}, use https:// or s3://", url));
+        }
+
+        let fetched = async {
+            let response = self.client.get(url).send().await?.error_for_status()?;
+            Ok::<_, reqwest::Error>(response.bytes().await?.to_vec())
+        };
+
+        fetched
+            .await
+            .with_context(|| format!("Failed to fetch {}", url))
+    }
+
+    /// Like [`Fetcher::fetch`], but reuses a copy kept in the user's cache directory for
+    /// `ttl`. The cached copy is also used, with a warning, when fetching fails so that
+    /// working offline or during an outage still succeeds.
+    pub async fn fetch_cached(&self, url: &str, ttl: Duration) -> Result<Vec<u8>> {
+        let path = cache_path(url);
+        let cached = fs::read(&path).ok();
+        let age = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+        if let Some(contents) = &cached
+            && age.is_some_and(|age| age < ttl)
+        {
+            return Ok(contents.clone());
+        }
+
+        match self.fetch(url).await {
+            Ok(contents) => {
+                // Caching is only an optimization, so failing to write is ignored
+                let _ = path
+                    .parent()
+                    .map(fs::create_dir_all)
+                    .transpose()
+                    .and_then(|_| fs::write(&path, &contents));
+                Ok(contents)
+            }
+            Err(error) => match cached {
+                Some(contents) => {
+                    term::warn(format!("{:#}, using the copy cached earlier", error));
+                    Ok(contents)
+                }
+                None => Err(error),
+            },
+        }
+    }
+}
+
+/// File the copy of `url` is cached in, named after its hash
+fn cache_path(url: &str) -> PathBuf {
+    let hash = Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    cache_dir().join("awsm-env").join("remote").join(hash)
+}
+
+fn cache_dir() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(env::temp_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejects_unsupported_urls() {
+        assert!(is_url("s3://bucket/config.toml"));
+        assert!(!is_url("http://example.com/config.toml"));
+        assert!(!is_url("configs/base.toml"));
+
+        let fetcher = Fetcher::default();
+        let err = fetcher
+            .fetch("http://example.com/config.toml")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unsupported URL"));
+        let err = fetcher.fetch("s3://bucket").await.unwrap_err();
+        assert!(err.to_string().contains("s3://<bucket>/<key>"));
+    }
+
+    #[test]
+    fn test_applies_aws_options() {
+        let aws = AwsOptions {
+            proxy: Some("http://proxy.internal:3128".to_string()),
+            ..Default::default()
+        };
+        assert!(Fetcher::new(&aws).is_ok());
+
+        let aws = AwsOptions {
+            ca_bundle: Some(
+                b"-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n".to_vec(),
+            ),
+            ..Default::default()
+        };
+        let Err(err) = Fetcher::new(&aws) else {
+            panic!("expected an invalid CA bundle to be rejected");
+        };
+        assert_eq!(err.to_string(), "Invalid CA bundle");
+    }
+}
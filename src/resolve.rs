@@ -1,23 +1,34 @@
-use std::sync::OnceLock;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    slice,
+    time::{Duration, SystemTime},
+};
 
-use anyhow::{Error, Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use indexmap::IndexMap;
 use itertools::Itertools;
-use regex::Regex;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
+    audit::{AuditOutcome, AuditRecord, AuditSink},
     cli::MergeMode,
-    provider::{AwsParameterStoreProvider, AwsSecretsManagerProvider, Provider},
-    variable::{ProviderConfig, Variables},
+    hooks::PlaceholderHook,
+    lock::Lockfile,
+    provider::{
+        AwsOptions, AwsParameterStoreProvider, AwsSecretsManagerProvider, CachedProvider,
+        PluginProvider, Provider, SdkConfig, SecretMetadata, SecretResult, SecretVersion,
+        caller_identity, load_config, request_cost,
+    },
+    variable::{Provenance, ProviderConfig, Variable, Variables},
 };
 
-static RE_PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
-static MARKER: &str = "\u{FFFF}ESCAPED\u{FFFF}";
-
 #[derive(Eq, PartialEq, Hash)]
 enum ProviderKind {
     AwsSecretsManager,
     AwsParameterStore,
+    Plugin(String),
 }
 
 impl From<&ProviderConfig> for ProviderKind {
@@ -25,61 +36,881 @@ impl From<&ProviderConfig> for ProviderKind {
         match value {
             ProviderConfig::AwsSecretsManager(_) => ProviderKind::AwsSecretsManager,
             ProviderConfig::AwsParameterStore(_) => ProviderKind::AwsParameterStore,
+            ProviderConfig::Plugin { name, .. } => ProviderKind::Plugin(name.clone()),
         }
     }
 }
 
+/// Which versions of secrets to fetch
+pub enum Pinning<'a> {
+    Latest,
+    /// The versions recorded in a lockfile
+    Locked(&'a Lockfile),
+    /// Secrets Manager secrets at a version stage, Parameter Store values as usual
+    Stage(&'a str),
+}
+
+/// How secrets are fetched and failures to fetch them handled
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchPolicy {
+    /// Longest a single provider request may take before it fails
+    pub timeout: Option<Duration>,
+    /// Keep going when a request fails. The entries it was for are fetched one at a time
+    /// so a single bad secret doesn't fail the others, the ones that still fail are
+    /// returned instead of an error.
+    pub best_effort: bool,
+    /// Fetch entries with a `@mirror` from whichever copy is cheaper to request rather
+    /// than the declared one first
+    pub prefer_cheaper_mirror: bool,
+}
+
+/// An entry whose secret couldn't be fetched by a best-effort resolution
+#[derive(Debug)]
+pub struct FetchFailure {
+    pub key: String,
+    pub error: anyhow::Error,
+}
+
+/// Resolves specs many times with the same providers, e.g. in a service that refreshes
+/// its configuration periodically. The AWS configuration is loaded and credentials are
+/// discovered once rather than on every resolution, and secrets found can be reused for
+/// a while with [`Resolver::with_cache_ttl`].
+pub struct Resolver<SM = AwsSecretsManagerProvider, PS = AwsParameterStoreProvider> {
+    aws_sm: CachedProvider<SM>,
+    aws_ps: CachedProvider<PS>,
+    policy: FetchPolicy,
+    /// Configuration the AWS providers were created from, `None` with other providers
+    aws_config: Option<SdkConfig>,
+    /// Caller's identity put in audit records, looked up on the first audited resolution
+    identity: OnceCell<Option<String>>,
+}
+
+impl Resolver {
+    /// Loads the AWS configuration with `options` and creates both AWS providers from it
+    pub async fn new(options: &AwsOptions) -> Result<Self> {
+        let config = load_config(options).await?;
+        let batching = options.batching;
+
+        Ok(Self {
+            aws_config: Some(config.clone()),
+            ..Self::with_providers(
+                AwsSecretsManagerProvider::from_config(&config).with_batching(batching),
+                AwsParameterStoreProvider::from_config(&config).with_batching(batching),
+            )
+        })
+    }
+}
+
+impl<SM: Provider, PS: Provider> Resolver<SM, PS> {
+    /// Fetches from the given providers instead of creating the AWS ones
+    pub fn with_providers(secrets_manager: SM, parameter_store: PS) -> Self {
+        Self {
+            aws_sm: CachedProvider::new(secrets_manager, Duration::ZERO),
+            aws_ps: CachedProvider::new(parameter_store, Duration::ZERO),
+            policy: FetchPolicy::default(),
+            aws_config: None,
+            identity: OnceCell::new(),
+        }
+    }
+
+    /// Reuses secrets found within `ttl` instead of fetching them again
+    pub fn with_cache_ttl(self, ttl: Duration) -> Self {
+        Self {
+            aws_sm: CachedProvider::new(self.aws_sm.into_inner(), ttl),
+            aws_ps: CachedProvider::new(self.aws_ps.into_inner(), ttl),
+            ..self
+        }
+    }
+
+    /// Handles failures according to `policy` instead of failing on the first one
+    pub fn with_policy(mut self, policy: FetchPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Fetches the latest versions of secrets using this resolver's providers and cache.
+    /// When the policy is best effort, returns the entries that couldn't be fetched.
+    pub async fn resolve(
+        &self,
+        variables: &mut Variables,
+        placeholders: IndexMap<String, String>,
+    ) -> Result<Vec<FetchFailure>> {
+        self.resolve_pinned(variables, placeholders, Pinning::Latest)
+            .await
+    }
+
+    /// Like [`Resolver::resolve`] but fetches the versions selected by `pinning`, which
+    /// are never cached
+    pub async fn resolve_pinned(
+        &self,
+        variables: &mut Variables,
+        placeholders: IndexMap<String, String>,
+        pinning: Pinning<'_>,
+    ) -> Result<Vec<FetchFailure>> {
+        resolve_using(
+            variables,
+            placeholders,
+            pinning,
+            self.policy,
+            &self.aws_sm,
+            &self.aws_ps,
+            None,
+        )
+        .await
+    }
+
+    /// Like [`Resolver::resolve_pinned`] but passes a record of every secret access to
+    /// `audit`. Records of resolvers created with [`Resolver::new`] include the caller's
+    /// identity, which takes an extra request the first time.
+    pub async fn resolve_audited(
+        &self,
+        variables: &mut Variables,
+        placeholders: IndexMap<String, String>,
+        pinning: Pinning<'_>,
+        audit: &dyn AuditSink,
+    ) -> Result<Vec<FetchFailure>> {
+        let audit = Audit {
+            sink: audit,
+            identity: self.identity().await,
+        };
+
+        resolve_using(
+            variables,
+            placeholders,
+            pinning,
+            self.policy,
+            &self.aws_sm,
+            &self.aws_ps,
+            Some(&audit),
+        )
+        .await
+    }
+
+    /// Forgets every cached secret, e.g. after they were rotated
+    pub fn clear_cache(&self) {
+        self.aws_sm.clear();
+        self.aws_ps.clear();
+    }
+
+    async fn identity(&self) -> Option<String> {
+        let Some(config) = &self.aws_config else {
+            return None;
+        };
+
+        self.identity
+            // Not knowing who made the requests shouldn't stop them
+            .get_or_init(|| async { caller_identity(config).await.ok() })
+            .await
+            .clone()
+    }
+}
+
+/// An [`AuditSink`] along with the identity put in its records
+struct Audit<'a> {
+    sink: &'a dyn AuditSink,
+    identity: Option<String>,
+}
+
+impl Audit<'_> {
+    fn record(
+        &self,
+        key: &str,
+        config: &ProviderConfig,
+        id: &str,
+        outcome: AuditOutcome,
+    ) -> AuditRecord {
+        let mut record = AuditRecord::new(key, config.name(), id, outcome);
+        record.identity = self.identity.clone();
+        record
+    }
+}
+
+/// Fetches the secrets of `variables` with the default [`AwsOptions`], use a [`Resolver`]
+/// for others
 pub async fn resolve(
     variables: &mut Variables,
     placeholders: IndexMap<String, String>,
 ) -> Result<()> {
-    let groups = variables
-        .iter_mut()
-        .into_group_map_by(|v| v.provider_config.as_ref().map(ProviderKind::from));
+    resolve_strict(variables, placeholders, Pinning::Latest, None).await
+}
+
+/// Like [`resolve`] but fetches the exact secret versions recorded in `lockfile`
+pub async fn resolve_locked(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    lockfile: &Lockfile,
+) -> Result<()> {
+    resolve_strict(variables, placeholders, Pinning::Locked(lockfile), None).await
+}
+
+/// Like [`resolve`] but fetches Secrets Manager secrets at the given version
+/// stage, e.g. `AWSPREVIOUS`. Parameter Store values are fetched as usual.
+pub async fn resolve_at_stage(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    stage: &str,
+) -> Result<()> {
+    resolve_strict(variables, placeholders, Pinning::Stage(stage), None).await
+}
+
+/// Like [`resolve`] but fetches the versions selected by `pinning`
+pub async fn resolve_pinned(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    pinning: Pinning<'_>,
+) -> Result<()> {
+    resolve_strict(variables, placeholders, pinning, None).await
+}
+
+/// Like [`resolve_pinned`] but passes a record of every secret access to `audit`, including
+/// the caller's identity, which takes an extra request to determine
+pub async fn resolve_audited(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    pinning: Pinning<'_>,
+    audit: &impl AuditSink,
+) -> Result<()> {
+    resolve_strict(variables, placeholders, pinning, Some(audit)).await
+}
+
+/// Like [`resolve_pinned`] but handles failures according to `policy`, returning the
+/// entries that couldn't be fetched when it's best effort. Accesses are recorded to
+/// `audit` like [`resolve_audited`] does.
+pub async fn resolve_with_policy(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    pinning: Pinning<'_>,
+    policy: FetchPolicy,
+    audit: Option<&dyn AuditSink>,
+) -> Result<Vec<FetchFailure>> {
+    // Specs used as plain dotenv templates never load the AWS configuration, which can
+    // take seconds when it falls back to instance metadata
+    if !variables.has_secrets() {
+        return Ok(Vec::new());
+    }
+
+    let resolver = Resolver::new(&AwsOptions::default())
+        .await?
+        .with_policy(policy);
+
+    match audit {
+        Some(audit) => {
+            resolver
+                .resolve_audited(variables, placeholders, pinning, audit)
+                .await
+        }
+        None => {
+            resolver
+                .resolve_pinned(variables, placeholders, pinning)
+                .await
+        }
+    }
+}
+
+/// Like [`resolve`] but gives up as soon as `token` is cancelled. In-flight requests are
+/// dropped and `variables` are left untouched. Dropping the returned future has the same effect.
+pub async fn resolve_cancellable(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    token: &CancellationToken,
+) -> Result<()> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(anyhow!("Resolution was cancelled")),
+        result = resolve(variables, placeholders) => result,
+    }
+}
+
+/// Like [`resolve`] but fetches from the given providers instead of AWS, e.g. to test
+/// or benchmark code that resolves a spec
+pub async fn resolve_with_providers(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    secrets_manager: &impl Provider,
+    parameter_store: &impl Provider,
+) -> Result<()> {
+    Resolver::with_providers(secrets_manager, parameter_store)
+        .resolve(variables, placeholders)
+        .await?;
+
+    Ok(())
+}
+
+async fn resolve_strict(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    pinning: Pinning<'_>,
+    audit: Option<&dyn AuditSink>,
+) -> Result<()> {
+    resolve_with_policy(
+        variables,
+        placeholders,
+        pinning,
+        FetchPolicy::default(),
+        audit,
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn resolve_using(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    pinning: Pinning<'_>,
+    policy: FetchPolicy,
+    aws_sm: &impl Provider,
+    aws_ps: &impl Provider,
+    audit: Option<&Audit<'_>>,
+) -> Result<Vec<FetchFailure>> {
+    if !variables.iter().any(|var| var.ps_path.is_some()) {
+        return fetch_all(
+            variables,
+            placeholders,
+            pinning,
+            policy,
+            aws_sm,
+            aws_ps,
+            audit,
+        )
+        .await;
+    }
+
+    // Expanded entries only replace the spec's once they've been fetched
+    let mut expanded = expand_paths(variables, &placeholders, aws_ps).await?;
+    let failures = fetch_all(
+        &mut expanded,
+        placeholders,
+        pinning,
+        policy,
+        aws_sm,
+        aws_ps,
+        audit,
+    )
+    .await?;
+    *variables = expanded;
+
+    Ok(failures)
+}
+
+/// Replaces entries declared with `@aws-ps-path` by an entry for every parameter under
+/// their path. Keys declared in the spec take precedence over expanded ones.
+async fn expand_paths(
+    variables: &Variables,
+    placeholders: &IndexMap<String, String>,
+    aws_ps: &impl Provider,
+) -> Result<Variables> {
+    let mut expanded: Vec<Variable> = Vec::new();
+
+    for var in variables.iter() {
+        let Some(path) = &var.ps_path else {
+            expanded.push(var.clone());
+            continue;
+        };
+
+        let path = replace_placeholders(path, placeholders)?;
+        let names = aws_ps
+            .list_secrets_by_path(&path)
+            .await
+            .with_context(|| format!("Failed to expand {} for {}", path, var.key))?;
+
+        if names.is_empty() && var.required {
+            return Err(anyhow!(
+                "No parameters found under {} for {}",
+                path,
+                var.key
+            ));
+        }
+
+        for name in names {
+            let key = path_key(&var.key, &path, &name);
+            if variables.find_by_key(&key).is_some() || expanded.iter().any(|v| v.key == key) {
+                continue;
+            }
+
+            expanded.push(Variable {
+                key,
+                default: None,
+                provider_config: Some(ProviderConfig::AwsParameterStore(name)),
+                ps_path: None,
+                ..var.clone()
+            });
+        }
+    }
+
+    Ok(expanded.into())
+}
+
+/// Key of the parameter `name` found under `path`, `prefix` followed by the segments
+/// below the path, e.g. `APP_DB_PASSWORD` for `/app/prod/db/password` under `/app/prod/`
+fn path_key(prefix: &str, path: &str, name: &str) -> String {
+    let segments = name
+        .strip_prefix(path)
+        .unwrap_or(name)
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            segment
+                .chars()
+                .map(|c| match c.is_ascii_alphanumeric() {
+                    true => c.to_ascii_uppercase(),
+                    false => '_',
+                })
+                .collect::<String>()
+        });
+
+    std::iter::once(prefix.to_string())
+        .chain(segments)
+        .join("_")
+}
+
+async fn fetch_all(
+    variables: &mut Variables,
+    placeholders: IndexMap<String, String>,
+    pinning: Pinning<'_>,
+    policy: FetchPolicy,
+    aws_sm: &impl Provider,
+    aws_ps: &impl Provider,
+    audit: Option<&Audit<'_>>,
+) -> Result<Vec<FetchFailure>> {
+    // Locked versions are those of the declared provider, so mirrors don't apply to them
+    let use_mirrors = !matches!(pinning, Pinning::Locked(_));
+    let mut entries = Vec::new();
+    let mut fallbacks = HashMap::new();
+
+    for (idx, var) in variables.iter().enumerate() {
+        let Some(config) = &var.provider_config else {
+            continue;
+        };
+
+        match var.mirror.as_ref().filter(|_| use_mirrors) {
+            Some(mirror) if policy.prefer_cheaper_mirror && is_cheaper(mirror, config) => {
+                entries.push((idx, mirror));
+                fallbacks.insert(idx, config);
+            }
+            Some(mirror) => {
+                entries.push((idx, config));
+                fallbacks.insert(idx, mirror);
+            }
+            None => entries.push((idx, config)),
+        }
+    }
+
+    let fetcher = Fetcher {
+        variables,
+        placeholders: &placeholders,
+        pinning: &pinning,
+        policy,
+        aws_sm,
+        aws_ps,
+        audit,
+    };
+    let mut updates = HashMap::new();
+    let mut failures = fetcher
+        .fetch_entries(&entries, |idx| fallbacks.contains_key(&idx), &mut updates)
+        .await?;
 
-    let aws_sm = AwsSecretsManagerProvider::new().await;
-    let aws_ps = AwsParameterStoreProvider::new().await;
+    // Entries the first provider had no value for, or failed to fetch, are fetched from
+    // their mirror
+    let mirrored = entries
+        .iter()
+        .filter(|(idx, _)| !updates.contains_key(idx))
+        .filter_map(|(idx, _)| Some((*idx, *fallbacks.get(idx)?)))
+        .collect::<Vec<_>>();
+
+    if !mirrored.is_empty() {
+        let mirror_failures = fetcher
+            .fetch_entries(&mirrored, |_| false, &mut updates)
+            .await?;
+
+        failures.retain(|(idx, _)| !updates.contains_key(idx));
+        for (idx, error) in mirror_failures {
+            if !failures.iter().any(|(failed, _)| *failed == idx) {
+                failures.push((idx, error));
+            }
+        }
+    }
 
-    for (kind, mut group) in groups {
-        let provider_kind = match kind {
-            Some(k) => k,
-            None => continue,
+    // Keys with a JSON key only take that field of the secret
+    for (&idx, fetched) in updates.iter_mut() {
+        let var = &variables[idx];
+        let Some(field) = &var.json_key else {
+            continue;
         };
 
-        let ids = group
+        match json_field(&fetched.value, field) {
+            Some(Ok(value)) => fetched.value = value,
+            Some(Err(())) => failures.push((
+                idx,
+                anyhow!(
+                    "Secret {} for {} has no field '{}'",
+                    fetched.id,
+                    var.key,
+                    field
+                ),
+            )),
+            None => failures.push((
+                idx,
+                anyhow!("Secret {} for {} isn't a JSON object", fetched.id, var.key),
+            )),
+        }
+    }
+    updates.retain(|idx, _| !failures.iter().any(|(failed, _)| failed == idx));
+
+    failures.sort_by_key(|(idx, _)| *idx);
+
+    // Without best effort a failure is only tolerated when the mirror had the value
+    if !policy.best_effort && !failures.is_empty() {
+        return Err(failures.swap_remove(0).1);
+    }
+
+    let failures = failures
+        .into_iter()
+        .map(|(idx, error)| FetchFailure {
+            key: variables[idx].key.clone(),
+            error,
+        })
+        .collect();
+
+    // Variables are only modified once every fetch has succeeded, so a failed or
+    // cancelled resolution never leaves them partially resolved
+    for (idx, var) in variables.iter_mut().enumerate() {
+        let Some(fetched) = updates.remove(&idx) else {
+            continue;
+        };
+
+        var.value = Some(fetched.value);
+        var.provenance = Some(Provenance {
+            provider: fetched.provider,
+            id: fetched.id,
+            version: fetched.version,
+            fetched_at: fetched.fetched_at,
+            expires_at: var.ttl.map(|ttl| fetched.fetched_at + ttl),
+            changed_at: fetched.metadata.changed_at,
+        });
+    }
+
+    Ok(failures)
+}
+
+/// The value of `field` in a JSON object, strings are taken as is and anything else is
+/// kept as JSON. `None` when the secret isn't a JSON object.
+fn json_field(secret: &str, field: &str) -> Option<Result<String, ()>> {
+    let Ok(serde_json::Value::Object(mut object)) = serde_json::from_str(secret) else {
+        return None;
+    };
+
+    Some(match object.remove(field) {
+        Some(serde_json::Value::String(value)) => Ok(value),
+        Some(value) => Ok(value.to_string()),
+        None => Err(()),
+    })
+}
+
+/// Whether fetching from `config` costs less than fetching from `other`, going by
+/// [`request_cost`]
+fn is_cheaper(config: &ProviderConfig, other: &ProviderConfig) -> bool {
+    matches!(
+        (request_cost(config), request_cost(other)),
+        (Some(cost), Some(other)) if cost < other
+    )
+}
+
+/// A secret fetched for an entry along with the name of the provider it came from
+struct Fetched {
+    id: String,
+    value: String,
+    version: Option<String>,
+    metadata: SecretMetadata,
+    provider: String,
+    fetched_at: SystemTime,
+}
+
+/// Everything a resolution fetches entries with
+struct Fetcher<'a, SM, PS> {
+    variables: &'a Variables,
+    placeholders: &'a IndexMap<String, String>,
+    pinning: &'a Pinning<'a>,
+    policy: FetchPolicy,
+    aws_sm: &'a SM,
+    aws_ps: &'a PS,
+    audit: Option<&'a Audit<'a>>,
+}
+
+impl<SM: Provider, PS: Provider> Fetcher<'_, SM, PS> {
+    /// Fetches `entries`, the index of each one along with the provider to fetch it from,
+    /// adding the secrets found to `updates`. Entries that failed are returned when the
+    /// policy is best effort, or when `has_fallback` holds for every entry of the failed
+    /// request so they can be fetched from elsewhere.
+    async fn fetch_entries(
+        &self,
+        entries: &[(usize, &ProviderConfig)],
+        has_fallback: impl Fn(usize) -> bool,
+        updates: &mut HashMap<usize, Fetched>,
+    ) -> Result<Vec<(usize, anyhow::Error)>> {
+        // Entries of a secret at different versions are requested separately
+        let groups = entries
             .iter()
-            .map(|v| {
-                v.provider_config
-                    .as_ref()
-                    .expect("Expected nones to be filtered out")
-                    .id()
+            .map(|&(idx, config)| {
+                let version = self.variables[idx].version.as_ref();
+                ((ProviderKind::from(config), version), (idx, config))
             })
-            .map(|id| replace_placeholders(id, &placeholders))
-            .collect::<Result<Vec<_>>>()?;
+            .into_group_map();
+
+        let mut failures = Vec::new();
+
+        for ((provider_kind, version), group) in groups {
+            let ids = group
+                .iter()
+                .map(|(_, config)| replace_placeholders(config.id(), self.placeholders))
+                .map_ok(Cow::into_owned)
+                .collect::<Result<Vec<_>>>()?;
+
+            let request = self.fetch(&provider_kind, version, &group, &ids);
+
+            let resolved = match with_timeout(self.policy.timeout, request).await {
+                // Fetching entries one at a time finds the ones that keep failing
+                Err(_) if self.policy.best_effort => {
+                    let mut resolved = Vec::new();
+
+                    for (entry, id) in group.iter().zip(&ids) {
+                        let (entry, id) = (slice::from_ref(entry), slice::from_ref(id));
+                        let request = self.fetch(&provider_kind, version, entry, id);
+                        let single = with_timeout(self.policy.timeout, request).await;
+
+                        if let Some(audit) = self.audit {
+                            audit_group(audit, self.variables, entry, id, &single)?;
+                        }
+
+                        match single {
+                            Ok(secrets) => resolved.extend(secrets),
+                            Err(error) => failures.push((entry[0].0, error)),
+                        }
+                    }
+
+                    resolved
+                }
+                resolved => {
+                    if let Some(audit) = self.audit {
+                        audit_group(audit, self.variables, &group, &ids, &resolved)?;
+                    }
+
+                    match resolved {
+                        Err(error) if group.iter().all(|&(idx, _)| has_fallback(idx)) => {
+                            failures.extend(
+                                group.iter().map(|&(idx, _)| (idx, anyhow!("{:#}", error))),
+                            );
+                            continue;
+                        }
+                        resolved => resolved?,
+                    }
+                }
+            };
+            let fetched_at = SystemTime::now();
+
+            // Keys using different fields of a JSON secret share it
+            for (&(idx, config), id) in group.iter().zip(&ids) {
+                let key = &self.variables[idx].key;
+                let result = resolved
+                    .iter()
+                    .find(|(fetched, _)| fetched == id)
+                    .map(|(_, result)| result);
 
-        let resolved = match provider_kind {
-            ProviderKind::AwsSecretsManager => aws_sm.provide_secrets(ids).await?,
-            ProviderKind::AwsParameterStore => aws_ps.provide_secrets(ids).await?,
+                match result {
+                    Some(SecretResult::Found {
+                        value,
+                        version,
+                        metadata,
+                    }) => {
+                        updates.insert(
+                            idx,
+                            Fetched {
+                                id: id.clone(),
+                                value: value.clone(),
+                                version: version.clone(),
+                                metadata: metadata.clone(),
+                                provider: config.name().to_string(),
+                                fetched_at,
+                            },
+                        );
+                    }
+                    Some(SecretResult::AccessDenied) => {
+                        failures.push((idx, anyhow!("Access denied to {} for {}", id, key)));
+                    }
+                    Some(SecretResult::Error(error)) => {
+                        failures.push((
+                            idx,
+                            anyhow!("{:#}", error).context(format!("Failed to fetch {}", key)),
+                        ));
+                    }
+                    Some(SecretResult::NotFound) | None => {}
+                }
+            }
+        }
+
+        Ok(failures)
+    }
+
+    /// Fetches the secrets of a single provider, `entries` and `ids` are its entries and
+    /// `version` the one they select in the spec. Returns the result for each distinct ID.
+    async fn fetch(
+        &self,
+        provider_kind: &ProviderKind,
+        version: Option<&SecretVersion>,
+        entries: &[(usize, &ProviderConfig)],
+        ids: &[String],
+    ) -> Result<Vec<(String, SecretResult)>> {
+        // Keys using different fields of a JSON secret share its id
+        let unique = ids.iter().unique().cloned().collect::<Vec<_>>();
+
+        // Lockfiles record the exact version, even of entries that select one
+        let versions = match (self.pinning, provider_kind, version) {
+            (Pinning::Locked(lockfile), _, _) => {
+                Some(locked_versions(lockfile, self.variables, entries, ids)?)
+            }
+            (_, _, Some(version)) => Some(
+                unique
+                    .iter()
+                    .map(|id| (id.clone(), version.clone()))
+                    .collect(),
+            ),
+            (Pinning::Latest, _, None)
+            | (Pinning::Stage(_), ProviderKind::AwsParameterStore, None) => None,
+            (Pinning::Stage(stage), _, None) => Some(staged(&unique, stage)),
+        };
+        let requested = match &versions {
+            Some(versions) => versions.iter().map(|(id, _)| id.clone()).collect(),
+            None => unique,
         };
 
-        for secret in resolved {
-            let var = group
-                .iter_mut()
-                .find(|v| {
-                    replace_placeholders(
-                        v.provider_config
-                            .as_ref()
-                            .expect("Expected nones to be filtered out")
-                            .id(),
-                        &placeholders,
-                    )
-                    .expect("Placholder substitution succeeded earlier")
-                        == secret.id
-                })
-                .expect("Expected matching variable");
+        let (aws_sm, aws_ps) = (self.aws_sm, self.aws_ps);
+        let results = match (provider_kind, versions) {
+            (ProviderKind::AwsSecretsManager, None) => {
+                aws_sm.provide_secrets(requested.clone()).await?
+            }
+            (ProviderKind::AwsSecretsManager, Some(versions)) => {
+                aws_sm.provide_secret_versions(versions).await?
+            }
+            (ProviderKind::AwsParameterStore, None) => {
+                aws_ps.provide_secrets(requested.clone()).await?
+            }
+            (ProviderKind::AwsParameterStore, Some(versions)) => {
+                aws_ps.provide_secret_versions(versions).await?
+            }
+            (ProviderKind::Plugin(name), None) => {
+                let plugin = PluginProvider::find(name)?;
+                plugin.provide_secrets(requested.clone()).await?
+            }
+            (ProviderKind::Plugin(name), Some(versions)) => {
+                let plugin = PluginProvider::find(name)?;
+                plugin.provide_secret_versions(versions).await?
+            }
+        };
 
-            var.value = Some(secret.secret);
+        if results.len() != requested.len() {
+            return Err(anyhow!(
+                "Provider {} returned {} results for {} secrets",
+                entries[0].1.name(),
+                results.len(),
+                requested.len()
+            ));
         }
+
+        Ok(requested
+            .into_iter()
+            .zip(results)
+            .map(|(id, result)| match (self.pinning, result) {
+                // Versions that were locked are expected to exist
+                (Pinning::Locked(_), SecretResult::NotFound) => {
+                    let error = anyhow!("Locked version of {} not found", id);
+                    (id, SecretResult::Error(error))
+                }
+                (_, result) => (id, result),
+            })
+            .collect())
+    }
+}
+
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    request: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return request.await;
+    };
+
+    tokio::time::timeout(timeout, request)
+        .await
+        .map_err(|_| anyhow!("Timed out after {}", humantime::format_duration(timeout)))?
+}
+
+fn staged(ids: &[String], stage: &str) -> Vec<(String, SecretVersion)> {
+    ids.iter()
+        .map(|id| (id.clone(), SecretVersion::Stage(stage.to_string())))
+        .collect()
+}
+
+/// The locked version of every entry, entries without one are left out
+fn locked_versions(
+    lockfile: &Lockfile,
+    variables: &Variables,
+    entries: &[(usize, &ProviderConfig)],
+    ids: &[String],
+) -> Result<Vec<(String, SecretVersion)>> {
+    let mut versions = Vec::new();
+
+    for (&(idx, config), id) in entries.iter().zip(ids) {
+        let var = &variables[idx];
+
+        if let Some(version) = lockfile.version_for(&var.key, config, id, var.required)?
+            && !versions.iter().any(|(locked, _)| locked == id)
+        {
+            versions.push((id.clone(), SecretVersion::Id(version.to_owned())));
+        }
+    }
+
+    Ok(versions)
+}
+
+/// Records the outcome of fetching every entry in a group, failing every entry when
+/// the fetch failed
+fn audit_group(
+    audit: &Audit<'_>,
+    variables: &Variables,
+    entries: &[(usize, &ProviderConfig)],
+    ids: &[String],
+    resolved: &Result<Vec<(String, SecretResult)>>,
+) -> Result<()> {
+    for (&(idx, config), id) in entries.iter().zip(ids) {
+        let key = &variables[idx].key;
+
+        let record = match resolved {
+            Ok(results) => match results.iter().find(|(fetched, _)| fetched == id) {
+                Some((_, SecretResult::Found { version, .. })) => AuditRecord {
+                    version: version.clone(),
+                    ..audit.record(key, config, id, AuditOutcome::Resolved)
+                },
+                Some((_, SecretResult::AccessDenied)) => {
+                    audit.record(key, config, id, AuditOutcome::AccessDenied)
+                }
+                Some((_, SecretResult::Error(err))) => AuditRecord {
+                    error: Some(format!("{:#}", err)),
+                    ..audit.record(key, config, id, AuditOutcome::Error)
+                },
+                Some((_, SecretResult::NotFound)) | None => {
+                    audit.record(key, config, id, AuditOutcome::NotFound)
+                }
+            },
+            Err(err) => AuditRecord {
+                error: Some(format!("{:#}", err)),
+                ..audit.record(key, config, id, AuditOutcome::Error)
+            },
+        };
+
+        audit
+            .sink
+            .record(&record)
+            .context("Failed to record secret access")?;
     }
 
     Ok(())
@@ -100,37 +931,181 @@ pub fn merge(mut variables: Variables, mut others: Variables, mode: MergeMode) -
     }
 }
 
-fn replace_placeholders(id: &str, placeholders: &IndexMap<String, String>) -> Result<String> {
-    let re = RE_PLACEHOLDER.get_or_init(|| Regex::new(r"\$(\w+)").unwrap());
-    let output = id.replace("$$", MARKER);
+/// Returns the keys of secret-backed `variables` whose value would be replaced by `overrides`
+pub fn shadowed_keys<'a>(variables: &'a Variables, overrides: &Variables) -> Vec<&'a str> {
+    variables
+        .iter()
+        .filter(|var| var.provider_config.is_some())
+        .filter(|var| {
+            overrides
+                .find_by_key(&var.key)
+                .is_some_and(|o| o.value.is_some())
+        })
+        .map(|var| var.key.as_str())
+        .collect()
+}
 
-    let mut missing: Option<Error> = None;
+/// Returns the names of placeholders used in secret IDs that have no value in `placeholders`
+pub fn missing_placeholders(
+    variables: &Variables,
+    placeholders: &IndexMap<String, String>,
+) -> Vec<String> {
+    let ids = variables.iter().flat_map(|v| {
+        let id = v.provider_config.as_ref().map(|config| config.id());
+        id.into_iter().chain(v.ps_path.as_deref())
+    });
 
-    let mut output = re.replace_all(&output, |caps: &regex::Captures| {
-        let name = caps
-            .get(1)
-            .expect("a match should contain a capture")
-            .as_str();
+    placeholder_names(ids)
+        .into_iter()
+        .filter(|name| !placeholders.contains_key(name))
+        .collect()
+}
 
-        match placeholders.get(name) {
-            Some(value) => value,
-            None => {
-                missing = Some(anyhow!("Missing placeholder: {}", name));
-                ""
-            }
+/// Asks `hook` for the placeholders missing from `placeholders`, keeping the values it
+/// returns for them and ignoring any others
+pub fn fill_placeholders(
+    variables: &Variables,
+    placeholders: &mut IndexMap<String, String>,
+    hook: &impl PlaceholderHook,
+) -> Result<()> {
+    let missing = missing_placeholders(variables, placeholders);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    for (name, value) in hook.placeholders(&missing)? {
+        if missing.contains(&name) {
+            placeholders.insert(name, value);
         }
+    }
+
+    Ok(())
+}
+
+/// Returns the keys marked `@deprecated` that have a value, along with their notes
+pub fn deprecated_keys(variables: &Variables) -> Vec<(&str, &str)> {
+    variables
+        .iter()
+        .filter(|var| var.value.is_some() || var.default.is_some())
+        .filter_map(|var| Some((var.key.as_str(), var.deprecated.as_deref()?)))
+        .collect()
+}
+
+/// Returns the names of `placeholders` that aren't used by any secret ID or tag value
+pub fn unused_placeholders<'a>(
+    variables: &Variables,
+    placeholders: &'a IndexMap<String, String>,
+) -> Vec<&'a str> {
+    let texts = variables.iter().flat_map(|v| {
+        v.provider_config
+            .as_ref()
+            .map(|config| config.id())
+            .into_iter()
+            .chain(v.ps_path.as_deref())
+            .chain(v.tags.values().map(String::as_str))
     });
+    let used = placeholder_names(texts);
+
+    placeholders
+        .keys()
+        .filter(|name| !used.contains(name))
+        .map(String::as_str)
+        .collect()
+}
+
+fn placeholder_names<'a>(texts: impl Iterator<Item = &'a str>) -> Vec<String> {
+    texts
+        .flat_map(segments)
+        .filter_map(|segment| match segment {
+            Segment::Placeholder(name) => Some(name.to_owned()),
+            Segment::Literal(_) => None,
+        })
+        .unique()
+        .collect()
+}
 
-    if let Some(err) = missing {
-        return Err(err);
+/// Substitutes `$name` placeholders in a secret ID, `$$` escapes a literal `$`. IDs
+/// without any `$` are returned as is.
+pub fn replace_placeholders<'a>(
+    id: &'a str,
+    placeholders: &IndexMap<String, String>,
+) -> Result<Cow<'a, str>> {
+    if !id.contains('$') {
+        return Ok(Cow::Borrowed(id));
     }
 
-    Ok(output.to_mut().replace(MARKER, "$"))
+    let mut output = String::with_capacity(id.len());
+
+    for segment in segments(id) {
+        match segment {
+            Segment::Literal(text) => output.push_str(text),
+            Segment::Placeholder(name) => output.push_str(
+                placeholders
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Missing placeholder: {}", name))?,
+            ),
+        }
+    }
+
+    Ok(Cow::Owned(output))
+}
+
+/// Part of a text that may contain placeholders
+#[derive(Debug, PartialEq, Eq)]
+enum Segment<'a> {
+    Literal(&'a str),
+    /// Name of a placeholder, without the `$`
+    Placeholder(&'a str),
+}
+
+/// Splits `text` into literals and placeholders in a single pass. `$$` yields a literal
+/// `$` and a `$` not followed by a word character is kept as is.
+fn segments(text: &str) -> impl Iterator<Item = Segment<'_>> {
+    let mut rest = text;
+
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let Some(start) = rest.find('$') else {
+            return Some(Segment::Literal(std::mem::take(&mut rest)));
+        };
+
+        if start > 0 {
+            let (literal, tail) = rest.split_at(start);
+            rest = tail;
+            return Some(Segment::Literal(literal));
+        }
+
+        let after = &rest[1..];
+
+        if let Some(tail) = after.strip_prefix('$') {
+            rest = tail;
+            return Some(Segment::Literal("$"));
+        }
+
+        let len = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+
+        if len == 0 {
+            rest = after;
+            return Some(Segment::Literal("$"));
+        }
+
+        let (name, tail) = after.split_at(len);
+        rest = tail;
+        Some(Segment::Placeholder(name))
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::variable::Variable;
+    use crate::{
+        provider::{SecretMetadata, SecretResult},
+        variable::Variable,
+    };
 
     use super::*;
 
@@ -184,6 +1159,56 @@ mod tests {
         assert_eq!(result.unwrap(), "bar/456".to_string())
     }
 
+    #[test]
+    fn test_borrows_ids_without_placeholders() {
+        let result = replace_placeholders("foo/bar", &IndexMap::new()).unwrap();
+
+        assert!(matches!(result, Cow::Borrowed("foo/bar")));
+    }
+
+    #[test]
+    fn test_splits_segments() {
+        assert_eq!(
+            segments("a/$b$$c/$/$d_1-").collect::<Vec<_>>(),
+            vec![
+                Segment::Literal("a/"),
+                Segment::Placeholder("b"),
+                Segment::Literal("$"),
+                Segment::Literal("c/"),
+                Segment::Literal("$"),
+                Segment::Literal("/"),
+                Segment::Placeholder("d_1"),
+                Segment::Literal("-"),
+            ]
+        );
+        assert_eq!(segments("").count(), 0);
+    }
+
+    #[test]
+    fn test_finds_missing_placeholders() {
+        let variables: Variables = vec![
+            Variable {
+                key: "KEY1".to_string(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager(
+                    "$env/$$escaped/$foo".to_string(),
+                )),
+                ..Default::default()
+            },
+            Variable {
+                key: "KEY2".to_string(),
+                provider_config: Some(ProviderConfig::AwsParameterStore("$env/$bar".to_string())),
+                ..Default::default()
+            },
+        ]
+        .into();
+        let mut placeholders = IndexMap::new();
+        placeholders.insert("foo".to_string(), "123".to_string());
+
+        let result = missing_placeholders(&variables, &placeholders);
+
+        assert_eq!(result, vec!["env".to_string(), "bar".to_string()])
+    }
+
     fn var(key: &str, value: &str) -> Variable {
         Variable {
             key: key.to_string(),
@@ -291,4 +1316,734 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_cancelled_resolution_leaves_variables_untouched() {
+        let mut variables: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            default: Some("default".to_string()),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("foo/bar".to_string())),
+            ..Default::default()
+        }]
+        .into();
+        let expected = variables.to_vec();
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let result = resolve_cancellable(&mut variables, IndexMap::new(), &token).await;
+
+        assert_eq!(result.unwrap_err().to_string(), "Resolution was cancelled");
+        assert_eq!(variables.to_vec(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_resolves_without_secrets() {
+        let mut variables: Variables = vec![Variable {
+            key: "LITERAL".to_string(),
+            default: Some("default".to_string()),
+            ..Default::default()
+        }]
+        .into();
+        let expected = variables.to_vec();
+
+        assert!(!variables.has_secrets());
+
+        let lockfile = Lockfile::default();
+        resolve(&mut variables, IndexMap::new()).await.unwrap();
+        resolve_locked(&mut variables, IndexMap::new(), &lockfile)
+            .await
+            .unwrap();
+
+        assert_eq!(variables.to_vec(), expected);
+    }
+
+    /// Resolves every secret to its ID prefixed with the provider name
+    /// Returns every secret except those whose ID ends with `missing`, access to IDs
+    /// ending with `denied` is denied and IDs ending with `failing` fail on their own
+    struct EchoProvider(&'static str);
+
+    impl Provider for EchoProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            Ok(ids
+                .into_iter()
+                .map(|id| match id {
+                    id if id.ends_with("missing") => SecretResult::NotFound,
+                    id if id.ends_with("denied") => SecretResult::AccessDenied,
+                    id if id.ends_with("failing") => {
+                        SecretResult::Error(anyhow!("InternalServiceError"))
+                    }
+                    id => SecretResult::Found {
+                        value: format!("{}:{}", self.0, id),
+                        version: None,
+                        metadata: SecretMetadata::default(),
+                    },
+                })
+                .collect())
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            ids: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            self.provide_secrets(ids.into_iter().map(|(id, _)| id).collect())
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_with_providers() {
+        let mut variables: Variables = vec![
+            Variable {
+                key: "SM".to_string(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager("$env/db".to_string())),
+                ..Default::default()
+            },
+            Variable {
+                key: "PS".to_string(),
+                provider_config: Some(ProviderConfig::AwsParameterStore("/app/url".to_string())),
+                ..Default::default()
+            },
+            Variable {
+                key: "LITERAL".to_string(),
+                default: Some("default".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+        let mut placeholders = IndexMap::new();
+        placeholders.insert("env".to_string(), "prod".to_string());
+
+        resolve_with_providers(
+            &mut variables,
+            placeholders,
+            &EchoProvider("sm"),
+            &EchoProvider("ps"),
+        )
+        .await
+        .unwrap();
+
+        let sm = variables.find_by_key("SM").unwrap();
+        assert_eq!(sm.value.as_deref(), Some("sm:prod/db"));
+        assert_eq!(sm.provenance.as_ref().unwrap().provider, "aws-sm");
+        assert_eq!(
+            variables.find_by_key("PS").unwrap().value.as_deref(),
+            Some("ps:/app/url")
+        );
+        assert_eq!(variables.find_by_key("LITERAL").unwrap().value, None);
+    }
+
+    /// Fails any request including an ID containing `broken` and never answers requests
+    /// including one containing `slow`
+    struct FlakyProvider;
+
+    impl Provider for FlakyProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            if ids.iter().any(|id| id.contains("slow")) {
+                std::future::pending::<()>().await;
+            }
+            if ids.iter().any(|id| id.contains("broken")) {
+                return Err(anyhow!("AccessDeniedException"));
+            }
+
+            EchoProvider("flaky").provide_secrets(ids).await
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            ids: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            self.provide_secrets(ids.into_iter().map(|(id, _)| id).collect())
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_best_effort_isolates_failing_entries() {
+        let variables: Variables = ["ok", "broken", "slow"]
+            .into_iter()
+            .map(|id| Variable {
+                key: id.to_uppercase(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager(id.to_string())),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>()
+            .into();
+        let policy = FetchPolicy {
+            timeout: Some(Duration::from_millis(50)),
+            best_effort: false,
+            prefer_cheaper_mirror: false,
+        };
+
+        let mut strict: Variables = variables.to_vec().into();
+        let result = resolve_using(
+            &mut strict,
+            IndexMap::new(),
+            Pinning::Latest,
+            policy,
+            &FlakyProvider,
+            &FlakyProvider,
+            None,
+        )
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), "Timed out after 50ms");
+        assert_eq!(strict.to_vec(), variables.to_vec());
+
+        let mut best_effort: Variables = variables.to_vec().into();
+        let failures = resolve_using(
+            &mut best_effort,
+            IndexMap::new(),
+            Pinning::Latest,
+            FetchPolicy {
+                best_effort: true,
+                ..policy
+            },
+            &FlakyProvider,
+            &FlakyProvider,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            best_effort.find_by_key("OK").unwrap().value.as_deref(),
+            Some("flaky:ok")
+        );
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| (failure.key.as_str(), failure.error.to_string()))
+                .collect::<Vec<_>>(),
+            [
+                ("BROKEN", "AccessDeniedException".to_string()),
+                ("SLOW", "Timed out after 50ms".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_mirrors() {
+        let mirrored = |id: &str| Variable {
+            key: id.to_uppercase(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager(id.to_string())),
+            mirror: Some(ProviderConfig::AwsParameterStore(id.to_string())),
+            ..Default::default()
+        };
+        let fetched = async |var: Variable, policy| {
+            let mut variables: Variables = vec![var].into();
+            resolve_using(
+                &mut variables,
+                IndexMap::new(),
+                Pinning::Latest,
+                policy,
+                &FlakyProvider,
+                &EchoProvider("ps"),
+                None,
+            )
+            .await
+            .unwrap();
+
+            let var = variables.iter().next().unwrap();
+            (
+                var.value.clone().unwrap(),
+                var.provenance.as_ref().unwrap().provider.clone(),
+            )
+        };
+
+        assert_eq!(
+            fetched(mirrored("ok"), FetchPolicy::default()).await,
+            ("flaky:ok".to_string(), "aws-sm".to_string())
+        );
+        assert_eq!(
+            fetched(mirrored("broken"), FetchPolicy::default()).await,
+            ("ps:broken".to_string(), "aws-ps".to_string())
+        );
+
+        let cheaper = FetchPolicy {
+            prefer_cheaper_mirror: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fetched(mirrored("ok"), cheaper).await,
+            ("ps:ok".to_string(), "aws-ps".to_string())
+        );
+    }
+
+    /// Resolves secrets whose ID starts with `json` to a JSON object, anything else is
+    /// echoed. Each ID may only be requested once.
+    struct JsonProvider;
+
+    impl Provider for JsonProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            assert!(ids.iter().all_unique(), "Expected unique IDs: {:?}", ids);
+
+            let mut secrets = EchoProvider("sm").provide_secrets(ids.clone()).await?;
+            for (id, secret) in ids.iter().zip(&mut secrets) {
+                if let SecretResult::Found { value, .. } = secret
+                    && id.starts_with("json")
+                {
+                    *value = r#"{"user":"admin","port":5432}"#.to_string();
+                }
+            }
+
+            Ok(secrets)
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            ids: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            self.provide_secrets(ids.into_iter().map(|(id, _)| id).collect())
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extracts_json_keys() {
+        let field = |key: &str, id: &str, field: &str| Variable {
+            key: key.to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager(id.to_string())),
+            json_key: Some(field.to_string()),
+            ..Default::default()
+        };
+        let resolve = async |variables: &mut Variables| {
+            resolve_using(
+                variables,
+                IndexMap::new(),
+                Pinning::Latest,
+                FetchPolicy::default(),
+                &JsonProvider,
+                &EchoProvider("ps"),
+                None,
+            )
+            .await
+        };
+
+        let mut variables: Variables = vec![
+            field("DB_USER", "json/db", "user"),
+            field("DB_PORT", "json/db", "port"),
+        ]
+        .into();
+        resolve(&mut variables).await.unwrap();
+
+        let user = variables.find_by_key("DB_USER").unwrap();
+        assert_eq!(user.value.as_deref(), Some("admin"));
+        assert_eq!(user.provenance.as_ref().unwrap().id, "json/db");
+        assert_eq!(
+            variables.find_by_key("DB_PORT").unwrap().value.as_deref(),
+            Some("5432")
+        );
+
+        let mut variables: Variables = vec![field("DB_HOST", "json/db", "host")].into();
+        let err = resolve(&mut variables).await.err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Secret json/db for DB_HOST has no field 'host'"
+        );
+        assert_eq!(variables.find_by_key("DB_HOST").unwrap().value, None);
+
+        let mut variables: Variables = vec![field("TOKEN", "plain/token", "token")].into();
+        let err = resolve(&mut variables).await.err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Secret plain/token for TOKEN isn't a JSON object"
+        );
+    }
+
+    /// Parameter Store with a few parameters under `/app/prod/`
+    struct PathProvider;
+
+    impl Provider for PathProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            EchoProvider("ps").provide_secrets(ids).await
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            ids: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            EchoProvider("ps").provide_secret_versions(ids).await
+        }
+
+        async fn list_secrets_by_path(&self, path: &str) -> Result<Vec<String>> {
+            Ok([
+                "/app/prod/db/password",
+                "/app/prod/api-key",
+                "/app/prod/port",
+            ]
+            .into_iter()
+            .filter(|name| name.starts_with(path))
+            .map(str::to_string)
+            .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expands_parameter_paths() {
+        let template = |path: &str| Variable {
+            key: "APP".to_string(),
+            required: true,
+            ps_path: Some(path.to_string()),
+            ttl: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let resolve = async |variables: &mut Variables| {
+            resolve_using(
+                variables,
+                [("env".to_string(), "prod".to_string())].into(),
+                Pinning::Latest,
+                FetchPolicy::default(),
+                &EchoProvider("sm"),
+                &PathProvider,
+                None,
+            )
+            .await
+        };
+
+        let mut variables: Variables = vec![
+            template("/app/$env/"),
+            Variable {
+                key: "APP_PORT".to_string(),
+                default: Some("8080".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+        resolve(&mut variables).await.unwrap();
+
+        assert_eq!(
+            variables
+                .iter()
+                .map(|var| (var.key.as_str(), var.value.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("APP_DB_PASSWORD", Some("ps:/app/prod/db/password")),
+                ("APP_API_KEY", Some("ps:/app/prod/api-key")),
+                ("APP_PORT", None),
+            ]
+        );
+        assert_eq!(variables[0].ttl, Some(Duration::from_secs(60)));
+
+        let mut variables: Variables = vec![template("/other/")].into();
+        let err = resolve(&mut variables).await.unwrap_err();
+        assert_eq!(err.to_string(), "No parameters found under /other/ for APP");
+        assert_eq!(variables[0].ps_path.as_deref(), Some("/other/"));
+    }
+
+    /// Resolves secrets to their ID followed by the version requested
+    struct VersionedProvider;
+
+    impl Provider for VersionedProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            self.provide_secret_versions(
+                ids.into_iter()
+                    .map(|id| (id, SecretVersion::Stage("AWSCURRENT".to_string())))
+                    .collect(),
+            )
+            .await
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            ids: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            Ok(ids
+                .into_iter()
+                .map(|(id, version)| {
+                    let version = match version {
+                        SecretVersion::Id(version) | SecretVersion::Stage(version) => version,
+                    };
+
+                    SecretResult::Found {
+                        value: format!("{}@{}", id, version),
+                        version: Some(version),
+                        metadata: SecretMetadata::default(),
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetches_versions_selected_in_spec() {
+        let versioned = |key: &str, version: Option<SecretVersion>| Variable {
+            key: key.to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("prod/db".to_string())),
+            version,
+            ..Default::default()
+        };
+        let mut variables: Variables = vec![
+            versioned("CURRENT", None),
+            versioned(
+                "PREVIOUS",
+                Some(SecretVersion::Stage("AWSPREVIOUS".to_string())),
+            ),
+            versioned("PINNED", Some(SecretVersion::Id("v1".to_string()))),
+        ]
+        .into();
+
+        resolve_with_providers(
+            &mut variables,
+            IndexMap::new(),
+            &VersionedProvider,
+            &EchoProvider("ps"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            variables
+                .iter()
+                .map(|var| var.value.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["prod/db@AWSCURRENT", "prod/db@AWSPREVIOUS", "prod/db@v1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reports_failures_per_secret() {
+        let variables: Variables = ["app/ok", "app/denied", "app/failing"]
+            .into_iter()
+            .map(|id| Variable {
+                key: id.trim_start_matches("app/").to_uppercase(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager(id.to_string())),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>()
+            .into();
+        let resolve = async |variables: &mut Variables, best_effort| {
+            resolve_using(
+                variables,
+                IndexMap::new(),
+                Pinning::Latest,
+                FetchPolicy {
+                    best_effort,
+                    ..Default::default()
+                },
+                &EchoProvider("sm"),
+                &EchoProvider("ps"),
+                None,
+            )
+            .await
+        };
+
+        let mut strict: Variables = variables.to_vec().into();
+        let err = resolve(&mut strict, false).await.unwrap_err();
+        assert_eq!(err.to_string(), "Access denied to app/denied for DENIED");
+        assert_eq!(strict.to_vec(), variables.to_vec());
+
+        let mut best_effort: Variables = variables.to_vec().into();
+        let failures = resolve(&mut best_effort, true).await.unwrap();
+        assert_eq!(
+            best_effort.find_by_key("OK").unwrap().value.as_deref(),
+            Some("sm:app/ok")
+        );
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| format!("{}: {:#}", failure.key, failure.error))
+                .collect::<Vec<_>>(),
+            vec![
+                "DENIED: Access denied to app/denied for DENIED",
+                "FAILING: Failed to fetch FAILING: InternalServiceError",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_records_secret_accesses() {
+        let mut variables: Variables = vec![
+            Variable {
+                key: "FOUND".to_string(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager("prod/db".to_string())),
+                ..Default::default()
+            },
+            Variable {
+                key: "MISSING".to_string(),
+                provider_config: Some(ProviderConfig::AwsParameterStore(
+                    "/app/missing".to_string(),
+                )),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let records = std::sync::Mutex::new(Vec::new());
+        let sink = |record: &AuditRecord| {
+            records.lock().unwrap().push(record.clone());
+            Ok(())
+        };
+        let audit = Audit {
+            sink: &sink,
+            identity: Some("arn:aws:iam::123456789012:user/ci".to_string()),
+        };
+
+        resolve_using(
+            &mut variables,
+            IndexMap::new(),
+            Pinning::Latest,
+            FetchPolicy::default(),
+            &EchoProvider("sm"),
+            &EchoProvider("ps"),
+            Some(&audit),
+        )
+        .await
+        .unwrap();
+
+        let mut records = records.into_inner().unwrap();
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].key, "FOUND");
+        assert_eq!(records[0].provider, "aws-sm");
+        assert_eq!(records[0].id, "prod/db");
+        assert_eq!(records[0].outcome, AuditOutcome::Resolved);
+        assert_eq!(
+            records[0].identity.as_deref(),
+            Some("arn:aws:iam::123456789012:user/ci")
+        );
+        assert_eq!(records[1].key, "MISSING");
+        assert_eq!(records[1].outcome, AuditOutcome::NotFound);
+        assert!(
+            records
+                .iter()
+                .all(|record| !serde_json::to_string(record).unwrap().contains("sm:"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolver_records_accesses_without_identity() {
+        let mut variables: Variables = vec![Variable {
+            key: "FOUND".to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("prod/db".to_string())),
+            ..Default::default()
+        }]
+        .into();
+
+        let records = std::sync::Mutex::new(Vec::new());
+        let sink = |record: &AuditRecord| {
+            records.lock().unwrap().push(record.clone());
+            Ok(())
+        };
+
+        Resolver::with_providers(EchoProvider("sm"), EchoProvider("ps"))
+            .resolve_audited(&mut variables, IndexMap::new(), Pinning::Latest, &sink)
+            .await
+            .unwrap();
+
+        let records = records.into_inner().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].outcome, AuditOutcome::Resolved);
+        assert_eq!(records[0].identity, None);
+        assert_eq!(
+            variables.find_by_key("FOUND").unwrap().value.as_deref(),
+            Some("sm:prod/db")
+        );
+    }
+
+    #[test]
+    fn test_fills_placeholders_from_hook() {
+        let variables: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager(
+                "$env/$branch".to_string(),
+            )),
+            ..Default::default()
+        }]
+        .into();
+        let mut placeholders = IndexMap::new();
+        placeholders.insert("env".to_string(), "prod".to_string());
+
+        let hook = |missing: &[String]| -> Result<IndexMap<String, String>> {
+            assert_eq!(missing, ["branch".to_string()]);
+            Ok(IndexMap::from([
+                ("env".to_string(), "dev".to_string()),
+                ("branch".to_string(), "main".to_string()),
+                ("other".to_string(), "x".to_string()),
+            ]))
+        };
+        fill_placeholders(&variables, &mut placeholders, &hook).unwrap();
+
+        assert_eq!(placeholders.len(), 2);
+        assert_eq!(placeholders["env"], "prod");
+        assert_eq!(placeholders["branch"], "main");
+    }
+
+    #[test]
+    fn test_finds_deprecated_keys() {
+        let variables: Variables = vec![
+            Variable {
+                key: "DB_URL".to_string(),
+                value: Some("postgres://".to_string()),
+                deprecated: Some("use DATABASE_URL".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "UNSET".to_string(),
+                deprecated: Some("".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "PORT".to_string(),
+                default: Some("8080".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        assert_eq!(
+            deprecated_keys(&variables),
+            vec![("DB_URL", "use DATABASE_URL")]
+        );
+    }
+
+    #[test]
+    fn test_finds_shadowed_keys() {
+        let variables: Variables = vec![
+            Variable {
+                key: "SECRET".to_string(),
+                provider_config: Some(ProviderConfig::AwsSecretsManager("foo".to_string())),
+                ..Default::default()
+            },
+            Variable {
+                key: "LITERAL".to_string(),
+                default: Some("default".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "UNTOUCHED".to_string(),
+                provider_config: Some(ProviderConfig::AwsParameterStore("bar".to_string())),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let mut overrides = IndexMap::new();
+        overrides.insert("SECRET".to_string(), "stale".to_string());
+        overrides.insert("LITERAL".to_string(), "fine".to_string());
+
+        assert_eq!(shadowed_keys(&variables, &overrides.into()), ["SECRET"]);
+    }
+
+    #[test]
+    fn test_finds_unused_placeholders() {
+        let variables: Variables = vec![Variable {
+            key: "KEY1".to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager(
+                "$env/$$literal".to_string(),
+            )),
+            tags: IndexMap::from([("team".to_string(), "$team".to_string())]),
+            ..Default::default()
+        }]
+        .into();
+
+        let placeholders = IndexMap::from(
+            ["env", "team", "literal", "typo"].map(|name| (name.to_string(), "x".to_string())),
+        );
+
+        assert_eq!(
+            unused_placeholders(&variables, &placeholders),
+            ["literal", "typo"]
+        );
+    }
 }
@@ -0,0 +1,210 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use indexmap::IndexMap;
+
+use crate::{
+    provider::{AwsOptions, AwsSecretsManagerProvider},
+    resolve::replace_placeholders,
+    term::format_table,
+    variable::{ProviderConfig, Variables},
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Rotation configuration and history of a Secrets Manager secret
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RotationStatus {
+    pub enabled: bool,
+    pub interval_days: Option<i64>,
+    pub last_rotated: Option<SystemTime>,
+    pub next_rotation: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+}
+
+impl RotationStatus {
+    /// A secret is stale when rotation is disabled or overdue, or when it
+    /// hasn't changed in more than `max_age`
+    pub fn is_stale(&self, now: SystemTime, max_age: Duration) -> bool {
+        let overdue = self.next_rotation.is_some_and(|next| next < now);
+        let too_old = self
+            .last_rotated
+            .or(self.created)
+            .and_then(|changed| now.duration_since(changed).ok())
+            .is_some_and(|age| age > max_age);
+
+        !self.enabled || overdue || too_old
+    }
+}
+
+/// Rotation status of a single spec entry
+pub struct RotationReport {
+    pub key: String,
+    /// Secret ID after placeholder substitution
+    pub id: String,
+    pub status: Result<RotationStatus>,
+}
+
+/// Fetches the rotation status of every `@aws-sm` entry in `variables`
+pub async fn rotation_report(
+    variables: &Variables,
+    placeholders: &IndexMap<String, String>,
+    aws: &AwsOptions,
+) -> Result<Vec<RotationReport>> {
+    let aws_sm = AwsSecretsManagerProvider::new(aws).await?;
+    let mut reports = Vec::new();
+
+    for var in variables.iter() {
+        let Some(ProviderConfig::AwsSecretsManager(id)) = &var.provider_config else {
+            continue;
+        };
+
+        let id = replace_placeholders(id, placeholders)?.into_owned();
+        let status = aws_sm.describe_rotation(&id).await;
+
+        reports.push(RotationReport {
+            key: var.key.clone(),
+            id,
+            status,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// Formats `reports` as a table, flagging stale secrets
+pub fn format_report(reports: &[RotationReport], now: SystemTime, max_age: Duration) -> String {
+    let rows = reports
+        .iter()
+        .map(|report| {
+            let (rotation, last, next, state) = match &report.status {
+                Ok(status) => (
+                    match (status.enabled, status.interval_days) {
+                        (true, Some(days)) => format!("every {}d", days),
+                        (true, None) => "enabled".to_string(),
+                        (false, _) => "disabled".to_string(),
+                    },
+                    status
+                        .last_rotated
+                        .map(format_date)
+                        .unwrap_or("never".into()),
+                    status
+                        .next_rotation
+                        .map(|next| format_days_until(now, next))
+                        .unwrap_or("-".into()),
+                    match status.is_stale(now, max_age) {
+                        true => "STALE".to_string(),
+                        false => "ok".to_string(),
+                    },
+                ),
+                Err(err) => (
+                    "-".into(),
+                    "-".into(),
+                    "-".into(),
+                    format!("error: {}", err),
+                ),
+            };
+
+            [
+                report.key.clone(),
+                report.id.clone(),
+                rotation,
+                last,
+                next,
+                state,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    format_table(
+        [
+            "KEY",
+            "SECRET",
+            "ROTATION",
+            "LAST ROTATED",
+            "NEXT ROTATION",
+            "STATUS",
+        ],
+        &rows,
+    )
+}
+
+fn format_date(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()[..10].to_string()
+}
+
+fn format_days_until(now: SystemTime, then: SystemTime) -> String {
+    match then.duration_since(now) {
+        Ok(until) => format!("in {}d", until.as_secs() / DAY),
+        Err(err) => format!("overdue {}d", err.duration().as_secs() / DAY),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    fn days(n: u64) -> Duration {
+        Duration::from_secs(n * DAY)
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let now = UNIX_EPOCH + days(100);
+        let max_age = days(90);
+
+        let healthy = RotationStatus {
+            enabled: true,
+            interval_days: Some(30),
+            last_rotated: Some(UNIX_EPOCH + days(80)),
+            next_rotation: Some(UNIX_EPOCH + days(110)),
+            created: Some(UNIX_EPOCH),
+        };
+        assert!(!healthy.is_stale(now, max_age));
+
+        let disabled = RotationStatus {
+            enabled: false,
+            ..healthy.clone()
+        };
+        assert!(disabled.is_stale(now, max_age));
+
+        let overdue = RotationStatus {
+            next_rotation: Some(UNIX_EPOCH + days(95)),
+            ..healthy.clone()
+        };
+        assert!(overdue.is_stale(now, max_age));
+
+        let never_rotated = RotationStatus {
+            last_rotated: None,
+            next_rotation: None,
+            ..healthy
+        };
+        assert!(never_rotated.is_stale(now, max_age));
+    }
+
+    #[test]
+    fn test_format_report() {
+        let now = UNIX_EPOCH + days(100);
+        let reports = vec![RotationReport {
+            key: "KEY1".to_string(),
+            id: "prod/key1".to_string(),
+            status: Ok(RotationStatus {
+                enabled: true,
+                interval_days: Some(30),
+                last_rotated: Some(UNIX_EPOCH + days(80)),
+                next_rotation: Some(UNIX_EPOCH + days(110)),
+                created: Some(UNIX_EPOCH),
+            }),
+        }];
+
+        let result = format_report(&reports, now, days(90));
+
+        assert_eq!(
+            result,
+            "KEY   SECRET     ROTATION   LAST ROTATED  NEXT ROTATION  STATUS\n\
+             KEY1  prod/key1  every 30d  1970-03-22    in 10d         ok\n"
+        );
+    }
+}
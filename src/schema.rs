@@ -0,0 +1,105 @@
+//! JSON Schemas of the machine-readable outputs. Each schema is versioned in its `$id`,
+//! a breaking change to an output ships as a new version of its schema.
+
+/// Version of the schemas shipped with this build
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A machine-readable output with a published schema
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schema {
+    /// Entries of a parsed spec, returned by the bindings. See [`SpecEntry`](crate::output::SpecEntry).
+    Spec,
+    /// Passed to `--post-hook` commands. See [`RenderSummary`](crate::hooks::RenderSummary).
+    Summary,
+    /// Lines of the `--audit-log` file. See [`AuditRecord`](crate::audit::AuditRecord).
+    Audit,
+}
+
+impl Schema {
+    /// The schema as a JSON document
+    pub fn json(&self) -> &'static str {
+        match self {
+            Schema::Spec => include_str!("../schemas/spec.v1.json"),
+            Schema::Summary => include_str!("../schemas/summary.v1.json"),
+            Schema::Audit => include_str!("../schemas/audit.v1.json"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+    use serde_json::Value;
+
+    use crate::{
+        audit::{AuditOutcome, AuditRecord},
+        hooks::RenderSummary,
+        output::SpecEntry,
+        variable::{ProviderConfig, Variable, Variables},
+    };
+
+    use super::*;
+
+    /// Checks that `value` has exactly the properties described by `schema`
+    fn assert_matches(schema: &Value, value: &Value) {
+        let properties = schema["properties"].as_object().unwrap();
+        let object = value.as_object().unwrap();
+
+        for key in object.keys() {
+            assert!(properties.contains_key(key), "{} is not in the schema", key);
+        }
+        for key in schema["required"].as_array().unwrap() {
+            assert!(
+                object.contains_key(key.as_str().unwrap()),
+                "{} is missing",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_schemas_are_versioned() {
+        for schema in Schema::value_variants() {
+            let json: Value = serde_json::from_str(schema.json()).unwrap();
+            let id = json["$id"].as_str().unwrap();
+
+            assert!(
+                id.ends_with(&format!(".v{}.json", SCHEMA_VERSION)),
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_outputs_match_schemas() {
+        let var = Variable {
+            key: "KEY1".to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("prod/key1".to_string())),
+            ..Default::default()
+        };
+
+        let spec: Value = serde_json::from_str(Schema::Spec.json()).unwrap();
+        let entry = serde_json::to_value(SpecEntry::from(&var)).unwrap();
+        assert_matches(&spec["items"], &entry);
+
+        let summary: Value = serde_json::from_str(Schema::Summary.json()).unwrap();
+        let variables: Variables = vec![var].into();
+        let rendered = RenderSummary::new(&variables, None, "env");
+        assert_matches(&summary, &serde_json::to_value(rendered).unwrap());
+
+        let audit: Value = serde_json::from_str(Schema::Audit.json()).unwrap();
+        let record = AuditRecord {
+            error: Some("denied".to_string()),
+            ..AuditRecord::new("KEY1", "aws-sm", "prod/key1", AuditOutcome::Error)
+        };
+        let record = serde_json::to_value(record).unwrap();
+        assert_matches(&audit, &record);
+        assert_eq!(
+            serde_json::from_value::<AuditRecord>(record)
+                .unwrap()
+                .outcome,
+            AuditOutcome::Error
+        );
+    }
+}
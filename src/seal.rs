@@ -0,0 +1,139 @@
+//! Encryption of the values of keys marked `@seal`, so rendered output can be committed
+//! and only decrypted where it's deployed
+
+use anyhow::Result;
+#[cfg(feature = "age")]
+use anyhow::anyhow;
+
+use crate::{hooks::SealHook, variable::Variables};
+
+/// Replaces the value of every key marked `@seal` with its sealed form. Keys without a
+/// value are left alone.
+pub fn seal_marked(variables: &mut Variables, hook: &impl SealHook) -> Result<()> {
+    for var in variables.iter_mut().filter(|var| var.seal) {
+        if let Some(value) = var.value.take().or_else(|| var.default.take()) {
+            var.value = Some(hook.seal(&var.key, &value)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Keys marked `@seal`, which can't be rendered without a way to seal them
+pub fn sealed_keys(variables: &Variables) -> Vec<&str> {
+    variables
+        .iter()
+        .filter(|var| var.seal)
+        .map(|var| var.key.as_str())
+        .collect()
+}
+
+/// Seals values for [age](https://age-encryption.org) recipients as ASCII-armored
+/// ciphertext, decrypted with `age --decrypt`
+#[cfg(feature = "age")]
+pub struct AgeSealer {
+    recipients: Vec<age::x25519::Recipient>,
+}
+
+#[cfg(feature = "age")]
+impl AgeSealer {
+    /// Parses `age1...` recipients
+    pub fn new(recipients: &[impl AsRef<str>]) -> Result<Self> {
+        Ok(AgeSealer {
+            recipients: parse_recipients(recipients)?,
+        })
+    }
+}
+
+/// Parses `age1...` recipients, of which there must be at least one
+#[cfg(feature = "age")]
+pub fn parse_recipients(recipients: &[impl AsRef<str>]) -> Result<Vec<age::x25519::Recipient>> {
+    let recipients = recipients
+        .iter()
+        .map(|recipient| {
+            recipient
+                .as_ref()
+                .parse()
+                .map_err(|err| anyhow!("Invalid age recipient '{}': {}", recipient.as_ref(), err))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if recipients.is_empty() {
+        return Err(anyhow!("At least one age recipient is required"));
+    }
+
+    Ok(recipients)
+}
+
+#[cfg(feature = "age")]
+impl SealHook for AgeSealer {
+    fn seal(&self, key: &str, value: &str) -> Result<String> {
+        use std::io::Write;
+
+        use age::armor::{ArmoredWriter, Format};
+
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|recipient| recipient as &dyn age::Recipient);
+        let encryptor = age::Encryptor::with_recipients(recipients)
+            .map_err(|err| anyhow!("Failed to seal {}: {}", key, err))?;
+
+        let mut output = Vec::new();
+        let armored = ArmoredWriter::wrap_output(&mut output, Format::AsciiArmor)?;
+        let mut writer = encryptor.wrap_output(armored)?;
+        writer.write_all(value.as_bytes())?;
+        writer.finish()?.finish()?;
+
+        Ok(String::from_utf8(output)?.trim_end().to_string())
+    }
+}
+
+#[cfg(all(test, feature = "age"))]
+mod tests {
+    use std::io::Read;
+
+    use age::{Decryptor, armor::ArmoredReader, x25519::Identity};
+
+    use crate::variable::Variable;
+
+    use super::*;
+
+    #[test]
+    fn test_seals_marked_values() {
+        let identity = Identity::generate();
+        let sealer = AgeSealer::new(&[identity.to_public().to_string()]).unwrap();
+
+        let mut variables: Variables = vec![
+            Variable {
+                key: "TOKEN".to_string(),
+                value: Some("hunter2".to_string()),
+                seal: true,
+                ..Default::default()
+            },
+            Variable {
+                key: "PORT".to_string(),
+                default: Some("8080".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        seal_marked(&mut variables, &sealer).unwrap();
+
+        let sealed = variables[0].value.as_deref().unwrap();
+        assert!(sealed.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert_eq!(variables[1].default.as_deref(), Some("8080"));
+
+        let decryptor = Decryptor::new(ArmoredReader::new(sealed.as_bytes())).unwrap();
+        let mut plaintext = String::new();
+        decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .unwrap()
+            .read_to_string(&mut plaintext)
+            .unwrap();
+        assert_eq!(plaintext, "hunter2");
+
+        assert!(AgeSealer::new(&["age1invalid"]).is_err());
+    }
+}
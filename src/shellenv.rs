@@ -0,0 +1,131 @@
+//! Assignments for `eval "$(awsm-env shellenv)"` in shell profiles, in the syntax of the
+//! shell that runs the command
+
+use std::{env, path::Path};
+
+use crate::variable::Variables;
+
+/// Shell whose syntax to print
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    /// Also used for other POSIX shells such as `sh` and `dash`
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    /// Recognizes a shell from a process or executable name, e.g. `-zsh` or `/bin/bash`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = Path::new(name.trim().trim_start_matches('-'))
+            .file_stem()?
+            .to_str()?;
+
+        match name {
+            "bash" | "sh" | "dash" | "ksh" | "ash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "pwsh" | "powershell" => Some(Shell::Powershell),
+            _ => None,
+        }
+    }
+
+    /// Detects the shell running this process from the parent process, falling back to
+    /// `$SHELL`. On Windows, PowerShell is assumed when `PSModulePath` is set.
+    pub fn detect() -> Option<Self> {
+        parent_name()
+            .and_then(|name| Shell::from_name(&name))
+            .or_else(|| Shell::from_name(&env::var("SHELL").ok()?))
+            .or_else(|| {
+                (cfg!(windows) && env::var_os("PSModulePath").is_some())
+                    .then_some(Shell::Powershell)
+            })
+    }
+
+    /// Quotes `value` so the shell takes it literally
+    pub fn quote(&self, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", value.replace('\'', r"'\''")),
+            Shell::Fish => format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'")),
+            Shell::Powershell => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+
+    /// Assigns and exports `key` in the shell, with `value` quoted literally
+    pub fn assignment(&self, key: &str, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}={}", key, self.quote(value)),
+            Shell::Fish => format!("set -gx {} {};", key, self.quote(value)),
+            Shell::Powershell => format!("$env:{} = {}", key, self.quote(value)),
+        }
+    }
+
+    /// Assignments for the keys that have a value
+    pub fn format(&self, variables: &Variables) -> String {
+        variables
+            .iter()
+            .filter_map(|var| {
+                let value = var.value.as_deref().or(var.default.as_deref())?;
+                Some(format!("{}\n", self.assignment(&var.key, value)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parent_name() -> Option<String> {
+    let ppid = std::os::unix::process::parent_id();
+    std::fs::read_to_string(format!("/proc/{}/comm", ppid)).ok()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parent_name() -> Option<String> {
+    let ppid = std::os::unix::process::parent_id();
+    let output = std::process::Command::new("ps")
+        .args(["-o", "comm=", "-p", &ppid.to_string()])
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(not(unix))]
+fn parent_name() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_from_name() {
+        assert_eq!(Shell::from_name("-zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_name("/usr/bin/fish\n"), Some(Shell::Fish));
+        assert_eq!(Shell::from_name("dash"), Some(Shell::Bash));
+        assert_eq!(Shell::from_name("pwsh.exe"), Some(Shell::Powershell));
+        assert_eq!(Shell::from_name("cargo"), None);
+    }
+
+    #[test]
+    fn test_shell_assignments() {
+        let value = r"it's $HOME \n";
+
+        assert_eq!(
+            Shell::Bash.assignment("KEY", value),
+            r"export KEY='it'\''s $HOME \n'"
+        );
+        assert_eq!(
+            Shell::Fish.assignment("KEY", value),
+            r"set -gx KEY 'it\'s $HOME \\n';"
+        );
+        assert_eq!(
+            Shell::Powershell.assignment("KEY", value),
+            r"$env:KEY = 'it''s $HOME \n'"
+        );
+    }
+}
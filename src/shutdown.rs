@@ -0,0 +1,75 @@
+//! Graceful shutdown on SIGINT and SIGTERM
+
+use tokio_util::sync::CancellationToken;
+
+/// Exit code used when interrupted, as for shells killed by SIGINT
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// Returns a token that is cancelled on the first SIGINT or SIGTERM. Commands stop on
+/// their own once it's cancelled, so temporary files and the like are still cleaned up.
+///
+/// Installs process-wide handlers, so only binaries should call it. Must be called from
+/// within a tokio runtime.
+pub fn on_signal() -> CancellationToken {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        cancel.cancel();
+    });
+
+    token
+}
+
+/// Completes on the next SIGINT or SIGTERM, or their Windows equivalents
+pub async fn wait_for_signal() {
+    tokio::select! {
+        _ = ctrl_c() => {}
+        _ = terminate() => {}
+    }
+}
+
+/// Never completes if the handler can't be installed, rather than acting as if a
+/// signal arrived
+async fn ctrl_c() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending().await
+    }
+}
+
+#[cfg(unix)]
+async fn terminate() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            terminate.recv().await;
+        }
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// Ctrl+Break, closing the console and system shutdown, which is how service wrappers
+/// and Task Scheduler stop console programs
+#[cfg(windows)]
+async fn terminate() {
+    use tokio::signal::windows::{ctrl_break, ctrl_close, ctrl_shutdown};
+
+    let (Ok(mut ctrl_break), Ok(mut ctrl_close), Ok(mut ctrl_shutdown)) =
+        (ctrl_break(), ctrl_close(), ctrl_shutdown())
+    else {
+        return std::future::pending().await;
+    };
+
+    tokio::select! {
+        _ = ctrl_break.recv() => {}
+        _ = ctrl_close.recv() => {}
+        _ = ctrl_shutdown.recv() => {}
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn terminate() {
+    std::future::pending().await
+}
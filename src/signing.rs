@@ -0,0 +1,331 @@
+//! [Minisign](https://jedisct1.github.io/minisign/) signatures over specs, so that a spec
+//! fetched from a URL or taken from a shared repository is only resolved when it was
+//! signed by a trusted key. Keys made with `minisign -G` and signatures made with
+//! `minisign -S` and `awsm-env sign` are interchangeable. Verification is done by the
+//! `minisign-verify` crate, only signing is implemented here.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use blake2::{Blake2b512, Digest, digest::consts::U32};
+use ed25519_dalek::{Signer, SigningKey};
+
+/// Extension of the signature file kept next to a spec
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// Algorithm of keys
+const ALG_ED25519: &[u8; 2] = b"Ed";
+/// Signature algorithm of signatures over the BLAKE2b-512 hash of the message
+const ALG_PREHASHED: &[u8; 2] = b"ED";
+const KDF_SCRYPT: &[u8; 2] = b"Sc";
+const KDF_NONE: &[u8; 2] = &[0, 0];
+const CHK_BLAKE2B: &[u8; 2] = b"B2";
+
+const UNTRUSTED_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_PREFIX: &str = "trusted comment: ";
+
+type Blake2b256 = blake2::Blake2b<U32>;
+
+/// A public key that signatures are checked against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    // minisign-verify doesn't expose the ID, it's kept to name untrusted keys
+    key_id: [u8; 8],
+    key: minisign_verify::PublicKey,
+}
+
+impl PublicKey {
+    /// Parses a key as printed by `minisign -G`, either the base64 line alone or the
+    /// contents of a `.pub` file
+    pub fn parse(input: &str) -> Result<Self> {
+        let line = last_line(input);
+        let key = minisign_verify::PublicKey::from_base64(line)
+            .map_err(|err| anyhow!("Invalid public key: {}", err))?;
+        let key_id = decode_line(line)?[2..10].try_into()?;
+        Ok(PublicKey { key_id, key })
+    }
+
+    /// The key ID as minisign prints it
+    pub fn id(&self) -> String {
+        format_key_id(&self.key_id)
+    }
+}
+
+/// A secret key that specs are signed with
+pub struct SecretKey {
+    key_id: [u8; 8],
+    key: SigningKey,
+}
+
+impl SecretKey {
+    /// Parses the contents of a key file written by `minisign -G`. `password` is only
+    /// called for keys that are encrypted.
+    pub fn parse(input: &str, password: impl FnOnce() -> Result<String>) -> Result<Self> {
+        let bytes = decode_line(last_line(input)).context("Invalid secret key")?;
+        if bytes.len() != 158 {
+            return Err(anyhow!("Invalid secret key"));
+        }
+
+        let (alg, kdf, chk) = (&bytes[0..2], &bytes[2..4], &bytes[4..6]);
+        if alg != ALG_ED25519 || chk != CHK_BLAKE2B {
+            return Err(anyhow!("Unsupported secret key algorithm"));
+        }
+
+        let salt = &bytes[6..38];
+        let opslimit = u64::from_le_bytes(bytes[38..46].try_into()?);
+        let memlimit = u64::from_le_bytes(bytes[46..54].try_into()?);
+        let mut keynum = bytes[54..158].to_vec();
+
+        match kdf {
+            kdf if kdf == KDF_SCRYPT => {
+                let stream = derive_stream(&password()?, salt, opslimit, memlimit)?;
+                keynum
+                    .iter_mut()
+                    .zip(stream)
+                    .for_each(|(byte, mask)| *byte ^= mask);
+            }
+            kdf if kdf == KDF_NONE => {}
+            _ => return Err(anyhow!("Unsupported secret key encryption")),
+        }
+
+        let (key_id, sk, checksum) = (&keynum[0..8], &keynum[8..72], &keynum[72..104]);
+        let expected = Blake2b256::new()
+            .chain_update(alg)
+            .chain_update(key_id)
+            .chain_update(sk)
+            .finalize();
+        if expected.as_slice() != checksum {
+            return Err(anyhow!("Wrong password for the secret key"));
+        }
+
+        // Minisign stores the seed followed by the public key
+        let key = SigningKey::from_bytes(sk[0..32].try_into()?);
+        if key.verifying_key().as_bytes() != &sk[32..64] {
+            return Err(anyhow!("Invalid secret key"));
+        }
+
+        Ok(SecretKey {
+            key_id: key_id.try_into()?,
+            key,
+        })
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        let line = [
+            &ALG_ED25519[..],
+            &self.key_id,
+            self.key.verifying_key().as_bytes(),
+        ]
+        .concat();
+        PublicKey::parse(&STANDARD.encode(line)).expect("secret keys hold a valid public key")
+    }
+}
+
+/// Key derivation of encrypted secret keys, scrypt with the parameters libsodium picks
+/// for `opslimit` and `memlimit`
+fn derive_stream(password: &str, salt: &[u8], opslimit: u64, memlimit: u64) -> Result<Vec<u8>> {
+    let opslimit = opslimit.max(32768);
+    let r = 8u64;
+    let log_n_for = |max_n: u64| {
+        (1..63)
+            .find(|log_n| 1u64 << log_n > max_n / 2)
+            .unwrap_or(63)
+    };
+
+    let (log_n, p) = match opslimit < memlimit / 32 {
+        true => (log_n_for(opslimit / (r * 4)), 1),
+        false => {
+            let log_n = log_n_for(memlimit / (r * 128));
+            let max_rp = ((opslimit / 4) >> log_n).min(0x3fffffff);
+            (log_n, max_rp / r)
+        }
+    };
+
+    // The length in `Params` is only used for password hashes, `scrypt` fills `stream`
+    let params = scrypt::Params::new(
+        log_n as u8,
+        r as u32,
+        p as u32,
+        scrypt::Params::RECOMMENDED_LEN,
+    )
+    .map_err(|_| anyhow!("Unsupported secret key encryption parameters"))?;
+    let mut stream = vec![0; 104];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut stream)
+        .map_err(|_| anyhow!("Failed to decrypt the secret key"))?;
+
+    Ok(stream)
+}
+
+/// Where the signature of `spec` is kept, next to it with [`SIGNATURE_EXTENSION`]
+/// appended. Also used for URLs.
+pub fn signature_path(spec: &Path) -> PathBuf {
+    let mut path = spec.as_os_str().to_owned();
+    path.push(".");
+    path.push(SIGNATURE_EXTENSION);
+    path.into()
+}
+
+/// Signs `message` with `key`, returning the contents of a `.minisig` file.
+/// `trusted_comment` is signed along with it and shown when it's verified.
+pub fn sign(message: &[u8], key: &SecretKey, trusted_comment: &str) -> Result<String> {
+    if trusted_comment.contains(['\n', '\r']) {
+        return Err(anyhow!("The trusted comment must be a single line"));
+    }
+
+    let signature = key.key.sign(&Blake2b512::digest(message));
+    let global = key
+        .key
+        .sign(&[&signature.to_bytes()[..], trusted_comment.as_bytes()].concat());
+
+    let line = [&ALG_PREHASHED[..], &key.key_id, &signature.to_bytes()].concat();
+    Ok(format!(
+        "{}signature from awsm-env secret key\n{}\n{}{}\n{}\n",
+        UNTRUSTED_PREFIX,
+        STANDARD.encode(line),
+        TRUSTED_PREFIX,
+        trusted_comment,
+        STANDARD.encode(global.to_bytes())
+    ))
+}
+
+/// Checks that `signature`, the contents of a `.minisig` file, is a signature over
+/// `message` by one of `keys`, returning its trusted comment
+pub fn verify(message: &[u8], signature: &str, keys: &[PublicKey]) -> Result<String> {
+    let parsed = minisign_verify::Signature::decode(signature)
+        .map_err(|err| anyhow!("Invalid signature: {}", err))?;
+    let line = signature.lines().nth(1).unwrap_or_default();
+    let key_id: [u8; 8] = decode_line(line)?[2..10].try_into()?;
+    let key = keys
+        .iter()
+        .find(|key| key.key_id == key_id)
+        .ok_or_else(|| {
+            anyhow!(
+                "Signed by key {}, which isn't trusted",
+                format_key_id(&key_id)
+            )
+        })?;
+
+    // Signatures over the message itself are still made by `minisign -l`
+    key.key
+        .verify(message, &parsed, true)
+        .map_err(|err| match err {
+            minisign_verify::Error::InvalidSignature => {
+                anyhow!("The signature doesn't match, the file or its trusted comment was modified")
+            }
+            err => anyhow!("Invalid signature: {}", err),
+        })?;
+
+    Ok(parsed.trusted_comment().to_string())
+}
+
+/// The base64 line of a key file, skipping its untrusted comment
+fn last_line(input: &str) -> &str {
+    input
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .unwrap_or_default()
+}
+
+fn decode_line(line: &str) -> Result<Vec<u8>> {
+    Ok(STANDARD.decode(line.trim())?)
+}
+
+fn format_key_id(key_id: &[u8; 8]) -> String {
+    format!("{:016X}", u64::from_le_bytes(*key_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "untrusted comment: minisign public key E7620F1842B4E81F\nRWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\n";
+
+    #[test]
+    fn test_verifies_minisign_signatures() {
+        let key = PublicKey::parse(PUBLIC_KEY).unwrap();
+        assert_eq!(key.id(), "E7620F1842B4E81F");
+
+        let signature = "untrusted comment: signature from minisign secret key\nRUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=\ntrusted comment: timestamp:1633700835\tfile:test\tprehashed\nwLMDjy9FLAuxZ3q4NlEvkgtyhrr0gtTu6KC4KBJdITbbOeAi1zBIYo0v4iTgt8jJpIidRJnp94ABQkJAgAooBQ==\n";
+        assert_eq!(
+            verify(b"test", signature, std::slice::from_ref(&key)).unwrap(),
+            "timestamp:1633700835\tfile:test\tprehashed"
+        );
+
+        let err = verify(b"Test", signature, std::slice::from_ref(&key)).unwrap_err();
+        assert!(err.to_string().contains("was modified"));
+        let tampered = signature.replace("file:test", "file:other");
+        let err = verify(b"test", &tampered, &[key]).unwrap_err();
+        assert!(err.to_string().contains("was modified"));
+        let err = verify(b"test", signature, &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Signed by key E7620F1842B4E81F, which isn't trusted"
+        );
+    }
+
+    /// Contents of a secret key file for `seed`, with `keynum` XORed with `stream`
+    fn secret_key_file(seed: [u8; 32], kdf: &[u8; 2], params: &[u8], stream: &[u8]) -> String {
+        let signing = SigningKey::from_bytes(&seed);
+        let key_id = [1, 2, 3, 4, 5, 6, 7, 8];
+        let sk = [&seed[..], signing.verifying_key().as_bytes()].concat();
+        let checksum = Blake2b256::new()
+            .chain_update(ALG_ED25519)
+            .chain_update(key_id)
+            .chain_update(&sk)
+            .finalize();
+        let keynum: Vec<u8> = [&key_id[..], &sk, &checksum]
+            .concat()
+            .into_iter()
+            .zip(stream.iter().chain(std::iter::repeat(&0)))
+            .map(|(byte, mask)| byte ^ mask)
+            .collect();
+        let bytes = [&ALG_ED25519[..], kdf, CHK_BLAKE2B, params, &keynum].concat();
+        format!(
+            "untrusted comment: minisign secret key\n{}\n",
+            STANDARD.encode(bytes)
+        )
+    }
+
+    #[test]
+    fn test_signs_with_unencrypted_keys() {
+        let input = secret_key_file([7; 32], KDF_NONE, &[0; 48], &[]);
+
+        let key = SecretKey::parse(&input, || panic!("Not encrypted")).unwrap();
+        assert_eq!(key.public_key().id(), "0807060504030201");
+
+        let signature = sign(b"PORT=8080\n", &key, "file:.env.example").unwrap();
+        assert_eq!(
+            verify(b"PORT=8080\n", &signature, &[key.public_key()]).unwrap(),
+            "file:.env.example"
+        );
+        assert!(verify(b"PORT=8081\n", &signature, &[key.public_key()]).is_err());
+        assert!(sign(b"", &key, "a\nb").is_err());
+    }
+
+    #[test]
+    fn test_signs_with_encrypted_keys() {
+        // scrypt with N = 2^14, r = 8 and p = 1, what libsodium picks for these limits,
+        // computed independently with Python's hashlib.scrypt
+        let stream = STANDARD
+            .decode("W5SIzUOJ1LM5aykSlWdZ1Zrnuz7LY/OcC2Yr64oa2Tvj1O2EHWcoYQJKhi7V+wyZuNc0s3s7ZZiDo7VD9js4N6zVrBLC/x1PcitUVfbpHwqXjwchFg2pHy3UGOdjj9/c2EL0UaViDHc=")
+            .unwrap();
+        let params = [
+            &[5u8; 32][..],
+            &524288u64.to_le_bytes(),
+            &16777216u64.to_le_bytes(),
+        ]
+        .concat();
+        let input = secret_key_file([9; 32], KDF_SCRYPT, &params, &stream);
+
+        let key = SecretKey::parse(&input, || Ok("correct horse".to_string())).unwrap();
+        let signature = sign(b"PORT=8080\n", &key, "file:.env.example").unwrap();
+        assert!(verify(b"PORT=8080\n", &signature, &[key.public_key()]).is_ok());
+
+        let Err(err) = SecretKey::parse(&input, || Ok("wrong".to_string())) else {
+            panic!("expected the wrong password to fail");
+        };
+        assert_eq!(err.to_string(), "Wrong password for the secret key");
+    }
+}
@@ -0,0 +1,277 @@
+//! Point-in-time copies of resolved values encrypted with [age](https://age-encryption.org),
+//! so a spec can still be rendered while AWS is unavailable
+
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::variable::{Provenance, Variable, Variables};
+
+/// Version of the snapshot format, bumped on incompatible changes
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Resolved entries of a spec along with where their values came from
+#[derive(Debug)]
+pub struct Snapshot {
+    pub created_at: SystemTime,
+    /// Path or URL of the spec the entries were resolved from
+    pub spec: String,
+    pub variables: Variables,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    version: u32,
+    created_at: String,
+    spec: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provenance: Option<EntryProvenance>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude_from: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    no_export: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    seal: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntryProvenance {
+    provider: String,
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    fetched_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    changed_at: Option<String>,
+}
+
+impl Snapshot {
+    pub fn new(spec: impl Into<String>, variables: Variables) -> Self {
+        Snapshot {
+            created_at: SystemTime::now(),
+            spec: spec.into(),
+            variables,
+        }
+    }
+
+    pub fn created_at_rfc3339(&self) -> String {
+        humantime::format_rfc3339_seconds(self.created_at).to_string()
+    }
+
+    /// Encrypts the snapshot for `age1...` recipients
+    pub fn encrypt(&self, recipients: &[impl AsRef<str>]) -> Result<Vec<u8>> {
+        encrypt(&self.to_json()?, recipients)
+    }
+
+    /// Decrypts a snapshot with the identities in an age identity file
+    pub fn decrypt(ciphertext: &[u8], identity_file: &[u8]) -> Result<Self> {
+        Snapshot::from_json(&decrypt(ciphertext, identity_file)?)
+    }
+
+    fn to_json(&self) -> Result<Vec<u8>> {
+        let entries = self
+            .variables
+            .iter()
+            .map(|var| Entry {
+                key: var.key.clone(),
+                value: var.value.clone(),
+                default: var.default.clone(),
+                provenance: var.provenance.as_ref().map(|provenance| EntryProvenance {
+                    provider: provenance.provider.clone(),
+                    id: provenance.id.clone(),
+                    version: provenance.version.clone(),
+                    fetched_at: provenance.fetched_at_rfc3339(),
+                    expires_at: provenance.expires_at_rfc3339(),
+                    changed_at: provenance.changed_at_rfc3339(),
+                }),
+                exclude_from: var.exclude_from.clone(),
+                no_export: var.no_export,
+                seal: var.seal,
+            })
+            .collect();
+
+        let document = Document {
+            version: SNAPSHOT_VERSION,
+            created_at: self.created_at_rfc3339(),
+            spec: self.spec.clone(),
+            entries,
+        };
+
+        Ok(serde_json::to_vec(&document)?)
+    }
+
+    fn from_json(json: &[u8]) -> Result<Self> {
+        let document: Document = serde_json::from_slice(json).context("Invalid snapshot")?;
+        if document.version != SNAPSHOT_VERSION {
+            return Err(anyhow!(
+                "Unsupported snapshot version {}, expected {}",
+                document.version,
+                SNAPSHOT_VERSION
+            ));
+        }
+
+        let variables = document
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let provenance = match entry.provenance {
+                    Some(provenance) => Some(Provenance {
+                        provider: provenance.provider,
+                        id: provenance.id,
+                        version: provenance.version,
+                        fetched_at: humantime::parse_rfc3339(&provenance.fetched_at)?,
+                        expires_at: provenance
+                            .expires_at
+                            .as_deref()
+                            .map(humantime::parse_rfc3339)
+                            .transpose()?,
+                        changed_at: provenance
+                            .changed_at
+                            .as_deref()
+                            .map(humantime::parse_rfc3339)
+                            .transpose()?,
+                    }),
+                    None => None,
+                };
+
+                Ok(Variable {
+                    key: entry.key,
+                    value: entry.value,
+                    default: entry.default,
+                    provenance,
+                    exclude_from: entry.exclude_from,
+                    no_export: entry.no_export,
+                    seal: entry.seal,
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid snapshot")?;
+
+        Ok(Snapshot {
+            created_at: humantime::parse_rfc3339(&document.created_at)
+                .context("Invalid snapshot")?,
+            spec: document.spec,
+            variables: variables.into(),
+        })
+    }
+}
+
+#[cfg(feature = "age")]
+fn encrypt(plaintext: &[u8], recipients: &[impl AsRef<str>]) -> Result<Vec<u8>> {
+    use std::io::Write;
+
+    let recipients = crate::seal::parse_recipients(recipients)?;
+    let encryptor = age::Encryptor::with_recipients(
+        recipients
+            .iter()
+            .map(|recipient| recipient as &dyn age::Recipient),
+    )
+    .map_err(|err| anyhow!("Failed to encrypt snapshot: {}", err))?;
+
+    let mut output = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut output)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?;
+
+    Ok(output)
+}
+
+#[cfg(feature = "age")]
+fn decrypt(ciphertext: &[u8], identity_file: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    use age::{Decryptor, IdentityFile};
+
+    let identities = IdentityFile::from_buffer(identity_file)
+        .context("Invalid age identity file")?
+        .into_identities()
+        .map_err(|err| anyhow!("Invalid age identity file: {}", err))?;
+
+    let decryptor = Decryptor::new(ciphertext).context("Not an age encrypted snapshot")?;
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|identity| identity.as_ref()))
+        .map_err(|err| anyhow!("Failed to decrypt snapshot: {}", err))?;
+
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+
+    Ok(plaintext)
+}
+
+#[cfg(not(feature = "age"))]
+fn encrypt(_: &[u8], _: &[impl AsRef<str>]) -> Result<Vec<u8>> {
+    Err(anyhow!("Snapshots require the age feature"))
+}
+
+#[cfg(not(feature = "age"))]
+fn decrypt(_: &[u8], _: &[u8]) -> Result<Vec<u8>> {
+    Err(anyhow!("Snapshots require the age feature"))
+}
+
+#[cfg(all(test, feature = "age"))]
+mod tests {
+    use std::time::Duration;
+
+    use age::{secrecy::ExposeSecret, x25519::Identity};
+
+    use super::*;
+
+    #[test]
+    fn test_round_trips_encrypted_snapshots() {
+        let identity = Identity::generate();
+        let fetched_at = humantime::parse_rfc3339("2026-01-01T00:00:00Z").unwrap();
+
+        let variables: Variables = vec![
+            Variable {
+                key: "DB_PASSWORD".to_string(),
+                value: Some("hunter2".to_string()),
+                provenance: Some(Provenance {
+                    provider: "aws-sm".to_string(),
+                    id: "prod/db".to_string(),
+                    version: Some("v1".to_string()),
+                    fetched_at,
+                    expires_at: Some(fetched_at + Duration::from_secs(300)),
+                    changed_at: Some(fetched_at - Duration::from_secs(86400)),
+                }),
+                seal: true,
+                ..Default::default()
+            },
+            Variable {
+                key: "PORT".to_string(),
+                default: Some("8080".to_string()),
+                exclude_from: vec!["json".to_string()],
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let snapshot = Snapshot::new(".env.example", variables.to_vec().into());
+        let ciphertext = snapshot
+            .encrypt(&[identity.to_public().to_string()])
+            .unwrap();
+        assert!(!ciphertext.windows(7).any(|window| window == b"hunter2"));
+
+        let identity_file = identity.to_string();
+        let restored =
+            Snapshot::decrypt(&ciphertext, identity_file.expose_secret().as_bytes()).unwrap();
+        assert_eq!(restored.spec, ".env.example");
+        assert_eq!(restored.variables.to_vec(), variables.to_vec());
+
+        let other = Identity::generate().to_string();
+        assert!(Snapshot::decrypt(&ciphertext, other.expose_secret().as_bytes()).is_err());
+    }
+}
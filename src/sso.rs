@@ -0,0 +1,84 @@
+use std::{env, process::Command};
+
+use anyhow::{Context, Result, anyhow};
+
+/// Phrases in SDK errors that indicate the SSO session or its cached token is no longer usable
+const EXPIRED_MARKERS: [&str; 5] = [
+    "expired",
+    "unauthorized",
+    "token not found",
+    "failed to load",
+    "invalid",
+];
+
+/// Returns the profile whose SSO session has to be refreshed if `err` was caused by an
+/// expired or missing AWS SSO session
+pub fn expired_sso_profile(err: &anyhow::Error) -> Option<String> {
+    let message = format!("{:#}", err).to_lowercase();
+
+    let expired = message
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| word == "sso")
+        && EXPIRED_MARKERS
+            .iter()
+            .any(|marker| message.contains(marker));
+
+    expired.then(current_profile)
+}
+
+fn current_profile() -> String {
+    ["AWS_PROFILE", "AWS_DEFAULT_PROFILE"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|p| !p.is_empty()))
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// The command that refreshes the SSO session of `profile`
+pub fn login_command(profile: &str) -> String {
+    format!("aws sso login --profile {}", profile)
+}
+
+/// Runs `aws sso login` for `profile`, letting it interact with the terminal
+pub fn login(profile: &str) -> Result<()> {
+    let status = Command::new("aws")
+        .args(["sso", "login", "--profile", profile])
+        .status()
+        .context("Failed to run the AWS CLI")?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "`{}` failed with {}",
+            login_command(profile),
+            status
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_expired_sso_session() {
+        let err = anyhow!("the SSO token has expired and cannot be refreshed")
+            .context("dispatch failure")
+            .context("Failed to fetch secrets");
+
+        assert!(expired_sso_profile(&err).is_some());
+    }
+
+    #[test]
+    fn test_ignores_other_errors() {
+        let err =
+            anyhow!("ResourceNotFoundException: Secrets Manager can't find the specified secret")
+                .context("Failed to fetch secrets");
+        assert!(expired_sso_profile(&err).is_none());
+
+        let err = anyhow!("the security token included in the request is expired");
+        assert!(expired_sso_profile(&err).is_none());
+
+        let err = anyhow!("processor token has expired");
+        assert!(expired_sso_profile(&err).is_none());
+    }
+}
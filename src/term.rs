@@ -0,0 +1,140 @@
+//! Styling for messages written to stderr. Colors and the spinner are only used when
+//! stderr is a terminal and `NO_COLOR` isn't set.
+
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const RED: &str = "1;31";
+const YELLOW: &str = "1;33";
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Whether stderr should be styled, see <https://no-color.org>
+pub fn styled() -> bool {
+    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+fn paint(code: &str, text: &str, enabled: bool) -> String {
+    match enabled {
+        true => format!("\x1b[{}m{}\x1b[0m", code, text),
+        false => text.to_owned(),
+    }
+}
+
+/// Prints a warning to stderr
+pub fn warn(message: impl Display) {
+    eprintln!("{} {}", paint(YELLOW, "Warning:", styled()), message);
+}
+
+/// Prints an error and its causes to stderr
+pub fn error(err: &anyhow::Error) {
+    write_error(&mut io::stderr(), err);
+}
+
+/// Writes an error and its causes to `out`, styled when stderr is a terminal
+pub fn write_error(out: &mut dyn Write, err: &anyhow::Error) {
+    let _ = writeln!(out, "{} {:?}", paint(RED, "Error:", styled()), err);
+}
+
+/// Formats rows as a table with left-aligned columns separated by two spaces
+pub fn format_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+    let header = header.map(String::from);
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Shows a message with a spinner on stderr until dropped
+pub struct Spinner {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Starts the spinner, does nothing when stderr isn't styled
+    pub fn start(message: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = styled().then(|| {
+            let stop = stop.clone();
+            let message = message.to_owned();
+
+            thread::spawn(move || {
+                for frame in FRAMES.iter().cycle() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    eprint!("\r{} {}", frame, message);
+                    let _ = io::stderr().flush();
+                    thread::park_timeout(Duration::from_millis(80));
+                }
+
+                eprint!("\r\x1b[2K");
+            })
+        });
+
+        Spinner { stop, handle }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_table() {
+        let rows = [
+            ["a".to_string(), "long value".to_string()],
+            ["longer".to_string(), "".to_string()],
+        ];
+
+        assert_eq!(
+            format_table(["ONE", "TWO"], &rows),
+            "ONE     TWO\na       long value\nlonger\n"
+        );
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint(YELLOW, "Warning:", true), "\x1b[1;33mWarning:\x1b[0m");
+        assert_eq!(paint(YELLOW, "Warning:", false), "Warning:");
+    }
+}
@@ -0,0 +1,353 @@
+use std::{io, time::Duration};
+
+use anyhow::{Context, Result};
+use arboard::Clipboard;
+use indexmap::IndexMap;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+};
+
+use crate::{
+    cli::{ResolveArgs, Settings},
+    provider::{AwsOptions, Provider},
+    resolve::Resolver,
+    variable::{Variable, Variables},
+};
+
+const MASK: &str = "••••••••";
+const HELP: &str = "↑/↓ move · v toggle values · c copy · r refresh · q quit";
+/// How long to wait for input before checking again, keeping the runtime's threads free
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Resolves the spec described by `args` and opens a terminal UI to browse the result
+pub async fn run(args: &ResolveArgs, settings: &Settings) -> Result<()> {
+    let variables = args.load(settings).await?;
+
+    let mut terminal = ratatui::init();
+    let result = App::new(variables, args.placeholders(), settings.aws.clone())
+        .run(&mut terminal)
+        .await;
+    ratatui::restore();
+
+    result
+}
+
+struct App {
+    variables: Vec<Variable>,
+    placeholders: IndexMap<String, String>,
+    /// Used to load the AWS configuration for refreshes
+    aws: AwsOptions,
+    table: TableState,
+    show_values: bool,
+    clipboard: Option<Clipboard>,
+    message: Option<String>,
+}
+
+impl App {
+    fn new(variables: Variables, placeholders: IndexMap<String, String>, aws: AwsOptions) -> Self {
+        Self {
+            variables: variables.into_iter().collect(),
+            placeholders,
+            aws,
+            table: TableState::default().with_selected(0),
+            show_values: false,
+            clipboard: None,
+            message: None,
+        }
+    }
+
+    async fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Some(Event::Key(key)) = tokio::task::spawn_blocking(next_event).await?? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::Char('v') => self.show_values = !self.show_values,
+                KeyCode::Char('c') => self.copy_selected(),
+                KeyCode::Char('r') => self.refresh_selected().await,
+                _ => (),
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, footer_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let rows = self.variables.iter().map(|var| {
+            let value = match var.value.as_ref().or(var.default.as_ref()) {
+                Some(_) if !self.show_values => MASK,
+                Some(value) => value.as_str(),
+                None => "",
+            };
+
+            Row::new(vec![
+                var.key.clone(),
+                provider(var),
+                status(var).to_owned(),
+                value.to_owned(),
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(35),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["Key", "Provider", "Status", "Value"]).bold())
+        .block(Block::bordered().title(" awsm-env "))
+        .row_highlight_style(Style::new().reversed());
+
+        // The selected key's description replaces the help until something happens
+        let description = self
+            .table
+            .selected()
+            .and_then(|idx| self.variables.get(idx))
+            .and_then(|var| var.description.clone());
+        let footer = self
+            .message
+            .as_deref()
+            .or(description.as_deref())
+            .unwrap_or(HELP);
+
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+        frame.render_widget(Paragraph::new(footer).dim(), footer_area);
+    }
+
+    fn selected(&mut self) -> Option<&mut Variable> {
+        self.table
+            .selected()
+            .and_then(|idx| self.variables.get_mut(idx))
+    }
+
+    fn copy_selected(&mut self) {
+        let Some(var) = self.selected() else {
+            return;
+        };
+
+        let Some(value) = var.value.clone().or(var.default.clone()) else {
+            self.message = Some(format!("{} has no value to copy", var.key));
+            return;
+        };
+        let key = var.key.clone();
+
+        let copied = match self.clipboard.as_mut() {
+            Some(clipboard) => clipboard.set_text(value).context("Failed to copy"),
+            None => Clipboard::new()
+                .context("Failed to access clipboard")
+                .and_then(|mut clipboard| {
+                    clipboard.set_text(value).context("Failed to copy")?;
+                    self.clipboard = Some(clipboard);
+                    Ok(())
+                }),
+        };
+
+        self.message = Some(match copied {
+            Ok(()) => format!("Copied {} to clipboard", key),
+            Err(err) => format!("{:#}", err),
+        });
+    }
+
+    async fn refresh_selected(&mut self) {
+        let placeholders = self.placeholders.clone();
+        let aws = self.aws.clone();
+        let Some(var) = self.selected() else {
+            return;
+        };
+
+        if var.provider_config.is_none() {
+            self.message = Some(format!("{} is not backed by a secret", var.key));
+            return;
+        }
+
+        let refreshed = match Resolver::new(&aws).await {
+            Ok(resolver) => refetch(&resolver, var, placeholders).await,
+            Err(err) => Err(err),
+        };
+        self.message = Some(apply_refresh(var, refreshed));
+    }
+}
+
+/// Fetches the value of `var` again, keeping its JSON field, version pin and mirror
+async fn refetch<SM: Provider, PS: Provider>(
+    resolver: &Resolver<SM, PS>,
+    var: &Variable,
+    placeholders: IndexMap<String, String>,
+) -> Result<Option<String>> {
+    let mut single: Variables = vec![Variable {
+        value: None,
+        provenance: None,
+        ..var.clone()
+    }]
+    .into();
+
+    resolver.resolve(&mut single, placeholders).await?;
+    Ok(single.into_iter().next().and_then(|var| var.value))
+}
+
+/// Sets the refreshed value of `var`, keeping the previous one when the secret is gone,
+/// and returns the message to show
+fn apply_refresh(var: &mut Variable, refreshed: Result<Option<String>>) -> String {
+    match refreshed {
+        Ok(Some(value)) => {
+            var.value = Some(value);
+            format!("Refreshed {}", var.key)
+        }
+        Ok(None) => format!(
+            "Secret {} of {} wasn't found, keeping the previous value",
+            provider(var),
+            var.key
+        ),
+        Err(err) => format!("Failed to refresh {}: {:#}", var.key, err),
+    }
+}
+
+/// Waits up to [`POLL_INTERVAL`] for an input event
+fn next_event() -> io::Result<Option<Event>> {
+    match event::poll(POLL_INTERVAL)? {
+        true => event::read().map(Some),
+        false => Ok(None),
+    }
+}
+
+fn provider(var: &Variable) -> String {
+    var.provider_config
+        .as_ref()
+        .map(|config| format!("{} {}", config.name(), config.id()))
+        .unwrap_or_default()
+}
+
+fn status(var: &Variable) -> &'static str {
+    match (&var.value, &var.default) {
+        (Some(_), _) => "resolved",
+        (None, Some(_)) => "default",
+        (None, None) if var.required && var.provider_config.is_some() => "missing",
+        (None, None) => "empty",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        provider::{SecretMetadata, SecretResult, SecretVersion},
+        variable::ProviderConfig,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let resolved = Variable {
+            key: "KEY1".to_string(),
+            value: Some("value1".to_string()),
+            default: Some("def".to_string()),
+            ..Default::default()
+        };
+        let default = Variable {
+            key: "KEY2".to_string(),
+            default: Some("def".to_string()),
+            ..Default::default()
+        };
+        let missing = Variable {
+            key: "KEY3".to_string(),
+            required: true,
+            provider_config: Some(ProviderConfig::AwsSecretsManager("foo".to_string())),
+            ..Default::default()
+        };
+        let empty = Variable {
+            key: "KEY4".to_string(),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("foo".to_string())),
+            ..Default::default()
+        };
+
+        assert_eq!(status(&resolved), "resolved");
+        assert_eq!(status(&default), "default");
+        assert_eq!(status(&missing), "missing");
+        assert_eq!(status(&empty), "empty");
+    }
+
+    #[test]
+    fn test_refresh_keeps_value_of_missing_secret() {
+        let mut var = Variable {
+            key: "DB_PASSWORD".to_string(),
+            value: Some("old".to_string()),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("app/db".to_string())),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            apply_refresh(&mut var, Ok(None)),
+            "Secret aws-sm app/db of DB_PASSWORD wasn't found, keeping the previous value"
+        );
+        assert_eq!(var.value.as_deref(), Some("old"));
+
+        assert_eq!(
+            apply_refresh(&mut var, Ok(Some("new".to_string()))),
+            "Refreshed DB_PASSWORD"
+        );
+        assert_eq!(var.value.as_deref(), Some("new"));
+    }
+
+    /// Every secret is a JSON object whose password tells whether a version was pinned
+    struct JsonProvider;
+
+    impl JsonProvider {
+        fn found(password: &str) -> SecretResult {
+            SecretResult::Found {
+                value: format!(r#"{{"user":"admin","password":"{}"}}"#, password),
+                version: None,
+                metadata: SecretMetadata::default(),
+            }
+        }
+    }
+
+    impl Provider for JsonProvider {
+        async fn provide_secrets(&self, ids: Vec<String>) -> Result<Vec<SecretResult>> {
+            Ok(ids.iter().map(|_| Self::found("current")).collect())
+        }
+
+        async fn provide_secret_versions(
+            &self,
+            ids: Vec<(String, SecretVersion)>,
+        ) -> Result<Vec<SecretResult>> {
+            Ok(ids.iter().map(|_| Self::found("pinned")).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refetch_keeps_field_and_version() {
+        let resolver = Resolver::with_providers(JsonProvider, JsonProvider);
+        let mut var = Variable {
+            key: "DB_PASSWORD".to_string(),
+            value: Some("old".to_string()),
+            provider_config: Some(ProviderConfig::AwsSecretsManager("prod/db".to_string())),
+            json_key: Some("password".to_string()),
+            ..Default::default()
+        };
+
+        let value = refetch(&resolver, &var, IndexMap::new()).await.unwrap();
+        assert_eq!(value.as_deref(), Some("current"));
+
+        var.version = Some(SecretVersion::Stage("AWSPREVIOUS".to_string()));
+        let value = refetch(&resolver, &var, IndexMap::new()).await.unwrap();
+        assert_eq!(value.as_deref(), Some("pinned"));
+    }
+}
@@ -0,0 +1,461 @@
+//! Constraints on values declared with `@validate`, `@min-length` and `@unique`, checked
+//! after resolution to catch secrets that were rotated to something unusable, along with
+//! checks of the spec's internal consistency and of key names against where they end up
+
+use std::{fmt::Display, str::FromStr, sync::LazyLock, time::SystemTime};
+
+use anyhow::{Context, Error, Result, anyhow};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::variable::Variables;
+
+/// A constraint on the value of a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validation {
+    /// The value must match the pattern, `@validate regex:<pattern>`
+    Regex(Pattern),
+    /// The value must have at least this many characters, `@min-length <n>` or
+    /// `@validate min-length:<n>`
+    MinLength(usize),
+}
+
+impl Validation {
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            Validation::Regex(pattern) => pattern.0.is_match(value),
+            Validation::MinLength(min) => value.chars().count() >= *min,
+        }
+    }
+}
+
+impl FromStr for Validation {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        match rule.split_once(':') {
+            Some(("regex", pattern)) => Ok(Validation::Regex(pattern.parse()?)),
+            Some(("min-length", min)) => Ok(Validation::MinLength(
+                min.parse().context("Expected a number of characters")?,
+            )),
+            _ => Err(anyhow!(
+                "Unknown rule '{}', expected `regex:<pattern>` or `min-length:<n>`",
+                rule
+            )),
+        }
+    }
+}
+
+impl Display for Validation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Validation::Regex(pattern) => write!(f, "regex:{}", pattern.0),
+            Validation::MinLength(min) => write!(f, "min-length:{}", min),
+        }
+    }
+}
+
+/// A compiled regular expression, compared by its source
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        Ok(Pattern(Regex::new(pattern)?))
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+/// Checks the value of every key against its validations. Keys without a value are
+/// skipped. The error lists every failure but never the values themselves.
+pub fn validate(variables: &Variables) -> Result<()> {
+    let failures = variables
+        .iter()
+        .flat_map(|var| {
+            let value = var.value.as_deref().or(var.default.as_deref());
+
+            var.validations
+                .iter()
+                .filter(move |validation| value.is_some_and(|value| !validation.is_valid(value)))
+                .map(|validation| format!("{} fails {}", var.key, validation))
+        })
+        .collect::<Vec<_>>();
+
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!("Invalid values: {}", failures.join(", "))),
+    }
+}
+
+/// Checks that keys in the same `@unique` group have different values. Keys without a
+/// value are skipped.
+pub fn check_unique(variables: &Variables) -> Result<()> {
+    let mut groups: IndexMap<&str, IndexMap<&str, Vec<&str>>> = IndexMap::new();
+
+    for var in variables.iter() {
+        let (Some(group), Some(value)) = (
+            var.unique.as_deref(),
+            var.value.as_deref().or(var.default.as_deref()),
+        ) else {
+            continue;
+        };
+
+        groups
+            .entry(group)
+            .or_default()
+            .entry(value)
+            .or_default()
+            .push(&var.key);
+    }
+
+    let failures = groups
+        .iter()
+        .flat_map(|(group, values)| {
+            values
+                .values()
+                .filter(|keys| keys.len() > 1)
+                .map(move |keys| match group.is_empty() {
+                    true => keys.join(" = "),
+                    false => format!("{} in '{}'", keys.join(" = "), group),
+                })
+        })
+        .collect::<Vec<_>>();
+
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Keys marked @unique share a value: {}",
+            failures.join(", ")
+        )),
+    }
+}
+
+/// Describes the keys with a `@max-age` whose fetched value last changed longer ago than
+/// that at `now`, enforcing rotation where values are used
+pub fn stale_keys(variables: &Variables, now: SystemTime) -> Vec<String> {
+    variables
+        .iter()
+        .filter_map(|var| {
+            let max_age = var.max_age?;
+            let changed_at = var.provenance.as_ref()?.changed_at?;
+
+            let age = now.duration_since(changed_at).unwrap_or_default();
+            (age > max_age).then(|| {
+                format!(
+                    "{} (changed {}, @max-age {})",
+                    var.key,
+                    humantime::format_rfc3339_seconds(changed_at),
+                    humantime::format_duration(max_age)
+                )
+            })
+        })
+        .collect()
+}
+
+/// Keys with a `@max-age` whose value came from a provider that doesn't report when it
+/// changed, so its age can't be checked
+pub fn undated_keys(variables: &Variables) -> Vec<&str> {
+    variables
+        .iter()
+        .filter(|var| var.max_age.is_some())
+        .filter(|var| {
+            var.provenance
+                .as_ref()
+                .is_some_and(|provenance| provenance.changed_at.is_none())
+        })
+        .map(|var| var.key.as_str())
+        .collect()
+}
+
+/// `${NAME}` references in values, as expanded by tools such as Docker Compose
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{([A-Za-z0-9_.-]+)\}").expect("should be a valid regex"));
+
+/// Names of the keys `value` references with `${NAME}`
+pub fn references(value: &str) -> impl Iterator<Item = &str> {
+    REFERENCE
+        .captures_iter(value)
+        .map(|captures| captures.extract::<1>().1[0])
+}
+
+/// Returns `KEY -> NAME` for every default value that references a `NAME` the spec
+/// doesn't declare
+pub fn undeclared_references(variables: &Variables) -> Vec<String> {
+    variables
+        .iter()
+        .flat_map(|var| {
+            let default = var.default.as_deref().unwrap_or_default();
+
+            references(default)
+                .filter(|name| variables.find_by_key(name).is_none())
+                .map(|name| format!("{} -> {}", var.key, name))
+        })
+        .unique()
+        .collect()
+}
+
+/// Rules for key names imposed by where the output ends up
+#[derive(clap::ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NameRules {
+    /// POSIX shell variables: letters, digits and `_`, not starting with a digit
+    Posix,
+    /// Docker `--env-file` and Compose: anything but `=`, whitespace and control characters
+    Docker,
+    /// Kubernetes Secret and ConfigMap keys: letters, digits, `-`, `_` and `.`, at most 253
+    /// characters
+    Kubernetes,
+}
+
+impl NameRules {
+    pub fn is_valid(&self, key: &str) -> bool {
+        !key.is_empty() && key == self.sanitize(key)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NameRules::Posix => "posix",
+            NameRules::Docker => "docker",
+            NameRules::Kubernetes => "kubernetes",
+        }
+    }
+
+    /// Replaces characters that aren't allowed with `_`, e.g. `my-key` becomes `my_key`
+    /// under POSIX rules
+    pub fn sanitize(&self, key: &str) -> String {
+        let mut sanitized = key
+            .chars()
+            .map(|c| match self.allows(c) {
+                true => c,
+                false => '_',
+            })
+            .collect::<String>();
+
+        match self {
+            NameRules::Posix if sanitized.starts_with(|c: char| c.is_ascii_digit()) => {
+                sanitized.insert(0, '_')
+            }
+            NameRules::Kubernetes if sanitized == "." || sanitized == ".." => {
+                sanitized = sanitized.replace('.', "_")
+            }
+            NameRules::Kubernetes => sanitized.truncate(253),
+            _ => {}
+        }
+
+        sanitized
+    }
+
+    fn allows(&self, c: char) -> bool {
+        match self {
+            NameRules::Posix => c.is_ascii_alphanumeric() || c == '_',
+            NameRules::Docker => c != '=' && !c.is_whitespace() && !c.is_control(),
+            NameRules::Kubernetes => c.is_ascii_alphanumeric() || "-_.".contains(c),
+        }
+    }
+}
+
+/// Returns `KEY (try SUGGESTION)` for every key that breaks `rules`
+pub fn invalid_names(variables: &Variables, rules: NameRules) -> Vec<String> {
+    variables
+        .iter()
+        .filter(|var| !rules.is_valid(&var.key))
+        .map(|var| format!("{} (try {})", var.key, rules.sanitize(&var.key)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::variable::{Provenance, Variable};
+
+    use super::*;
+
+    #[test]
+    fn test_parses_rules() {
+        assert_eq!(
+            "min-length:32".parse::<Validation>().unwrap(),
+            Validation::MinLength(32)
+        );
+        assert_eq!(
+            "regex:^postgres://"
+                .parse::<Validation>()
+                .unwrap()
+                .to_string(),
+            "regex:^postgres://"
+        );
+        assert!("regex:(".parse::<Validation>().is_err());
+        assert!("min-length:x".parse::<Validation>().is_err());
+        assert_eq!(
+            "length:3".parse::<Validation>().unwrap_err().to_string(),
+            "Unknown rule 'length:3', expected `regex:<pattern>` or `min-length:<n>`"
+        );
+    }
+
+    #[test]
+    fn test_validates_values() {
+        let variables: Variables = vec![
+            Variable {
+                key: "DATABASE_URL".to_string(),
+                value: Some("mysql://secret".to_string()),
+                validations: vec!["regex:^postgres://".parse().unwrap()],
+                ..Default::default()
+            },
+            Variable {
+                key: "API_KEY".to_string(),
+                value: Some("0123456789".to_string()),
+                validations: vec![Validation::MinLength(10)],
+                ..Default::default()
+            },
+            Variable {
+                key: "TOKEN".to_string(),
+                default: Some("short".to_string()),
+                validations: vec![Validation::MinLength(32)],
+                ..Default::default()
+            },
+            Variable {
+                key: "MISSING".to_string(),
+                validations: vec![Validation::MinLength(32)],
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let err = validate(&variables).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "Invalid values: DATABASE_URL fails regex:^postgres://, TOKEN fails min-length:32"
+        );
+        assert!(!err.contains("secret"));
+    }
+
+    #[test]
+    fn test_finds_values_past_max_age() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = UNIX_EPOCH + 100 * day;
+        let fetched = |key: &str, changed_at: Option<SystemTime>| Variable {
+            key: key.to_string(),
+            value: Some("value".to_string()),
+            max_age: Some(30 * day),
+            provenance: Some(Provenance {
+                provider: "aws-sm".to_string(),
+                id: key.to_lowercase(),
+                version: None,
+                fetched_at: now,
+                expires_at: None,
+                changed_at,
+            }),
+            ..Default::default()
+        };
+
+        let variables: Variables = vec![
+            fetched("ROTATED", Some(now - 10 * day)),
+            fetched("STALE", Some(UNIX_EPOCH)),
+            fetched("UNDATED", None),
+            Variable {
+                key: "OVERRIDDEN".to_string(),
+                value: Some("value".to_string()),
+                max_age: Some(day),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        assert_eq!(
+            stale_keys(&variables, now),
+            ["STALE (changed 1970-01-01T00:00:00Z, @max-age 30days)"]
+        );
+        assert_eq!(undated_keys(&variables), ["UNDATED"]);
+    }
+
+    #[test]
+    fn test_checks_unique_values() {
+        let var = |key: &str, value: &str, group: &str| Variable {
+            key: key.to_string(),
+            value: Some(value.to_string()),
+            unique: Some(group.to_string()),
+            ..Default::default()
+        };
+
+        let variables: Variables = vec![
+            var("JWT_SECRET", "same", ""),
+            var("COOKIE_SECRET", "same", ""),
+            var("PRIMARY_DB", "db1", "dbs"),
+            var("REPLICA_DB", "db1", "dbs"),
+            var("OTHER_DB", "same", "dbs"),
+        ]
+        .into();
+
+        assert_eq!(
+            check_unique(&variables).unwrap_err().to_string(),
+            "Keys marked @unique share a value: JWT_SECRET = COOKIE_SECRET, PRIMARY_DB = REPLICA_DB in 'dbs'"
+        );
+
+        let variables: Variables = vec![var("A", "1", ""), var("B", "2", "")].into();
+        assert!(check_unique(&variables).is_ok());
+    }
+
+    #[test]
+    fn test_finds_undeclared_references() {
+        let variables: Variables = vec![
+            Variable {
+                key: "HOST".to_string(),
+                default: Some("localhost".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "URL".to_string(),
+                default: Some("http://${HOST}:${PORT}/$PATH".to_string()),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        assert_eq!(undeclared_references(&variables), vec!["URL -> PORT"]);
+    }
+
+    #[test]
+    fn test_checks_names_against_platform_rules() {
+        assert!(NameRules::Posix.is_valid("_DATABASE_URL2"));
+        assert!(!NameRules::Posix.is_valid("api-key"));
+        assert!(!NameRules::Posix.is_valid("2FA_SECRET"));
+        assert_eq!(NameRules::Posix.sanitize("api.key-v2"), "api_key_v2");
+        assert_eq!(NameRules::Posix.sanitize("2FA_SECRET"), "_2FA_SECRET");
+
+        assert!(NameRules::Docker.is_valid("api-key.v2"));
+        assert_eq!(NameRules::Docker.sanitize("api key"), "api_key");
+
+        assert!(NameRules::Kubernetes.is_valid("tls.crt"));
+        assert!(!NameRules::Kubernetes.is_valid(".."));
+        assert!(!NameRules::Kubernetes.is_valid(&"a".repeat(254)));
+        assert_eq!(NameRules::Kubernetes.sanitize("ca/bundle"), "ca_bundle");
+
+        let variables: Variables = vec![
+            Variable {
+                key: "PORT".to_string(),
+                ..Default::default()
+            },
+            Variable {
+                key: "api-key".to_string(),
+                ..Default::default()
+            },
+        ]
+        .into();
+        assert_eq!(
+            invalid_names(&variables, NameRules::Posix),
+            ["api-key (try api_key)"]
+        );
+    }
+}
@@ -1,11 +1,32 @@
-use std::{ops::Deref, slice::IterMut};
+use std::{
+    fmt,
+    ops::Deref,
+    slice::IterMut,
+    time::{Duration, SystemTime},
+};
 
 use indexmap::IndexMap;
 
-#[derive(Debug, PartialEq, Eq)]
+use crate::{generate::Generator, validate::Validation};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ProviderConfig {
     AwsSecretsManager(String),
     AwsParameterStore(String),
+    /// An external provider, `@plugin <name> <id>`
+    Plugin {
+        name: String,
+        id: String,
+    },
+}
+
+/// Selects a specific version of a secret
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SecretVersion {
+    /// An exact version ID
+    Id(String),
+    /// A staging label such as `AWSPREVIOUS`, or a label for Parameter Store parameters
+    Stage(String),
 }
 
 impl ProviderConfig {
@@ -13,18 +34,194 @@ impl ProviderConfig {
         match self {
             ProviderConfig::AwsSecretsManager(id) => id,
             ProviderConfig::AwsParameterStore(id) => id,
+            ProviderConfig::Plugin { id, .. } => id,
+        }
+    }
+
+    /// Name of the provider as used in directives without the `@`, or the plugin's name
+    pub fn name(&self) -> &str {
+        match self {
+            ProviderConfig::AwsSecretsManager(_) => "aws-sm",
+            ProviderConfig::AwsParameterStore(_) => "aws-ps",
+            ProviderConfig::Plugin { name, .. } => name,
+        }
+    }
+
+    /// The same provider with a different ID, e.g. after placeholder substitution
+    pub fn with_id(&self, id: String) -> Self {
+        match self {
+            ProviderConfig::AwsSecretsManager(_) => ProviderConfig::AwsSecretsManager(id),
+            ProviderConfig::AwsParameterStore(_) => ProviderConfig::AwsParameterStore(id),
+            ProviderConfig::Plugin { name, .. } => ProviderConfig::Plugin {
+                name: name.clone(),
+                id,
+            },
+        }
+    }
+}
+
+/// Describes where a resolved value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub provider: String,
+    /// Secret ID after placeholder substitution
+    pub id: String,
+    pub version: Option<String>,
+    pub fetched_at: SystemTime,
+    /// When the value should be fetched again, from the entry's `@ttl`
+    pub expires_at: Option<SystemTime>,
+    /// When the value was last changed, for providers that report it
+    pub changed_at: Option<SystemTime>,
+}
+
+impl Provenance {
+    pub fn fetched_at_rfc3339(&self) -> String {
+        humantime::format_rfc3339_seconds(self.fetched_at).to_string()
+    }
+
+    pub fn expires_at_rfc3339(&self) -> Option<String> {
+        self.expires_at
+            .map(|at| humantime::format_rfc3339_seconds(at).to_string())
+    }
+
+    pub fn changed_at_rfc3339(&self) -> Option<String> {
+        self.changed_at
+            .map(|at| humantime::format_rfc3339_seconds(at).to_string())
+    }
+
+    /// Whether the value has outlived its TTL at `now`. Values without a TTL never go stale.
+    pub fn is_stale(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.provider, self.id)?;
+
+        if let Some(version) = &self.version {
+            write!(f, " version={}", version)?;
+        }
+
+        if let Some(changed_at) = self.changed_at_rfc3339() {
+            write!(f, " changed={}", changed_at)?;
         }
+
+        write!(f, " fetched={}", self.fetched_at_rfc3339())?;
+
+        if let Some(expires_at) = self.expires_at_rfc3339() {
+            write!(f, " expires={}", expires_at)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a resolved entry's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Fetched from a provider
+    Secret {
+        provider: String,
+        id: String,
+        version: Option<String>,
+        /// When the value was last changed, for providers that report it
+        changed_at: Option<SystemTime>,
+    },
+    /// The spec's default value
+    Default,
+    /// Supplied outside the spec, e.g. by `--var`, an existing output file or a prompt
+    Override,
+}
+
+/// A key with its final value and where that value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedEntry {
+    pub key: String,
+    pub value: String,
+    pub source: Source,
+}
+
+/// List of [`ResolvedEntry`]s in spec order
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ResolvedEntries(Vec<ResolvedEntry>);
+
+impl ResolvedEntries {
+    pub fn find_by_key(&self, key: &str) -> Option<&ResolvedEntry> {
+        self.0.iter().find(|e| e.key == key)
+    }
+
+    /// Map view of the entries, from key to value
+    pub fn to_map(&self) -> IndexMap<&str, &str> {
+        self.0
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+            .collect()
+    }
+}
+
+impl Deref for ResolvedEntries {
+    type Target = [ResolvedEntry];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoIterator for ResolvedEntries {
+    type Item = ResolvedEntry;
+    type IntoIter = std::vec::IntoIter<ResolvedEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
     }
 }
 
 /// Represents a single environment variable binding
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Variable {
     pub key: String,
     pub required: bool,
     pub default: Option<String>,
     pub value: Option<String>,
     pub provider_config: Option<ProviderConfig>,
+    pub provenance: Option<Provenance>,
+    /// Tags attached to the remote secret when it is created
+    pub tags: IndexMap<String, String>,
+    /// How long a fetched value stays fresh, set with `@ttl`
+    pub ttl: Option<Duration>,
+    /// Longest since the secret last changed before its value is too old to use, set
+    /// with `@max-age`
+    pub max_age: Option<Duration>,
+    /// How to generate the secret when it doesn't exist, set with `@generate`
+    pub generate: Option<Generator>,
+    /// The same secret kept in the other AWS service, set with `@mirror`. Fetched when
+    /// the first service tried has no value.
+    pub mirror: Option<ProviderConfig>,
+    /// Field of a JSON secret whose value is used instead of the whole secret, set with
+    /// `@aws-sm <id>#<field>`
+    pub json_key: Option<String>,
+    /// Version of the secret fetched instead of the current one, set with
+    /// `@version-stage=<STAGE>` or `@version-id=<id>`
+    pub version: Option<SecretVersion>,
+    /// Parameter Store path whose parameters each become a key prefixed with this one,
+    /// set with `@aws-ps-path`
+    pub ps_path: Option<String>,
+    /// What the key is for, set with `@desc`
+    pub description: Option<String>,
+    /// Set with `@deprecated`, holds the note following it which may be empty
+    pub deprecated: Option<String>,
+    /// Constraints on the value, set with `@validate` and `@min-length`
+    pub validations: Vec<Validation>,
+    /// Set with `@unique`, holds the group whose values must differ, empty for the
+    /// default group
+    pub unique: Option<String>,
+    /// Names of the output formats the key is left out of, set with `@exclude-from`
+    pub exclude_from: Vec<String>,
+    /// Set with `@no-export`, leaves the key out of every output format
+    pub no_export: bool,
+    /// Set with `@seal`, the value is encrypted before it's written
+    pub seal: bool,
 }
 
 impl Variable {
@@ -40,10 +237,44 @@ impl Variable {
         self.default = None;
     }
 
+    pub fn drop_provenance(&mut self) {
+        self.provenance = None;
+    }
+
+    /// Whether the key belongs in output rendered with the named format
+    pub fn is_exported_to(&self, format: &str) -> bool {
+        !self.no_export && !self.exclude_from.iter().any(|excluded| excluded == format)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.value.is_none() && self.default.is_none()
     }
 
+    /// Converts into a [`ResolvedEntry`], or `None` if there's no value. Values without
+    /// provenance didn't come from a provider and are treated as overrides.
+    pub fn into_resolved(self) -> Option<ResolvedEntry> {
+        let (value, source) = match (self.value, self.provenance, self.default) {
+            (Some(value), Some(provenance), _) => (
+                value,
+                Source::Secret {
+                    provider: provenance.provider,
+                    id: provenance.id,
+                    version: provenance.version,
+                    changed_at: provenance.changed_at,
+                },
+            ),
+            (Some(value), None, _) => (value, Source::Override),
+            (None, _, Some(default)) => (default, Source::Default),
+            (None, _, None) => return None,
+        };
+
+        Some(ResolvedEntry {
+            key: self.key,
+            value,
+            source,
+        })
+    }
+
     pub fn merge(&mut self, mut other: Variable) {
         if self.key != other.key {
             panic!("Cannot merge variables with different keys");
@@ -59,16 +290,52 @@ impl Variable {
 
         if other.value.is_some() {
             self.value = other.value.take();
+            self.provenance = other.provenance.take();
         }
 
         if other.provider_config.is_some() {
             self.provider_config = other.provider_config.take();
         }
+
+        if !other.tags.is_empty() {
+            self.tags = other.tags;
+        }
+
+        if other.ttl.is_some() {
+            self.ttl = other.ttl;
+        }
+
+        if other.generate.is_some() {
+            self.generate = other.generate;
+        }
+
+        if other.description.is_some() {
+            self.description = other.description;
+        }
+
+        if other.deprecated.is_some() {
+            self.deprecated = other.deprecated;
+        }
+
+        if !other.validations.is_empty() {
+            self.validations = other.validations;
+        }
+
+        if other.unique.is_some() {
+            self.unique = other.unique;
+        }
+
+        if !other.exclude_from.is_empty() {
+            self.exclude_from = other.exclude_from;
+        }
+
+        self.no_export |= other.no_export;
+        self.seal |= other.seal;
     }
 }
 
 /// List of [`Variable`]s.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Variables(Vec<Variable>);
 
 impl Variables {
@@ -94,6 +361,21 @@ impl Variables {
         self.0.iter_mut()
     }
 
+    pub fn retain(&mut self, f: impl FnMut(&Variable) -> bool) {
+        self.0.retain(f);
+    }
+
+    pub fn sort_by_key(&mut self) {
+        self.0.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+
+    /// Whether any variable is backed by a provider
+    pub fn has_secrets(&self) -> bool {
+        self.0
+            .iter()
+            .any(|v| v.provider_config.is_some() || v.ps_path.is_some())
+    }
+
     pub fn drop_empty(&mut self) {
         self.0.retain(|v| !v.is_empty());
     }
@@ -110,6 +392,17 @@ impl Variables {
     }
 }
 
+impl From<Variables> for ResolvedEntries {
+    fn from(value: Variables) -> Self {
+        ResolvedEntries(
+            value
+                .into_iter()
+                .filter_map(Variable::into_resolved)
+                .collect(),
+        )
+    }
+}
+
 impl Into<IndexMap<String, String>> for Variables {
     fn into(self) -> IndexMap<String, String> {
         self.into_iter()
@@ -155,3 +448,96 @@ impl IntoIterator for Variables {
         self.0.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    #[test]
+    fn test_resolved_entries_sources() {
+        let variables: Variables = vec![
+            Variable {
+                key: "SECRET".to_string(),
+                default: Some("default".to_string()),
+                value: Some("secret".to_string()),
+                provenance: Some(Provenance {
+                    provider: "aws-sm".to_string(),
+                    id: "foo/bar".to_string(),
+                    version: Some("v1".to_string()),
+                    fetched_at: UNIX_EPOCH,
+                    expires_at: None,
+                    changed_at: Some(UNIX_EPOCH),
+                }),
+                ..Default::default()
+            },
+            Variable {
+                key: "LITERAL".to_string(),
+                default: Some("default".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "OVERRIDDEN".to_string(),
+                value: Some("override".to_string()),
+                ..Default::default()
+            },
+            Variable {
+                key: "MISSING".to_string(),
+                ..Default::default()
+            },
+        ]
+        .into();
+
+        let entries = ResolvedEntries::from(variables);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries.find_by_key("SECRET").unwrap().source,
+            Source::Secret {
+                provider: "aws-sm".to_string(),
+                id: "foo/bar".to_string(),
+                version: Some("v1".to_string()),
+                changed_at: Some(UNIX_EPOCH),
+            }
+        );
+        assert_eq!(
+            entries.find_by_key("LITERAL").unwrap().source,
+            Source::Default
+        );
+        assert_eq!(
+            entries.find_by_key("OVERRIDDEN").unwrap().source,
+            Source::Override
+        );
+        assert_eq!(
+            entries.to_map().into_iter().collect::<Vec<_>>(),
+            [
+                ("SECRET", "secret"),
+                ("LITERAL", "default"),
+                ("OVERRIDDEN", "override")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_provenance_staleness() {
+        let provenance = Provenance {
+            provider: "aws-sm".to_string(),
+            id: "db/creds".to_string(),
+            version: None,
+            fetched_at: UNIX_EPOCH,
+            expires_at: Some(UNIX_EPOCH + Duration::from_secs(300)),
+            changed_at: None,
+        };
+
+        assert!(!provenance.is_stale(UNIX_EPOCH + Duration::from_secs(299)));
+        assert!(provenance.is_stale(UNIX_EPOCH + Duration::from_secs(300)));
+        assert!(
+            !Provenance {
+                expires_at: None,
+                ..provenance
+            }
+            .is_stale(SystemTime::now())
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Comparison of a live environment against the values the spec resolves to, to tell
+//! whether a process picked up rotated secrets
+
+use std::fs;
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+
+use crate::term::format_table;
+
+/// How a key in a live environment compares to the spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drift {
+    Current,
+    /// The environment has a different value than the spec resolves to
+    Stale,
+    /// The key isn't set in the environment
+    Missing,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct KeyDrift {
+    pub key: String,
+    pub drift: Drift,
+}
+
+/// Reads the environment a process was started with from `/proc/<pid>/environ`, only
+/// available on Linux
+pub fn process_env(pid: u32) -> Result<IndexMap<String, String>> {
+    let path = format!("/proc/{}/environ", pid);
+    let environ = fs::read(&path)
+        .with_context(|| format!("Failed to read the environment of process {}", pid))?;
+
+    Ok(parse_environ(&environ))
+}
+
+/// Parses the NUL separated `KEY=value` pairs of an `environ` file
+pub fn parse_environ(environ: &[u8]) -> IndexMap<String, String> {
+    environ
+        .split(|byte| *byte == 0)
+        .filter_map(|pair| {
+            let pair = String::from_utf8_lossy(pair);
+            let (key, value) = pair.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Compares each expected key with the live environment, keys only present in the
+/// environment are ignored
+pub fn compare(
+    expected: &IndexMap<String, String>,
+    actual: &IndexMap<String, String>,
+) -> Vec<KeyDrift> {
+    expected
+        .iter()
+        .map(|(key, value)| KeyDrift {
+            key: key.clone(),
+            drift: match actual.get(key) {
+                Some(actual) if actual == value => Drift::Current,
+                Some(_) => Drift::Stale,
+                None => Drift::Missing,
+            },
+        })
+        .collect()
+}
+
+/// Formats the drifts as a table, values are never shown
+pub fn format_report(drifts: &[KeyDrift]) -> String {
+    let rows = drifts
+        .iter()
+        .map(|drift| {
+            let status = match drift.drift {
+                Drift::Current => "ok",
+                Drift::Stale => "STALE",
+                Drift::Missing => "MISSING",
+            };
+
+            [drift.key.clone(), status.to_string()]
+        })
+        .collect::<Vec<_>>();
+
+    format_table(["KEY", "STATUS"], &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_environ() {
+        let env = parse_environ(b"PATH=/usr/bin\0TOKEN=a=b\0EMPTY=\0INVALID\0");
+
+        assert_eq!(env.len(), 3);
+        assert_eq!(env["PATH"], "/usr/bin");
+        assert_eq!(env["TOKEN"], "a=b");
+        assert_eq!(env["EMPTY"], "");
+    }
+
+    #[test]
+    fn test_compares_environments() {
+        let expected = IndexMap::from([
+            ("PORT".to_string(), "8080".to_string()),
+            ("TOKEN".to_string(), "rotated".to_string()),
+            ("REGION".to_string(), "us-east-1".to_string()),
+        ]);
+        let actual = IndexMap::from([
+            ("PORT".to_string(), "8080".to_string()),
+            ("TOKEN".to_string(), "original".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ]);
+
+        let drifts = compare(&expected, &actual);
+
+        assert_eq!(
+            drifts.iter().map(|drift| drift.drift).collect::<Vec<_>>(),
+            [Drift::Current, Drift::Stale, Drift::Missing]
+        );
+        assert_eq!(
+            format_report(&drifts),
+            "KEY     STATUS\nPORT    ok\nTOKEN   STALE\nREGION  MISSING\n"
+        );
+    }
+}
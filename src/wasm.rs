@@ -0,0 +1,53 @@
+//! `wasm-bindgen` wrappers around the parser and formatters, allowing specs to be
+//! validated and previewed client-side with the same grammar as the CLI
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    output::{EnvOutput, JsonOutput, Output, ShellOutput, spec_entries},
+    parser::{Duplicates, EnvParser},
+    variable::Variables,
+};
+
+fn parse_spec(input: &str, duplicates: Option<String>) -> Result<Variables, JsError> {
+    let duplicates = match duplicates.as_deref() {
+        None | Some("last-wins") => Duplicates::LastWins,
+        Some("first-wins") => Duplicates::FirstWins,
+        Some("error") => Duplicates::Error,
+        Some(other) => {
+            return Err(JsError::new(&format!(
+                "Unknown duplicates policy: {}",
+                other
+            )));
+        }
+    };
+
+    EnvParser::parse_variables_with(input, duplicates).map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// Parses a spec, returning its entries as a JSON array of
+/// `{key, required, default, provider, id, tags, ttl, description, deprecated}` objects
+#[wasm_bindgen]
+pub fn parse(input: &str, duplicates: Option<String>) -> Result<String, JsError> {
+    let variables = parse_spec(input, duplicates)?;
+    Ok(spec_entries(&variables).to_string())
+}
+
+/// Renders a spec in the given format (`env`, `shell` or `json`) using its default values,
+/// as the CLI would without access to any provider
+#[wasm_bindgen]
+pub fn preview(input: &str, format: &str) -> Result<String, JsError> {
+    let outputter: Box<dyn Output> = match format {
+        "env" => Box::new(EnvOutput),
+        "shell" => Box::new(ShellOutput),
+        "json" => Box::new(JsonOutput),
+        other => return Err(JsError::new(&format!("Unsupported format: {}", other))),
+    };
+
+    let mut variables = parse_spec(input, None)?;
+    variables.drop_empty();
+
+    outputter
+        .format(variables)
+        .map_err(|err| JsError::new(&err.to_string()))
+}